@@ -0,0 +1,32 @@
+#ifndef WILD_H
+#define WILD_H
+
+/* Generated by cbindgen from src/ffi.rs. Don't edit it by hand. */
+
+#include <stdint.h>
+
+/**
+ * Splits and glob-expands a UTF-16 command line, like `wild::args_os()` does with the command line of the current process.
+ *
+ * Returns an array of `*argc` NUL-terminated arguments followed by a null pointer, like `argv` of `main`.
+ * The array has to be freed with `wild_free_argv()`. Returns null if `command_line` is null.
+ *
+ * The program name, if any, is the first argument. Like in `wild::args_os()`, it's split by its own rules and never expanded.
+ * Outside of Windows the arguments are only split.
+ *
+ * # Safety
+ *
+ * `command_line` must be null or a NUL-terminated string, and `argc` must be null or writable.
+ */
+uint16_t **wild_expand_command_line(const uint16_t *command_line, int *argc);
+
+/**
+ * Frees an array returned by `wild_expand_command_line()`. Does nothing if `argv` is null.
+ *
+ * # Safety
+ *
+ * `argv` must have been returned by `wild_expand_command_line()`, and not freed already.
+ */
+void wild_free_argv(uint16_t **argv);
+
+#endif /* WILD_H */
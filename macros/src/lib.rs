@@ -0,0 +1,35 @@
+//! The `#[wild::main]` attribute. Use it through the `macros` feature of the `wild` crate.
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Error, FnArg, ItemFn};
+
+/// Makes `main` take glob-expanded arguments, e.g. `fn main(args: Vec<String>)`, or without arguments,
+/// expands them before `main` runs, for later calls to `wild::args()`.
+#[proc_macro_attribute]
+pub fn main(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return Error::new(proc_macro2::TokenStream::from(attr).span(), "#[wild::main] takes no options, use wild::set_config() in main")
+            .to_compile_error().into();
+    }
+    let mut main = parse_macro_input!(item as ItemFn);
+    let inputs = std::mem::take(&mut main.sig.inputs);
+    let mut inputs = inputs.into_iter();
+    let init = match (inputs.next(), inputs.next()) {
+        (None, _) => quote_spanned! {main.sig.ident.span()=> ::wild::preload(); },
+        (Some(FnArg::Typed(arg)), None) => {
+            let (pat, ty) = (&arg.pat, &arg.ty);
+            quote_spanned! {arg.span()=> let #pat: #ty = ::wild::MainArgs::main_args(); }
+        },
+        (Some(arg), _) => {
+            return Error::new(arg.span(), "main can take only the arguments, e.g. `args: Vec<String>`").to_compile_error().into();
+        },
+    };
+    main.block.stmts.insert(0, syn::parse_quote!(#init));
+    quote::quote!(#main).into()
+}
@@ -0,0 +1,55 @@
+use argh::TopLevelCommand;
+use std::path::Path;
+use std::process;
+
+/// Same as [`argh::from_env()`](https://docs.rs/argh/latest/argh/fn.from_env.html), but parses glob-expanded `wild::args_os()`.
+///
+/// argh accepts only Unicode strings, so if any argument (or a file it matched) is not valid Unicode,
+/// this prints an error naming it and exits, the same way `argh::from_env()` does.
+/// See `from_env_lossy()` for an alternative.
+///
+/// Requires the `argh` feature.
+pub fn from_env<T: TopLevelCommand>() -> T {
+    let strings = ::args_os()
+        .map(|s| s.into_string())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|arg| {
+            eprintln!("Invalid utf8: {}", arg.to_string_lossy());
+            process::exit(1)
+        });
+    from_strings(&strings)
+}
+
+/// Same as `wild::from_env()`, but arguments that aren't valid Unicode are converted lossily
+/// (with `U+FFFD` replacement characters) instead of exiting.
+///
+/// Requires the `argh` feature.
+pub fn from_env_lossy<T: TopLevelCommand>() -> T {
+    let strings: Vec<_> = ::args_os()
+        .map(|s| s.to_string_lossy().into_owned())
+        .collect();
+    from_strings(&strings)
+}
+
+/// Splits off the program name and exits on `--help` or errors, like argh does.
+fn from_strings<T: TopLevelCommand>(strings: &[String]) -> T {
+    if strings.is_empty() {
+        eprintln!("No program name, argv is empty");
+        process::exit(1)
+    }
+
+    let cmd = Path::new(&strings[0]).file_name().and_then(|s| s.to_str()).unwrap_or(&strings[0]);
+    let strs: Vec<&str> = strings.iter().map(|s| s.as_str()).collect();
+    T::from_args(&[cmd], &strs[1..]).unwrap_or_else(|early_exit| {
+        process::exit(match early_exit.status {
+            Ok(()) => {
+                println!("{}", early_exit.output);
+                0
+            },
+            Err(()) => {
+                eprintln!("{}\nRun {} --help for more information.", early_exit.output, cmd);
+                1
+            },
+        })
+    })
+}
@@ -1,68 +1,694 @@
 use globiter::*;
+use std::collections::{HashSet, VecDeque};
+use std::ops::RangeBounds;
 use std::ffi::OsString;
-use glob;
+use std::path::{is_separator, Path, PathBuf, MAIN_SEPARATOR};
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::vec;
+use error::{Error, TooManyMatches, UnreadableDir};
+use config::{Config, DeviceNames, EndOfOptions, Engine, OptionValues, PathForm, Separators};
+use iter::{Expansion, Origin};
+use matcher::CustomMatcher;
+use vfs::VfsMetadata;
+#[cfg(test)]
+use vfs::MemoryFs;
+#[cfg(test)]
+use tempdir::TempDir;
+use walk::{DirCache, Found};
+use exclude::Exclusions;
+
+/// An argument, whether it's a file system match, and its metadata if it was learned while matching
+pub(crate) type ArgEntry = (OsString, Origin, Option<VfsMetadata>);
 
 /// Windows replacement for `std::env::Args`
+///
+/// `I` is the source of parsed arguments, normally `GlobArgs` parsing the command line.
+///
+/// Matches of each argument are buffered, so that the iterator can be cloned.
+/// Once the source runs out, it's dropped, so the iterator is fused.
+#[derive(Debug, Clone)]
 #[cfg_attr(test, allow(dead_code))]
-pub(crate) struct Args {
-    pub(crate) args: Option<GlobArgs<'static>>,
-    pub(crate) current_arg_globs: Option<glob::Paths>,
+pub(crate) struct Args<I = CommandLine> {
+    pub(crate) args: Option<I>,
+    pub(crate) config: Config,
+    /// Remaining results of the argument being expanded
+    pub(crate) current: vec::IntoIter<ArgEntry>,
+    /// `--` has been seen
+    pub(crate) after_end_of_options: bool,
+    pub(crate) cache: DirCache,
+    /// Matches returned so far, if they have to be unique
+    pub(crate) seen: HashSet<PathBuf>,
+    /// The kill switch was on, or a Unix shell has already done the expansion, so all arguments are literal
+    pub(crate) disabled: bool,
+    /// With `Config::negated_args`, the rest of the arguments, planned ahead to find all exclusions
+    pub(crate) queued: Option<VecDeque<Planned>>,
+    /// Index of the next argument in the source, for `Config::expand_if`
+    pub(crate) index: usize,
+    /// Errors of the arguments whose matches were cut to `Config::max_results` by the infallible iterators
+    pub(crate) truncated: Vec<TooManyMatches>,
 }
 
-fn first_non_error<T,E,I>(iter: &mut I) -> Option<T> where I: Iterator<Item=Result<T,E>> {
-    loop {
-        match iter.next() {
-            Some(Ok(item)) => return Some(item),
-            None => return None,
-            Some(Err(_)) => {},
+impl<I: Iterator<Item = Arg>> Args<I> {
+    pub(crate) fn new(args: Option<I>, config: Config) -> Self {
+        Self {
+            args,
+            current: Vec::new().into_iter(),
+            truncated: Vec::new(),
+            after_end_of_options: false,
+            cache: DirCache::new(&config),
+            seen: HashSet::new(),
+            queued: None,
+            index: 0,
+            disabled: is_disabled(&config),
+            config,
         }
     }
 }
 
-impl Iterator for Args {
-    type Item = OsString;
+impl Args<CommandLine> {
+    /// Drops the program name without expanding it. Must be called before any argument is taken.
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn skip_program_name(&mut self) {
+        if let Some(ref mut args) = self.args {
+            args.skip_program_name();
+            self.index = 1;
+        }
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let glob_options = glob::MatchOptions { case_sensitive: false, ..Default::default() };
-        match self.current_arg_globs.as_mut().and_then(first_non_error) {
-            Some(path) => Some(path.into_os_string()),
-            None => match self.args {
-                Some(ref mut args) => match args.next() {
-                    // lossy: https://github.com/rust-lang-nursery/glob/issues/23
-                    Some(arg) => match glob::glob_with(&arg.pattern.to_string_lossy(), &glob_options) {
-                        Ok(mut glob_iter) => {
-                            let first_glob = first_non_error(&mut glob_iter);
-                            self.current_arg_globs = Some(glob_iter);
-                            match first_glob {
-                                Some(path) => Some(path.into_os_string()),
-                                None => {
-                                    // non-matching patterns are passed as regular strings
-                                    self.current_arg_globs = None;
-                                    Some(arg.text)
-                                },
-                            }
-                        },
-                        Err(_) => {
-                            // Invalid patterns are passed as regular strings
-                            Some(arg.text)
-                        },
+impl Args<CommandLine> {
+    /// Arguments from `expand_all()`, which don't touch the file system again
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn expanded(expanded: Vec<Planned>, config: Config) -> Self {
+        let mut args = Self::new(None, config);
+        args.queued = Some(expanded.into());
+        args
+    }
+}
+
+/// The kill switch is on, or a Unix shell has already done the expansion
+fn is_disabled(config: &Config) -> bool {
+    ::config::expansion_disabled() || (config.detect_unix_shell &&
+        ::config::launched_from_unix_shell(::std::env::var_os("MSYSTEM"), ::std::env::var_os("SHELL")))
+}
+
+/// Expands a single pattern for `wild::glob_os()`, as if it was an argument without quotes.
+///
+/// Option values and `!pattern` aren't special in it. All errors are returned together after the paths.
+#[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+pub(crate) fn glob(pattern: OsString, config: &Config) -> Vec<Result<PathBuf, Error>> {
+    let arg = Arg::from(pattern);
+    if is_disabled(config) {
+        return vec![Ok(PathBuf::from(arg.into_text()))];
+    }
+    let mut cache = DirCache::new(config);
+    cache.keep_errors();
+    let text = arg.text().into_owned();
+    let mut error = Error::default();
+    let mut results: Vec<_> = match (Planned::Expand { arg, option: None, value: None }).expand(config, &cache) {
+        Ok(expanded) if expanded.matches.is_empty() => vec![Ok(PathBuf::from(expanded.original))],
+        Ok(expanded) => expanded.matches.into_iter().map(Ok).collect(),
+        Err(err) => {
+            error.too_many_matches.push(err);
+            Vec::new()
+        },
+    };
+    error.unreadable_dirs.extend(cache.take_errors().into_iter()
+        .map(|(dir, err)| UnreadableDir { pattern: text.clone(), dir, error: err }));
+    if !error.too_many_matches.is_empty() || !error.unreadable_dirs.is_empty() {
+        results.push(Err(error));
+    }
+    results
+}
+
+fn prefixed<S: Into<OsString>>(prefix: &Option<OsString>, s: S) -> OsString {
+    match *prefix {
+        Some(ref prefix) => {
+            let mut out = prefix.clone();
+            out.push(s.into());
+            out
+        },
+        None => s.into(),
+    }
+}
+
+/// Whether the argument has unquoted wildcards of the configured engine, so it's expanded
+fn is_pattern(config: &Config, arg: &Arg) -> bool {
+    if let Some(ref matcher) = config.matcher {
+        return !arg.quoted_wildcard && matcher.is_pattern(&matcher.translate(&arg.text()));
+    }
+    match config.engine {
+        Engine::Glob => arg.has_metacharacters() || (config.extglob && ::wildcard::has_groups(&arg.pattern)),
+        // Windows wildcards can't be escaped, so a quoted one makes the whole argument literal
+        _ => !arg.quoted_wildcard && ::walk::has_wildcards(&arg.text()),
+    }
+}
+
+/// Paths matching the argument's pattern, using the configured engine
+fn find_matches(config: &Config, cache: &DirCache, arg: &Arg) -> Vec<Found> {
+    if let Some(ref matcher) = config.matcher {
+        let pattern = matcher.translate(&arg.text());
+        if !matcher.is_pattern(&pattern) || arg.quoted_wildcard {
+            return Vec::new();
+        }
+        return ::walk::walk(&pattern, &CustomMatcher(&**matcher), config, cache);
+    }
+    // Plain arguments are passed through without touching the file system
+    if !is_pattern(config, arg) {
+        return Vec::new();
+    }
+    #[cfg(feature = "tracing")]
+    ::tracing::trace!(pattern = %arg.pattern.to_string_lossy(), engine = ?config.engine, "pattern");
+    // `D:*.txt` is relative to the current directory of `D:`, which isn't the root or the current directory
+    #[cfg(windows)]
+    let resolved = ::drive::resolve(arg, ::drive::full_path);
+    #[cfg(windows)]
+    let arg = resolved.as_ref().unwrap_or(arg);
+    #[cfg(windows)]
+    if config.alternate_streams && config.vfs.is_none() {
+        if let Some((file, stream)) = ::streams::split(arg) {
+            return find_streams(config, cache, &file, &stream);
+        }
+    }
+    if config.exact_names {
+        let path = ::walk::in_base_dir(Path::new(&arg.text()), config);
+        if cache.exists(&path) {
+            return vec![Found::new(path)];
+        }
+    }
+    match config.engine {
+        #[cfg(windows)]
+        Engine::Native if config.vfs.is_none() => {
+            if arg.quoted_wildcard {
+                return Vec::new();
+            }
+            ::native::find(&arg.text(), config, cache)
+        },
+        Engine::Dos => {
+            if arg.quoted_wildcard {
+                return Vec::new();
+            }
+            ::dos::find(&arg.text(), config, cache)
+        },
+        // Invalid patterns have no matches, so they're passed as regular strings
+        _ if config.brackets => {
+            let matches = ::globmatch::find(&arg.pattern, config, cache);
+            if !matches.is_empty() {
+                return matches;
+            }
+            // Windows file names often contain brackets, e.g. `[draft] notes*.md`
+            let literal = arg.pattern_with_literal_brackets();
+            if literal == arg.pattern {
+                return matches;
+            }
+            ::globmatch::find(&literal, config, cache)
+        },
+        _ => ::globmatch::find(&arg.pattern_with_literal_brackets(), config, cache),
+    }
+}
+
+/// Expands the file part of `file:stream` like any other pattern, and then lists streams of the matches
+#[cfg(windows)]
+fn find_streams(config: &Config, cache: &DirCache, file: &Arg, stream: &::std::ffi::OsStr) -> Vec<Found> {
+    let mut files = find_matches(config, cache, file);
+    let path = ::walk::in_base_dir(Path::new(&file.text()), config);
+    if files.is_empty() && cache.exists(&path) {
+        files.push(Found::new(path));
+    }
+    files.into_iter()
+        .take_while(|_| !cache.is_cancelled())
+        .flat_map(|found| {
+            let linked = found.linked;
+            let streams = ::streams::find(&found.path, stream).unwrap_or_else(|err| {
+                cache.report(&found.path, &err);
+                Vec::new()
+            });
+            streams.into_iter().map(move |path| Found { path, linked, metadata: None })
+        })
+        .collect()
+}
+
+/// An argument and how it's going to be expanded, decided before touching the file system
+#[derive(Debug, Clone)]
+pub(crate) enum Planned {
+    Literal(Arg),
+    /// `option` is `--name=` if only the `value` part is expanded
+    Expand { arg: Arg, option: Option<OsString>, value: Option<Arg> },
+    /// Expanded already, with the error if its matches were cut to `Config::max_results`
+    Expanded(Box<(Expansion, Option<TooManyMatches>)>),
+}
+
+impl Planned {
+    /// Finds the matches. Plans don't depend on each other, so they can be expanded in any order.
+    pub(crate) fn expand(self, config: &Config, cache: &DirCache) -> Result<Expansion, TooManyMatches> {
+        match self.expand_truncated(config, cache) {
+            (expanded, None) => Ok(expanded),
+            (_, Some(err)) => Err(err),
+        }
+    }
+
+    /// Like `expand`, but with too many matches, keeps the first `Config::max_results` of them, and returns the error alongside
+    pub(crate) fn expand_truncated(self, config: &Config, cache: &DirCache) -> (Expansion, Option<TooManyMatches>) {
+        match self {
+            Planned::Literal(arg) => (Expansion::literal(arg), None),
+            Planned::Expanded(expanded) => *expanded,
+            Planned::Expand { arg, option, value } => {
+                #[cfg(feature = "tracing")]
+                let _span = ::tracing::debug_span!("expand", arg = %arg.text().to_string_lossy()).entered();
+                let pattern = value.as_ref().unwrap_or(&arg);
+                let cache = &if config.on_error.is_some() { cache.for_pattern(&pattern.text()) } else { cache.clone() };
+                let mut found = find_matches(config, cache, pattern);
+                let exclusions = Exclusions::new(config);
+                if !exclusions.is_empty() {
+                    found.retain(|found| !exclusions.is_excluded(&found.path));
+                }
+                if let Some(ref on_no_match) = config.on_no_match {
+                    if found.is_empty() && !cache.is_cancelled() && is_pattern(config, pattern) {
+                        (on_no_match.0)(&pattern.text());
+                    }
+                }
+                #[cfg(feature = "tracing")]
+                ::tracing::debug!(matches = found.len(), "expanded");
+                cache.matched(found.len());
+                let too_many = match config.max_results {
+                    Some(limit) if found.len() > limit => {
+                        #[cfg(feature = "tracing")]
+                        ::tracing::warn!(limit, "too many matches");
+                        found.truncate(limit);
+                        let err = TooManyMatches { pattern: arg.text().into_owned(), limit };
+                        if let Some(ref on_too_many_matches) = config.on_too_many_matches {
+                            (on_too_many_matches.0)(&err);
+                        }
+                        Some(err)
                     },
-                    None => None, // end of args
-                },
-                None => None, // error: no args available at all
+                    _ => None,
+                };
+                let mut matches = Vec::with_capacity(found.len());
+                let mut linked = Vec::with_capacity(found.len());
+                let mut metadata = Vec::with_capacity(found.len());
+                for found in found {
+                    matches.push(found.path);
+                    linked.push(found.linked);
+                    metadata.push(found.metadata);
+                }
+                if config.path_form != PathForm::AsMatched {
+                    for path in &mut matches {
+                        *path = in_form(path, config.path_form);
+                    }
+                }
+                #[cfg(windows)]
+                if config.name_form != ::config::NameForm::AsMatched {
+                    for path in &mut matches {
+                        *path = ::names::in_form(path, config.name_form);
+                    }
+                }
+                if config.separators != Separators::Unchanged {
+                    for path in &mut matches {
+                        *path = with_separators(path, config.separators);
+                    }
+                }
+                if config.long_path_prefix {
+                    for path in &mut matches {
+                        if ::longpath::wide_len(path) >= ::longpath::LEGACY_LIMIT {
+                            *path = ::longpath::extended(path);
+                        }
+                    }
+                }
+                // without `\\?\`, Windows would strip trailing dots and spaces, and open another file
+                #[cfg(windows)]
+                if config.vfs.is_none() {
+                    for path in &mut matches {
+                        if ::longpath::needs_verbatim(path) {
+                            *path = ::longpath::extended(path);
+                        }
+                    }
+                }
+                (Expansion {
+                    was_quoted: arg.quoted,
+                    original: arg.into_text(),
+                    option,
+                    matches,
+                    linked,
+                    metadata,
+                }, too_many)
             },
         }
     }
 }
 
+/// Makes the path absolute or canonical. Paths that can't be resolved are left unchanged.
+fn in_form(path: &Path, form: PathForm) -> PathBuf {
+    match form {
+        PathForm::AsMatched => path.to_owned(),
+        PathForm::Absolute if path.is_absolute() => path.to_owned(),
+        PathForm::Absolute => ::std::env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_owned()),
+        PathForm::Canonical => ::vfs::win32_path(path).canonicalize().unwrap_or_else(|_| path.to_owned()),
+        PathForm::Full => full_path(path).unwrap_or_else(|| path.to_owned()),
+    }
+}
+
+#[cfg(windows)]
+fn full_path(path: &Path) -> Option<PathBuf> {
+    // `GetFullPathNameW` would strip trailing dots and spaces
+    if ::longpath::needs_verbatim(path) {
+        return Some(::longpath::extended(path));
+    }
+    ::drive::full_path(path.as_os_str()).map(PathBuf::from)
+}
+
+/// Absolute path with `.` and `..` removed lexically, which is what `GetFullPathNameW` does on Windows
+#[cfg(not(windows))]
+fn full_path(path: &Path) -> Option<PathBuf> {
+    use std::path::Component;
+    let absolute = if path.is_absolute() { path.to_owned() } else { ::std::env::current_dir().ok()?.join(path) };
+    let mut full = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {},
+            // `..` of the root is the root
+            Component::ParentDir => {
+                full.pop();
+            },
+            _ => full.push(component),
+        }
+    }
+    // `dir*/` keeps the separator
+    if path.as_os_str().as_encoded_bytes().last().is_some_and(|&b| is_separator(char::from(b))) {
+        full.push("");
+    }
+    Some(full)
+}
+
+/// Rewrites all path separators in the given style
+fn with_separators(path: &Path, style: Separators) -> PathBuf {
+    let separator = match style {
+        Separators::Forward => '/',
+        _ => MAIN_SEPARATOR,
+    };
+    let mut buf = [0; 2];
+    let separator = separator.encode_utf16(&mut buf)[0];
+    let wide: Vec<u16> = path.as_os_str().encode_wide()
+        .map(|c| if c < 128 && is_separator(c as u8 as char) { separator } else { c })
+        .collect();
+    PathBuf::from(OsString::from_wide(&wide))
+}
+
+/// Arguments resulting from an expansion, each with a marker whether it's a file system match, and its metadata
+fn expansion_args(expanded: Expansion, option_values: OptionValues) -> Vec<ArgEntry> {
+    let mut linked = expanded.linked.into_iter();
+    let mut metadata = expanded.metadata.into_iter();
+    let mut matches = expanded.matches.into_iter();
+    let first_glob = match matches.next() {
+        Some(path) => path,
+        // non-matching patterns are passed as regular strings
+        None => return vec![(expanded.original, Origin::Literal, None)],
+    };
+    match (expanded.option, option_values) {
+        (Some(mut joined), OptionValues::Join(separator)) => {
+            joined.push(first_glob);
+            let mut buf = [0; 4];
+            for path in matches {
+                joined.push(&*separator.encode_utf8(&mut buf));
+                joined.push(path);
+            }
+            vec![(joined, Origin::Literal, None)]
+        },
+        // results of option values aren't paths
+        (option @ Some(_), _) => Some(first_glob).into_iter().chain(matches)
+            .map(|path| (prefixed(&option, path), Origin::Literal, None))
+            .collect(),
+        (None, _) => Some(first_glob).into_iter().chain(matches)
+            .map(|path| {
+                let origin = if linked.next().unwrap_or(false) { Origin::Linked } else { Origin::Matched };
+                (path.into_os_string(), origin, metadata.next().flatten())
+            })
+            .collect(),
+    }
+}
+
+/// With `Config::device_names`, reports an argument that is a reserved device name, and returns whether it's removed
+fn skips_device_name(config: &Config, arg: &OsString, origin: Origin) -> bool {
+    // option values are literals too, but not paths
+    let is_path = origin != Origin::Literal || !arg.as_encoded_bytes().starts_with(b"-");
+    if config.device_names == DeviceNames::Keep || !is_path || !::devices::is_device_name(arg) {
+        return false;
+    }
+    #[cfg(feature = "tracing")]
+    ::tracing::warn!(arg = %arg.to_string_lossy(), "reserved device name");
+    if let Some(ref on_device_name) = config.on_device_name {
+        (on_device_name.0)(arg);
+    }
+    config.device_names == DeviceNames::Skip
+}
+
+/// With `Config::unique`, whether the path has been matched already
+fn is_duplicate(config: &Config, seen: &mut HashSet<PathBuf>, arg: &OsString, origin: Origin) -> bool {
+    config.unique && origin != Origin::Literal && !seen.insert(PathBuf::from(arg))
+}
+
+/// Whether some arguments, or all matches of a pattern, may be left out, so they can't be counted in advance
+fn removes_args(config: &Config) -> bool {
+    // matches of `unique()` may all be duplicates, and `!pattern` arguments are removed
+    config.unique || config.negated_args || !config.excludes.is_empty() || config.expand_predicate.is_some() ||
+        config.device_names == DeviceNames::Skip
+}
+
+/// With `Config::negated_args`, the pattern of an unquoted `!pattern` argument
+fn negated_pattern(planned: &Planned, config: &Config) -> Option<OsString> {
+    let arg = match *planned {
+        Planned::Expand { ref arg, option: None, .. } if config.negated_args && !arg.quoted => arg,
+        _ => return None,
+    };
+    let text = arg.text();
+    let text = text.as_encoded_bytes();
+    // `!(a|b)` is an extended glob
+    if text.len() < 2 || text[0] != b'!' || (config.extglob && text[1] == b'(') {
+        return None;
+    }
+    // safe, since it's split after an ASCII character
+    Some(unsafe { ::std::ffi::OsStr::from_encoded_bytes_unchecked(&text[1..]) }.to_owned())
+}
+
+impl<I: Iterator<Item = Arg>> Args<I> {
+    /// The next argument to expand. With `Config::negated_args`, all of them are planned first,
+    /// and the negated ones become exclusions of the others.
+    fn next_planned(&mut self) -> Option<Planned> {
+        if self.config.negated_args && self.queued.is_none() {
+            let mut queued = VecDeque::new();
+            while let Some(planned) = self.plan_next() {
+                match negated_pattern(&planned, &self.config) {
+                    Some(pattern) => self.config.excludes.push(pattern),
+                    None => queued.push_back(planned),
+                }
+            }
+            self.queued = Some(queued);
+        }
+        match self.queued {
+            Some(ref mut queued) => queued.pop_front(),
+            None => self.plan_next(),
+        }
+    }
+
+    /// Parses the next argument, and decides whether and how to expand it
+    fn plan_next(&mut self) -> Option<Planned> {
+        let arg = match self.args.as_mut().map(Iterator::next) {
+            Some(Some(arg)) => arg,
+            Some(None) => { // end of args
+                self.args = None;
+                return None;
+            },
+            None => return None, // error: no args available at all, or already ended
+        };
+        let index = self.index;
+        self.index += 1;
+        if !self.after_end_of_options && *arg.text() == *"--" {
+            self.after_end_of_options = true;
+            return Some(Planned::Literal(arg));
+        }
+        let expand = match self.config.end_of_options {
+            EndOfOptions::Ignore => true,
+            EndOfOptions::StopExpansion => !self.after_end_of_options,
+            EndOfOptions::StartExpansion => self.after_end_of_options,
+        };
+        let selected = self.config.expand_range.contains(&index) &&
+            self.config.expand_predicate.as_ref().is_none_or(|predicate| (predicate.0)(index, &arg.text()));
+        if !expand || self.disabled || !selected {
+            return Some(Planned::Literal(arg));
+        }
+        let arg = if self.config.expand_quoted { arg.without_quoting() } else { arg };
+        let (option, value) = match self.config.option_values {
+            _ if self.after_end_of_options => (None, None),
+            OptionValues::Whole => (None, None),
+            _ => match arg.split_option(self.config.slash_options) {
+                Some((prefix, value)) => (Some(prefix), Some(value)),
+                None => (None, None),
+            },
+        };
+        Some(Planned::Expand { arg, option, value })
+    }
+
+    /// Parses the next argument, and expands it with all its matches at once
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn try_next_expanded(&mut self) -> Option<Result<Expansion, TooManyMatches>> {
+        let planned = self.next_planned()?;
+        Some(planned.expand(&self.config, &self.cache))
+    }
+
+    /// One expansion per remaining argument of the source
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn expansions_size_hint(&self) -> (usize, Option<usize>) {
+        let queued = self.queued.as_ref().map_or(0, VecDeque::len);
+        let (min, max) = self.args.as_ref().map_or((0, Some(0)), Iterator::size_hint);
+        (queued + min, max.map(|max| queued + max))
+    }
+
+    /// Like `try_next_expanded`, but with too many matches, has only the first `Config::max_results` of them
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn next_expanded(&mut self) -> Option<Expansion> {
+        let planned = self.next_planned()?;
+        let (expanded, too_many) = planned.expand_truncated(&self.config, &self.cache);
+        self.truncated.extend(too_many);
+        Some(expanded)
+    }
+
+    /// Next argument, whether it's a file system match, and its metadata if it was learned while matching
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn try_next_entry(&mut self) -> Option<Result<ArgEntry, TooManyMatches>> {
+        self.next_entry_with(|args| args.try_next_expanded())
+    }
+
+    /// Like `try_next_entry`, but with too many matches, has only the first `Config::max_results` of them
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn next_entry(&mut self) -> Option<ArgEntry> {
+        self.next_entry_with(|args| args.next_expanded().map(Ok)).map(|arg| arg.unwrap_or_else(|err| unreachable!("{}", err)))
+    }
+
+    fn next_entry_with<F>(&mut self, mut next_expanded: F) -> Option<Result<ArgEntry, TooManyMatches>>
+        where F: FnMut(&mut Self) -> Option<Result<Expansion, TooManyMatches>> {
+        loop {
+            if let Some((arg, origin, metadata)) = self.current.next() {
+                if skips_device_name(&self.config, &arg, origin) || is_duplicate(&self.config, &mut self.seen, &arg, origin) {
+                    continue;
+                }
+                return Some(Ok((arg, origin, metadata)));
+            }
+            let expanded = match next_expanded(self)? {
+                Ok(expanded) => expanded,
+                Err(err) => return Some(Err(err)),
+            };
+            self.current = expansion_args(expanded, self.config.option_values).into_iter();
+        }
+    }
+
+    /// Next argument, and whether it's a file system match
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn try_next_with_origin(&mut self) -> Option<Result<(OsString, Origin), TooManyMatches>> {
+        self.try_next_entry().map(|arg| arg.map(|(arg, origin, _)| (arg, origin)))
+    }
+
+    /// Like `try_next_with_origin`, but with too many matches, has only the first `Config::max_results` of them
+    pub(crate) fn next_with_origin(&mut self) -> Option<(OsString, Origin)> {
+        self.next_entry().map(|(arg, origin, _)| (arg, origin))
+    }
+
+    /// Expands all remaining arguments, collecting all errors instead of stopping at the first one
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn try_collect(mut self) -> Result<Vec<OsString>, Error> {
+        self.cache.keep_errors();
+        let mut error = Error::default();
+        // arguments cut short by `next()`
+        error.too_many_matches.append(&mut self.truncated);
+        let mut args: Vec<OsString> = self.current.by_ref().map(|(arg, _, _)| arg).collect();
+        while let Some(planned) = self.next_planned() {
+            let pattern = match planned {
+                Planned::Literal(ref arg) | Planned::Expand { ref arg, .. } => arg.text().into_owned(),
+                Planned::Expanded(ref expanded) => expanded.0.original.clone(),
+            };
+            match planned.expand(&self.config, &self.cache) {
+                Ok(expanded) => {
+                    let seen = &mut self.seen;
+                    let config = &self.config;
+                    args.extend(expansion_args(expanded, config.option_values).into_iter()
+                        .filter(|&(ref arg, origin, _)| !skips_device_name(config, arg, origin) && !is_duplicate(config, seen, arg, origin))
+                        .map(|(arg, _, _)| arg));
+                },
+                Err(err) => error.too_many_matches.push(err),
+            }
+            error.unreadable_dirs.extend(self.cache.take_errors().into_iter()
+                .map(|(dir, err)| UnreadableDir { pattern: pattern.clone(), dir, error: err }));
+        }
+        if error.too_many_matches.is_empty() && error.unreadable_dirs.is_empty() { Ok(args) } else { Err(error) }
+    }
+
+    /// Expands all remaining arguments, to be iterated by `Args::expanded()` as often as needed
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn expand_all(mut self) -> Vec<Planned> {
+        let mut expanded = Vec::new();
+        while let Some(planned) = self.next_planned() {
+            expanded.push(Planned::Expanded(Box::new(planned.expand_truncated(&self.config, &self.cache))));
+        }
+        expanded
+    }
+
+    /// Expands all remaining arguments, and independent directories of each pattern, in parallel
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn par_expand(mut self) -> Vec<OsString> {
+        use rayon::prelude::*;
+        let mut planned: Vec<Planned> = Vec::new();
+        while let Some(p) = self.next_planned() {
+            planned.push(p);
+        }
+        let mut config = self.config.clone();
+        config.parallel = true;
+        let cache = &self.cache;
+        let expanded: Vec<_> = planned.into_par_iter()
+            .flat_map_iter(|p| {
+                let (expanded, _) = p.expand_truncated(&config, cache);
+                expansion_args(expanded, config.option_values)
+            })
+            .collect();
+        let seen = &mut self.seen;
+        self.current.chain(expanded)
+            .filter(|&(ref arg, origin, _)| !skips_device_name(&config, arg, origin) && !is_duplicate(&config, seen, arg, origin))
+            .map(|(arg, _, _)| arg)
+            .collect()
+    }
+}
+
+impl<I: Iterator<Item = Arg>> Iterator for Args<I> {
+    type Item = OsString;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_origin().map(|(arg, _)| arg)
+    }
+
+    /// Every argument gives at least one, and only the unexpanded ones exactly one, unless a filter removes some of them
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (min, max) = self.expansions_size_hint();
+        let buffered = self.current.len();
+        let max = if self.disabled { max.map(|max| buffered + max) } else { None };
+        if removes_args(&self.config) {
+            return (0, max);
+        }
+        (buffered + min, max)
+    }
+}
+
+#[cfg(test)]
+fn expanded(cmd: &str, config: Config) -> Vec<String> {
+    let cmd = cmd.chars().map(|c| c as u16).collect::<Vec<_>>();
+    let args = GlobArgs::new(&cmd);
+    Args::new(Some(args), config).map(|c| c.to_string_lossy().to_string()).collect()
+}
+
 #[test]
 fn finds_cargo_toml() {
     let cmd = "foo.exe _not_?a?_[f]ilename_ \"_not_?a?_[p]attern_\" Cargo.tom?".chars().map(|c| c as u16).collect::<Vec<_>>();
-    let args = GlobArgs::new(unsafe {::std::mem::transmute(&cmd[..])});
-    let iter = Args {
-        args: Some(args),
-        current_arg_globs: None,
-    };
+    let args = GlobArgs::new(&cmd);
+    let iter = Args::new(Some(args), Config::new());
     let args: Vec<_> = iter.map(|c| c.to_string_lossy().to_string()).collect();
     assert_eq!(4, args.len());
     assert_eq!("foo.exe", &args[0]);
@@ -74,11 +700,8 @@ fn finds_cargo_toml() {
 #[test]
 fn finds_readme_case_insensitive() {
     let cmd = "foo.exe _not_?a?_[f]ilename_ \"_not_?a?_[p]attern_\" read*.MD".chars().map(|c| c as u16).collect::<Vec<_>>();
-    let args = GlobArgs::new(unsafe {::std::mem::transmute(&cmd[..])});
-    let iter = Args {
-        args: Some(args),
-        current_arg_globs: None,
-    };
+    let args = GlobArgs::new(&cmd);
+    let iter = Args::new(Some(args), Config::new());
     let args: Vec<_> = iter.map(|c| c.to_string_lossy().to_string()).collect();
     assert_eq!(4, args.len());
     assert_eq!("foo.exe", &args[0]);
@@ -86,3 +709,349 @@ fn finds_readme_case_insensitive() {
     assert_eq!("_not_?a?_[p]attern_", &args[2]);
     assert_eq!("README.md", &args[3]);
 }
+
+#[test]
+fn expands_option_values() {
+    let cmd = "foo.exe --include=Cargo.tom? \"--x=Cargo.tom?\" --=Cargo.tom? -y=Cargo.tom? /z:Cargo.tom?";
+    assert_eq!(vec!["foo.exe", "--include=Cargo.tom?", "--x=Cargo.tom?", "--=Cargo.tom?", "-y=Cargo.tom?", "/z:Cargo.tom?"], expanded(cmd, Config::new()));
+    assert_eq!(vec!["foo.exe", "--include=Cargo.toml", "--x=Cargo.tom?", "--=Cargo.tom?", "-y=Cargo.tom?", "/z:Cargo.tom?"],
+        expanded(cmd, Config::new().option_values(OptionValues::Repeat)));
+    assert_eq!(vec!["foo.exe", "--include=Cargo.toml", "--x=Cargo.tom?", "--=Cargo.tom?", "-y=Cargo.tom?", "/z:Cargo.toml"],
+        expanded(cmd, Config::new().option_values(OptionValues::Repeat).slash_options(true)));
+
+    let cmd = "foo.exe --src=src/????iter.rs --none=_not_?a?_[f]ilename_";
+    assert_eq!(vec!["foo.exe", "--src=src/argsiter.rs", "--src=src/globiter.rs", "--none=_not_?a?_[f]ilename_"],
+        expanded(cmd, Config::new().option_values(OptionValues::Repeat)));
+    assert_eq!(vec!["foo.exe", "--src=src/argsiter.rs,src/globiter.rs", "--none=_not_?a?_[f]ilename_"],
+        expanded(cmd, Config::new().option_values(OptionValues::Join(','))));
+}
+
+#[test]
+fn end_of_options() {
+    let cmd = "foo.exe Cargo.tom? -- Cargo.tom? -- --x=Cargo.tom?";
+    assert_eq!(vec!["foo.exe", "Cargo.toml", "--", "Cargo.toml", "--", "--x=Cargo.tom?"],
+        expanded(cmd, Config::new().option_values(OptionValues::Repeat)));
+    assert_eq!(vec!["foo.exe", "Cargo.toml", "--", "Cargo.tom?", "--", "--x=Cargo.tom?"],
+        expanded(cmd, Config::new().end_of_options(EndOfOptions::StopExpansion)));
+    assert_eq!(vec!["foo.exe", "Cargo.tom?", "--", "Cargo.toml", "--", "--x=Cargo.tom?"],
+        expanded(cmd, Config::new().end_of_options(EndOfOptions::StartExpansion)));
+}
+
+#[test]
+fn expands_any_source() {
+    let args = vec!["Cargo.tom?", "_not_?a?_[f]ilename_", "--x=Cargo.tom?"].into_iter().map(|s| Arg::from(OsString::from(s)));
+    let args: Vec<_> = Args::new(Some(args), Config::new().option_values(OptionValues::Repeat)).collect();
+    assert_eq!(vec!["Cargo.toml", "_not_?a?_[f]ilename_", "--x=Cargo.toml"], args);
+}
+
+#[test]
+fn clones_mid_expansion() {
+    let args = vec!["foo.exe", "src/????iter.rs", "Cargo.tom?"].into_iter().map(|s| Arg::from(OsString::from(s)));
+    let mut args = Args::new(Some(args), Config::new());
+    assert_eq!(Some(OsString::from("foo.exe")), args.next());
+    assert_eq!(Some(OsString::from("src/argsiter.rs")), args.next());
+    let copy = args.clone();
+    assert_eq!(vec!["src/globiter.rs", "Cargo.toml"], args.collect::<Vec<_>>());
+    assert_eq!(vec!["src/globiter.rs", "Cargo.toml"], copy.collect::<Vec<_>>());
+}
+
+#[test]
+fn size_hint() {
+    let cmd = "foo.exe \"a b\" src/*.rs c\"\"d".encode_utf16().collect::<Vec<_>>();
+    let mut args = Args::new(Some(GlobArgs::new(&cmd)), Config::new());
+    assert_eq!((4, None), args.size_hint());
+    args.next();
+    args.next();
+    assert_eq!((2, None), args.size_hint());
+    args.next();
+    assert!(args.size_hint().0 > 2);
+    let args = Args::new(Some(GlobArgs::new(&cmd)), Config::new().unique(true));
+    assert_eq!((0, None), args.size_hint());
+}
+
+#[test]
+fn size_hint_with_negated_args() {
+    let fs = MemoryFs::new(&["a.rs", "b.rs"]);
+    let cmd = "x a.rs !*.rs".encode_utf16().collect::<Vec<_>>();
+    let args = Args::new(Some(GlobArgs::new(&cmd)), Config::new().vfs(fs).negated_args(true));
+    let (min, _) = args.size_hint();
+    assert_eq!(vec!["x", "a.rs"], args.collect::<Vec<_>>());
+    assert!(min <= 2);
+}
+
+#[test]
+fn size_hint_with_skipped_device_names() {
+    let cmd = "x NUL CON".encode_utf16().collect::<Vec<_>>();
+    let args = Args::new(Some(GlobArgs::new(&cmd)), Config::new().device_names(DeviceNames::Skip));
+    let (min, _) = args.size_hint();
+    assert_eq!(vec!["x"], args.collect::<Vec<_>>());
+    assert!(min <= 1);
+}
+
+#[test]
+fn size_hint_when_disabled() {
+    let cmd = "x C*.toml NUL".encode_utf16().collect::<Vec<_>>();
+    let mut args = Args::new(Some(GlobArgs::new(&cmd)), Config::new());
+    args.disabled = true;
+    assert_eq!((3, Some(3)), args.size_hint());
+    let mut args = Args::new(Some(GlobArgs::new(&cmd)), Config::new().device_names(DeviceNames::Skip));
+    args.disabled = true;
+    assert_eq!((0, Some(3)), args.size_hint());
+    assert_eq!(vec!["x", "C*.toml"], args.collect::<Vec<_>>());
+}
+
+#[test]
+fn reports_origin() {
+    let args = vec!["Cargo.tom?", "_not_?a?_[f]ilename_", "--x=Cargo.tom?"].into_iter().map(|s| Arg::from(OsString::from(s)));
+    let mut args = Args::new(Some(args), Config::new().option_values(OptionValues::Repeat));
+    assert_eq!(Some(("Cargo.toml".into(), Origin::Matched)), args.next_with_origin());
+    assert_eq!(Some(("_not_?a?_[f]ilename_".into(), Origin::Literal)), args.next_with_origin());
+    assert_eq!(Some(("--x=Cargo.toml".into(), Origin::Literal)), args.next_with_origin());
+    assert_eq!(None, args.next_with_origin());
+}
+
+#[test]
+fn plain_args_are_literal() {
+    let cmd = "foo.exe Cargo.toml \"Cargo.tom?\" C:\\no\\such\\dir\\file.txt";
+    let cmd = cmd.encode_utf16().collect::<Vec<_>>();
+    let args = GlobArgs::new(&cmd).collect::<Vec<_>>();
+    assert!(args.iter().all(|arg| !arg.has_metacharacters()));
+    let mut args = Args::new(Some(args.into_iter()), Config::new());
+    args.next();
+    assert_eq!(Some(("Cargo.toml".into(), Origin::Literal)), args.next_with_origin());
+}
+
+#[test]
+fn reports_expansions() {
+    let cmd = "foo.exe src/????iter.rs \"Cargo.tom?\" --x=Cargo.tom?";
+    let cmd = cmd.chars().map(|c| c as u16).collect::<Vec<_>>();
+    let args = GlobArgs::new(&cmd);
+    let mut args = Args::new(Some(args), Config::new().option_values(OptionValues::Repeat));
+    let e = args.next_expanded().unwrap();
+    assert_eq!(("foo.exe".into(), None, vec![], false), (e.original, e.option, e.matches, e.was_quoted));
+    let e = args.next_expanded().unwrap();
+    assert_eq!(("src/????iter.rs".into(), None, vec![PathBuf::from("src/argsiter.rs"), PathBuf::from("src/globiter.rs")], false), (e.original, e.option, e.matches, e.was_quoted));
+    let e = args.next_expanded().unwrap();
+    assert_eq!(("Cargo.tom?".into(), None, vec![], true), (e.original, e.option, e.matches, e.was_quoted));
+    let e = args.next_expanded().unwrap();
+    assert_eq!(("--x=Cargo.tom?".into(), Some("--x=".into()), vec![PathBuf::from("Cargo.toml")], false), (e.original, e.option, e.matches, e.was_quoted));
+    assert!(args.next_expanded().is_none());
+}
+
+#[test]
+fn dos_engine() {
+    let cmd = "foo.exe s?c/d?s.* \"s?c/d?s.*\" [s]rc";
+    assert_eq!(vec!["foo.exe", "src/dos.rs", "s?c/d?s.*", "[s]rc"], expanded(cmd, Config::new().engine(Engine::Dos)));
+}
+
+#[test]
+fn case_sensitive() {
+    let cmd = "foo.exe read*.MD Cargo.tom? cargo.tom?";
+    assert_eq!(vec!["foo.exe", "read*.MD", "Cargo.toml", "cargo.tom?"], expanded(cmd, Config::new().case_sensitive(true)));
+    assert_eq!(vec!["foo.exe", "README.md", "Cargo.toml", "Cargo.toml"], expanded(cmd, Config::new()));
+}
+
+#[test]
+fn literal_brackets() {
+    let cmd = "foo.exe [CR]argo.toml [s]rc/args????.rs \"[s]\"*";
+    assert_eq!(vec!["foo.exe", "Cargo.toml", "src/argsiter.rs", "[s]*"], expanded(cmd, Config::new()));
+    assert_eq!(vec!["foo.exe", "[CR]argo.toml", "[s]rc/args????.rs", "[s]*"], expanded(cmd, Config::new().brackets(false)));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn expands_in_parallel() {
+    let cmd = "foo.exe src/**/*iter.rs -- Cargo.tom? --x=*.md \"*.md\"".encode_utf16().collect::<Vec<_>>();
+    let config = Config::new().option_values(OptionValues::Join(','));
+    let sequential: Vec<_> = Args::new(Some(GlobArgs::new(&cmd)), config.clone()).collect();
+    let parallel = Args::new(Some(GlobArgs::new(&cmd)), config).par_expand();
+    assert_eq!(sequential, parallel);
+    assert!(parallel.contains(&"src/globiter.rs".into()));
+}
+
+#[test]
+fn reads_directories_once() {
+    let fs = MemoryFs::new(&["Cargo.toml", "README.md", "src/lib.rs", "src/globiter.rs"]);
+    let cmd = "foo.exe *.toml *.md src/*.rs src/**/*iter.rs".encode_utf16().collect::<Vec<_>>();
+    let mut args = Args::new(Some(GlobArgs::new(&cmd)), Config::new().vfs(fs));
+    assert_eq!(6, args.by_ref().count());
+    assert_eq!(2, args.cache.len());
+}
+
+#[test]
+fn replays_expanded_args() {
+    let fs = MemoryFs::new(&["Cargo.toml", "src/lib.rs", "src/globiter.rs"]);
+    let cmd = "foo.exe *.toml src/*.rs x?y".encode_utf16().collect::<Vec<_>>();
+    let config = Config::new().vfs(fs);
+    let live: Vec<_> = Args::new(Some(GlobArgs::new(&cmd)), config.clone()).collect();
+    let expanded = Args::new(Some(CommandLine::Split(GlobArgs::new(&cmd).collect::<Vec<_>>().into_iter())), config.clone()).expand_all();
+    let mut args = Args::expanded(expanded.clone(), config.clone());
+    assert_eq!(live, args.by_ref().collect::<Vec<_>>());
+    assert_eq!(0, args.cache.len());
+    assert_eq!(live, Args::expanded(expanded, config).collect::<Vec<_>>());
+}
+
+#[test]
+fn unique() {
+    let cmd = "foo.exe -v *.toml -v Cargo.t* R*.md RE*.md";
+    assert_eq!(vec!["foo.exe", "-v", "Cargo.toml", "-v", "README.md"], expanded(cmd, Config::new().unique(true)));
+    assert_eq!(vec!["foo.exe", "-v", "Cargo.toml", "-v", "Cargo.toml", "README.md", "README.md"], expanded(cmd, Config::new()));
+}
+
+#[test]
+fn preserve_prefix() {
+    let cmd = "foo.exe ./src//glob*.rs src/../src/g?obiter.rs";
+    assert_eq!(vec!["foo.exe", "./src/globiter.rs", "./src/globmatch.rs", "src/../src/globiter.rs"], expanded(cmd, Config::new()));
+    assert_eq!(vec!["foo.exe", "./src//globiter.rs", "./src//globmatch.rs", "src/../src/globiter.rs"], expanded(cmd, Config::new().preserve_prefix(true)));
+}
+
+#[test]
+fn separators() {
+    let cmd = "foo.exe src/glob*.rs";
+    assert_eq!(vec!["foo.exe", "src/globiter.rs", "src/globmatch.rs"], expanded(cmd, Config::new().separators(Separators::Forward)));
+    if cfg!(windows) {
+        assert_eq!(vec!["foo.exe", "src\\globiter.rs", "src\\globmatch.rs"], expanded(cmd, Config::new().separators(Separators::Native)));
+        assert_eq!("C:/a/b/c", with_separators(Path::new("C:\\a/b\\c"), Separators::Forward).to_str().unwrap());
+    }
+}
+
+#[test]
+fn path_form() {
+    let cmd = "foo.exe src/../Cargo.tom? _not_?a?_[f]ilename_";
+    let dir = ::std::env::current_dir().unwrap();
+    assert_eq!(vec!["foo.exe".to_owned(), dir.join("src/../Cargo.toml").to_string_lossy().into_owned(), "_not_?a?_[f]ilename_".to_owned()],
+        expanded(cmd, Config::new().path_form(PathForm::Absolute)));
+    assert_eq!(vec!["foo.exe".to_owned(), dir.join("Cargo.toml").canonicalize().unwrap().to_string_lossy().into_owned(), "_not_?a?_[f]ilename_".to_owned()],
+        expanded(cmd, Config::new().path_form(PathForm::Canonical)));
+    assert_eq!(vec!["foo.exe".to_owned(), dir.join("Cargo.toml").to_string_lossy().into_owned(), "_not_?a?_[f]ilename_".to_owned()],
+        expanded(cmd, Config::new().path_form(PathForm::Full)));
+    #[cfg(not(windows))]
+    assert_eq!(Some(PathBuf::from("/a/c/")), full_path(Path::new("/a/./b/../../a/c/")));
+}
+
+#[test]
+fn long_paths() {
+    let temp = TempDir::new("long_paths");
+    let mut dir = temp.path().to_owned();
+    for i in 0..30 {
+        dir.push(format!("directory_{:02}", i));
+    }
+    ::std::fs::create_dir_all(&dir).unwrap();
+    ::std::fs::write(dir.join("file.txt"), "").unwrap();
+    let dir = dir.to_str().unwrap();
+    assert!(dir.len() > 300);
+    let cmd = format!("foo.exe {}/*.t?t", dir);
+    assert_eq!(vec!["foo.exe".to_owned(), format!("{}/file.txt", dir)], expanded(&cmd, Config::new()));
+    if cfg!(windows) {
+        assert!(expanded(&cmd, Config::new().long_path_prefix(true))[1].starts_with("\\\\?\\"));
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn follow_links() {
+    let (temp, target) = (TempDir::new("follow_links"), TempDir::new("follow_links_target"));
+    let (dir, target) = (temp.path(), target.path());
+    ::std::fs::create_dir_all(dir.join("a")).unwrap();
+    ::std::fs::write(dir.join("a/file.txt"), "").unwrap();
+    ::std::fs::write(target.join("t.txt"), "").unwrap();
+    ::std::os::unix::fs::symlink(dir, dir.join("a/loop")).unwrap();
+    ::std::os::unix::fs::symlink(target, dir.join("a/out")).unwrap();
+    let dir = dir.to_str().unwrap();
+    let cmd = format!("foo.exe {}/**/*.txt", dir).chars().map(|c| c as u16).collect::<Vec<_>>();
+    let found = |config| {
+        let args = GlobArgs::new(&cmd);
+        let mut iter = Args::new(Some(args), config);
+        iter.next_with_origin(); // program name
+        let mut found = Vec::new();
+        while let Some((path, origin)) = iter.next_with_origin() {
+            found.push((path.to_string_lossy()[dir.len()..].to_owned(), origin));
+        }
+        found
+    };
+    assert_eq!(vec![("/a/file.txt".to_owned(), Origin::Matched)], found(Config::new()));
+    assert_eq!(vec![
+        ("/a/file.txt".to_owned(), Origin::Matched),
+        ("/a/out/t.txt".to_owned(), Origin::Linked),
+    ], found(Config::new().follow_links(true)));
+}
+
+#[test]
+fn max_results() {
+    let cmd = "foo.exe C*.toml src/*.rs".chars().map(|c| c as u16).collect::<Vec<_>>();
+    let args = GlobArgs::new(&cmd);
+    let mut iter = Args::new(Some(args), Config::new().max_results(3));
+    assert_eq!(Some(Ok((OsString::from("foo.exe"), Origin::Literal))), iter.try_next_with_origin());
+    assert_eq!(Some(Ok((OsString::from("Cargo.toml"), Origin::Matched))), iter.try_next_with_origin());
+    let err = iter.try_next_with_origin().unwrap().unwrap_err();
+    assert_eq!(TooManyMatches { pattern: "src/*.rs".into(), limit: 3 }, err);
+    assert_eq!("pattern `src/*.rs` matches more than 3 paths", err.to_string());
+    assert_eq!(None, iter.try_next_with_origin());
+}
+
+#[test]
+fn entry_types() {
+    let cmd = "foo.exe s?c/../Cargo.tom? s?c";
+    assert_eq!(vec!["foo.exe", "src/../Cargo.toml", "src"], expanded(cmd, Config::new()));
+    assert_eq!(vec!["foo.exe", "src/../Cargo.toml", "s?c"], expanded(cmd, Config::new().entry_types(::EntryTypes::Files)));
+    assert_eq!(vec!["foo.exe", "s?c/../Cargo.tom?", "src"], expanded(cmd, Config::new().entry_types(::EntryTypes::Directories)));
+}
+
+#[test]
+#[cfg(feature = "ignore")]
+fn gitignore() {
+    let temp = TempDir::new("gitignore");
+    let dir = temp.path();
+    ::std::fs::create_dir_all(dir.join(".git")).unwrap();
+    ::std::fs::create_dir_all(dir.join("build")).unwrap();
+    ::std::fs::create_dir_all(dir.join("sub")).unwrap();
+    ::std::fs::write(dir.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+    ::std::fs::write(dir.join("sub/.gitignore"), "!keep.log\n").unwrap();
+    for name in &["a.txt", "a.log", "build/b.txt", "sub/keep.log"] {
+        ::std::fs::write(dir.join(name), "").unwrap();
+    }
+    let dir = dir.to_str().unwrap();
+    let cmd = format!("foo.exe {0}/**/*.txt {0}/**/*.log", dir);
+    let found = |config| expanded(&cmd, config).into_iter().skip(1).map(|p| p[dir.len() + 1..].to_owned()).collect::<Vec<_>>();
+    assert_eq!(vec!["a.txt", "build/b.txt", "a.log", "sub/keep.log"], found(Config::new()));
+    assert_eq!(vec!["a.txt", "sub/keep.log"], found(Config::new().gitignore(true)));
+}
+
+#[test]
+fn expand_quoted() {
+    let cmd = "foo.exe \"C*.toml\" \"R\"EAD*.md -- \"*.md\"";
+    assert_eq!(vec!["foo.exe", "C*.toml", "README.md", "--", "*.md"], expanded(cmd, Config::new()));
+    assert_eq!(vec!["foo.exe", "Cargo.toml", "README.md", "--", "README.md"], expanded(cmd, Config::new().expand_quoted(true)));
+}
+
+#[test]
+fn disabled() {
+    let cmd = "foo.exe C*.toml \"*\"".chars().map(|c| c as u16).collect::<Vec<_>>();
+    let args = GlobArgs::new(&cmd);
+    let mut iter = Args::new(Some(args), Config::new());
+    iter.disabled = true;
+    assert_eq!(vec!["foo.exe", "C*.toml", "*"], iter.collect::<Vec<_>>());
+}
+
+#[test]
+fn detects_unix_shells() {
+    use config::launched_from_unix_shell;
+    assert!(launched_from_unix_shell(Some("MINGW64".into()), None));
+    assert!(launched_from_unix_shell(None, Some("/usr/bin/bash".into())));
+    assert!(!launched_from_unix_shell(Some("".into()), Some("C:\\Windows\\system32\\cmd.exe".into())));
+    assert!(!launched_from_unix_shell(None, None));
+}
+
+#[cfg(windows)]
+#[test]
+fn keeps_trailing_dots_and_spaces() {
+    let temp = TempDir::new("trailing_dots");
+    let dir = temp.path();
+    let verbatim = ::longpath::extended(dir);
+    ::std::fs::create_dir_all(verbatim.join("sub.")).unwrap();
+    ::std::fs::write(verbatim.join("sub.").join("notes. "), "").unwrap();
+    ::std::fs::write(dir.join("plain.txt"), "").unwrap();
+    let cmd = format!("foo.exe {0}\\*\\* {0}\\p*", dir.display());
+    let expected = vec!["foo.exe".to_owned(), format!("{}\\sub.\\notes. ", verbatim.display()), format!("{}\\plain.txt", dir.display())];
+    assert_eq!(expected, expanded(&cmd, Config::new()));
+}
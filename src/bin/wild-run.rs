@@ -0,0 +1,26 @@
+//! Runs a program with wildcards in its arguments expanded, e.g. `wild-run findstr TODO src\*.rs`.
+//!
+//! This gives Unix-like expansion to existing Windows programs without changing them.
+//! Elsewhere the shell has already expanded the arguments, so they're passed on unchanged.
+extern crate wild;
+
+use std::process::{exit, Command};
+
+fn main() {
+    // the program to run is taken as typed, so that e.g. `wild-run *.exe` doesn't run whichever file matches first
+    let mut args = wild::Config::new().expand_range(2..).args_os().skip(1);
+    let program = match args.next() {
+        Some(program) => program,
+        None => {
+            eprintln!("Usage: wild-run <program> [arguments...]");
+            exit(2);
+        },
+    };
+    match Command::new(&program).args(args).status() {
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(err) => {
+            eprintln!("wild-run: can't run {}: {}", program.to_string_lossy(), err);
+            exit(127);
+        },
+    }
+}
@@ -0,0 +1,59 @@
+use bpaf::{Args, OptionParser, ParseFailure};
+use std::ffi::OsString;
+use std::path::Path;
+
+/// Extension trait adding glob-expanding variants of `run()` to [`bpaf::OptionParser`](https://docs.rs/bpaf/latest/bpaf/struct.OptionParser.html).
+///
+/// Requires the `bpaf` feature.
+///
+/// ```rust,no_run
+/// # extern crate bpaf;
+/// # extern crate wild;
+/// use bpaf::Parser;
+/// use wild::OptionParserExt;
+///
+/// let files = bpaf::positional::<std::path::PathBuf>("FILE")
+///     .many()
+///     .to_options()
+///     .run_wild();
+/// ```
+pub trait OptionParserExt<T> {
+    /// Same as `run()`, but parses `wild::args_os()` instead of `std::env::args_os()`.
+    ///
+    /// bpaf's `Args` needs to know the number of arguments up front, so all of them are expanded before parsing starts.
+    ///
+    /// Errors and help are wrapped at bpaf's default width of 100 columns, since a width set with `max_width()`
+    /// can't be read back from the parser. To use another width, call `try_run_wild()` and `ParseFailure::print_message()`.
+    fn run_wild(self) -> T;
+
+    /// Same as `run_inner(Args::current_args())`, but parses `wild::args_os()` instead of `std::env::args_os()`.
+    ///
+    /// Like in `run_wild()`, all arguments are expanded before parsing starts.
+    fn try_run_wild(self) -> Result<T, ParseFailure>;
+}
+
+/// `max_width` of bpaf's `OptionParser` unless it's changed
+const DEFAULT_MAX_WIDTH: usize = 100;
+
+impl<T> OptionParserExt<T> for OptionParser<T> {
+    fn run_wild(self) -> T {
+        match self.try_run_wild() {
+            Ok(t) => t,
+            Err(err) => {
+                err.print_message(DEFAULT_MAX_WIDTH);
+                std::process::exit(err.exit_code())
+            },
+        }
+    }
+
+    fn try_run_wild(self) -> Result<T, ParseFailure> {
+        let mut args = ::args_os();
+        let name = args.next().and_then(|n| Path::new(&n).file_name()?.to_str().map(String::from));
+        let args: Vec<OsString> = args.collect();
+        let args = Args::from(&args[..]);
+        match name {
+            Some(ref name) => self.run_inner(args.set_name(name)),
+            None => self.run_inner(args),
+        }
+    }
+}
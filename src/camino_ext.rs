@@ -0,0 +1,78 @@
+use camino::{FromPathBufError, Utf8PathBuf};
+use std::convert::TryFrom;
+use std::iter::FusedIterator;
+use std::path::PathBuf;
+use iter::ArgsOs;
+
+/// What `wild::args_utf8_paths()` does with arguments (or files they matched) that aren't valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonUtf8 {
+    /// Returned as errors, which give back the original path
+    Error,
+    /// Left out
+    Skip,
+    /// Converted with `U+FFFD` replacement characters, like `wild::args_lossy()` does
+    Lossy,
+}
+
+/// Iterator of glob-expanded arguments as camino's `Utf8PathBuf`s. Returned by `wild::args_utf8_paths()`.
+///
+/// Requires the `camino` feature.
+#[derive(Debug, Clone)]
+pub struct ArgsUtf8Paths {
+    inner: ArgsOs,
+    non_utf8: NonUtf8,
+}
+
+impl Iterator for ArgsUtf8Paths {
+    type Item = Result<Utf8PathBuf, FromPathBufError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let err = match Utf8PathBuf::try_from(PathBuf::from(self.inner.next()?)) {
+                Ok(path) => return Some(Ok(path)),
+                Err(err) => err,
+            };
+            match self.non_utf8 {
+                NonUtf8::Error => return Some(Err(err)),
+                NonUtf8::Skip => continue,
+                NonUtf8::Lossy => return Some(Ok(Utf8PathBuf::from(err.into_path_buf().to_string_lossy().into_owned()))),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (min, max) = self.inner.size_hint();
+        (if self.non_utf8 == NonUtf8::Skip { 0 } else { min }, max)
+    }
+}
+
+impl FusedIterator for ArgsUtf8Paths {}
+
+/// Returns the program arguments (glob-expanded for Windows) as camino's `Utf8PathBuf`s,
+/// handling the ones that aren't valid UTF-8 as chosen.
+///
+/// With `NonUtf8::Skip` or `NonUtf8::Lossy` all items are `Ok`.
+///
+/// ```rust
+/// for path in wild::args_utf8_paths(wild::NonUtf8::Error).skip(1) {
+///     match path {
+///         Ok(path) => println!("{}", path),
+///         Err(err) => eprintln!("error: {} is not valid UTF-8", err.as_path().display()),
+///     }
+/// }
+/// ```
+///
+/// Requires the `camino` feature.
+pub fn args_utf8_paths(non_utf8: NonUtf8) -> ArgsUtf8Paths {
+    ArgsUtf8Paths {
+        inner: ::args_os(),
+        non_utf8,
+    }
+}
+
+#[test]
+fn converts_args() {
+    let args: Vec<_> = args_utf8_paths(NonUtf8::Error).collect::<Result<_, _>>().unwrap();
+    assert_eq!(::args_os().count(), args.len());
+}
@@ -0,0 +1,67 @@
+//! Case-insensitive comparison the way the file system does it, and Unicode normalization
+#[cfg(feature = "unicode-normalization")]
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+#[cfg(feature = "unicode-normalization")]
+use config::Normalization;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+#[cfg(not(windows))]
+use parser::{LossyOsStrExt, LossyOsStringExt};
+
+/// Uppercases a UTF-16 code unit using the same table as NTFS
+#[cfg(windows)]
+pub(crate) fn upcase(c: u16) -> u16 {
+    unsafe { ::sys::RtlUpcaseUnicodeChar(c) }
+}
+
+/// Uppercases a UTF-16 code unit using Unicode simple case mapping, which NTFS tables are based on
+#[cfg(not(windows))]
+pub(crate) fn upcase(c: u16) -> u16 {
+    if c < 128 {
+        return u16::from((c as u8).to_ascii_uppercase());
+    }
+    let mut upper = ::std::char::from_u32(u32::from(c)).into_iter().flat_map(char::to_uppercase);
+    match (upper.next(), upper.next()) {
+        (Some(u), None) if (u as u32) < 0x10000 => u as u16,
+        _ => c,
+    }
+}
+
+/// Uppercases every code unit, so that strings can be compared case-insensitively
+pub(crate) fn fold(s: &OsStr) -> OsString {
+    let folded: Vec<u16> = s.encode_wide().map(upcase).collect();
+    OsString::from_wide(&folded)
+}
+
+/// The name in the normalization form. Names that aren't valid Unicode, or are ASCII, are unchanged.
+#[cfg(feature = "unicode-normalization")]
+pub(crate) fn normalize(s: &OsStr, form: Normalization) -> Cow<'_, OsStr> {
+    use unicode_normalization::UnicodeNormalization;
+    match (s.to_str(), form) {
+        (Some(s), Normalization::Nfc) if !s.is_ascii() => Cow::Owned(s.nfc().collect::<String>().into()),
+        (Some(s), Normalization::Nfd) if !s.is_ascii() => Cow::Owned(s.nfd().collect::<String>().into()),
+        _ => Cow::Borrowed(s),
+    }
+}
+
+/// Whether a literal name has to be matched against directory entries, since they may be in another normalization form
+#[cfg(feature = "unicode-normalization")]
+pub(crate) fn needs_normalization(s: &OsStr, form: Normalization) -> bool {
+    form != Normalization::None && !s.to_str().is_some_and(str::is_ascii)
+}
+
+#[test]
+fn folds_non_ascii() {
+    assert_eq!(OsString::from("ÉTÉ.TXT"), fold(OsStr::new("été.txt")));
+    assert_eq!(OsString::from("ΑΒΓ"), fold(OsStr::new("αβγ")));
+    assert_eq!(OsString::from("STRASSE ß"), fold(OsStr::new("strasse ß")));
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn normalizes() {
+    assert_eq!(OsStr::new("caf\u{e9}"), normalize(OsStr::new("cafe\u{301}"), Normalization::Nfc));
+    assert_eq!(OsStr::new("cafe\u{301}"), normalize(OsStr::new("caf\u{e9}"), Normalization::Nfd));
+    assert_eq!(OsStr::new("cafe\u{301}"), normalize(OsStr::new("cafe\u{301}"), Normalization::None));
+}
@@ -0,0 +1,53 @@
+use clap::{ArgMatches, Command, Error, Parser};
+
+/// Extension trait adding glob-expanding variants of `get_matches()` to [`clap::Command`](https://docs.rs/clap/latest/clap/struct.Command.html).
+///
+/// Requires the `clap` feature.
+///
+/// ```rust,no_run
+/// use wild::CommandExt;
+///
+/// let matches = clap::Command::new("your_app")
+///     .arg(clap::Arg::new("files").num_args(0..))
+///     .get_matches_wild();
+/// ```
+pub trait CommandExt {
+    /// Same as `get_matches()`, but parses `wild::args_os()` instead of `std::env::args_os()`.
+    fn get_matches_wild(self) -> ArgMatches;
+
+    /// Same as `try_get_matches()`, but parses `wild::args_os()` instead of `std::env::args_os()`.
+    fn try_get_matches_wild(self) -> Result<ArgMatches, Error>;
+}
+
+impl CommandExt for Command {
+    fn get_matches_wild(self) -> ArgMatches {
+        self.get_matches_from(::args_os())
+    }
+
+    fn try_get_matches_wild(self) -> Result<ArgMatches, Error> {
+        self.try_get_matches_from(::args_os())
+    }
+}
+
+/// Same as `T::parse()` for a [`clap::Parser`](https://docs.rs/clap/latest/clap/trait.Parser.html), but parses glob-expanded `wild::args_os()`.
+///
+/// Requires the `clap` feature.
+///
+/// ```rust,ignore
+/// #[derive(clap::Parser)]
+/// struct Cli {
+///     files: Vec<std::path::PathBuf>,
+/// }
+///
+/// let cli: Cli = wild::parse();
+/// ```
+pub fn parse<T: Parser>() -> T {
+    T::parse_from(::args_os())
+}
+
+/// Same as `T::try_parse()` for a [`clap::Parser`](https://docs.rs/clap/latest/clap/trait.Parser.html), but parses glob-expanded `wild::args_os()`.
+///
+/// Requires the `clap` feature.
+pub fn try_parse<T: Parser>() -> Result<T, Error> {
+    T::try_parse_from(::args_os())
+}
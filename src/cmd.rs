@@ -0,0 +1,87 @@
+const CARET: u16 = b'^' as u16;
+const QUOTE: u16 = b'"' as u16;
+const SPACE: u16 = b' ' as u16;
+const AMPERSAND: u16 = b'&' as u16;
+const PIPE: u16 = b'|' as u16;
+const LESS: u16 = b'<' as u16;
+const GREATER: u16 = b'>' as u16;
+
+fn is_digit(c: u16) -> bool {
+    c >= u16::from(b'0') && c <= u16::from(b'9')
+}
+
+/// Applies what `cmd.exe` does to a command before starting the program:
+/// removes `^` escapes and redirections, and cuts off commands chained with `&` or `|`.
+///
+/// Quotes are kept, since the program parses them.
+pub(crate) fn strip_cmd_syntax(line: &[u16]) -> Vec<u16> {
+    let mut out = Vec::with_capacity(line.len());
+    let mut quoted = false;
+    let mut i = 0;
+    while i < line.len() {
+        let c = line[i];
+        i += 1;
+        if c == QUOTE {
+            quoted = !quoted;
+            out.push(c);
+            continue;
+        }
+        if quoted {
+            out.push(c);
+            continue;
+        }
+        match c {
+            // escapes the next character, even a quote. A caret at the end is dropped.
+            CARET => {
+                if let Some(&next) = line.get(i) {
+                    out.push(next);
+                    i += 1;
+                }
+            },
+            // the rest is another command
+            AMPERSAND | PIPE => break,
+            LESS | GREATER => {
+                // a handle number right before the operator, as in `2>`, is part of the redirection
+                let len = out.len();
+                if len > 0 && is_digit(out[len - 1]) && (len == 1 || out[len - 2] == SPACE) {
+                    out.pop();
+                }
+                if line.get(i) == Some(&GREATER) {
+                    i += 1;
+                }
+                if line.get(i) == Some(&AMPERSAND) {
+                    // `2>&1` redirects to another handle
+                    i += 1;
+                    while i < line.len() && is_digit(line[i]) {
+                        i += 1;
+                    }
+                } else {
+                    while line.get(i) == Some(&SPACE) {
+                        i += 1;
+                    }
+                    let mut quoted_target = false;
+                    while let Some(&c) = line.get(i) {
+                        if c == QUOTE {
+                            quoted_target = !quoted_target;
+                        } else if !quoted_target && [SPACE, AMPERSAND, PIPE, LESS, GREATER].contains(&c) {
+                            break;
+                        }
+                        i += 1;
+                    }
+                }
+                // keep the arguments around the redirection apart
+                out.push(SPACE);
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[test]
+fn strips_cmd_syntax() {
+    let stripped = |s: &str| String::from_utf16(&strip_cmd_syntax(&s.encode_utf16().collect::<Vec<_>>())).unwrap();
+    assert_eq!(r#"prog a&b "x & y ^"   c   "#, stripped(r#"prog a^&b "x & y ^" > out.txt c 2>&1 & other"#));
+    assert_eq!(r#"prog "a"  "#, stripped(r#"prog ^"a^" <"in put.txt"| more"#));
+    assert_eq!("prog 10 ", stripped("prog 10>>log"));
+}
@@ -0,0 +1,1027 @@
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{self, Read};
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use error::{Error, TooManyMatches};
+use progress::{CancelToken, Progress};
+use iter::{Args, ArgsEntries, ArgsLossy, ArgsOs, ArgsPaths, Expand, Expansions, GlobOs, TryArgs};
+use matcher::PatternMatcher;
+use namematch::Matcher;
+use vfs::Vfs;
+
+/// How arguments of the form `--name=value` are expanded. See [`Config::option_values`](struct.Config.html#method.option_values).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionValues {
+    /// The argument is expanded as a whole, like any other argument. This is the default.
+    Whole,
+    /// Only the value is expanded, and the option is repeated for every match: `--name=a --name=b`.
+    Repeat,
+    /// Only the value is expanded, and all matches are joined with the given separator into one argument: `--name=a,b`.
+    Join(char),
+}
+
+/// What happens after the `--` end-of-options marker. See [`Config::end_of_options`](struct.Config.html#method.end_of_options).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndOfOptions {
+    /// `--` has no effect on expansion. This is the default.
+    Ignore,
+    /// Arguments after `--` are passed through as-is.
+    StopExpansion,
+    /// Only arguments after `--` are expanded.
+    StartExpansion,
+}
+
+/// How patterns are matched against file names. See [`Config::engine`](struct.Config.html#method.engine).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    /// Unix-like glob syntax (`*`, `?`, `[a-z]`). This is the default.
+    ///
+    /// With the `globset` feature, `{a,b}` alternations are supported too, within a single path component.
+    Glob,
+    /// Windows' own wildcard matching, performed by `FindFirstFileExW` one path component at a time.
+    ///
+    /// Only `*` and `?` are special, and they also match 8.3 short names, like in `dir`
+    /// (e.g. `*.htm` matches `page.html`). `[` and `]` are literal.
+    ///
+    /// Windows-only. Elsewhere (e.g. in cross-platform tests) `Glob` is used instead.
+    Native,
+    /// Windows' wildcard semantics reimplemented in Rust, matching what `cmd.exe` users expect:
+    /// `*.*` matches all files, `*.` matches files without an extension,
+    /// and `?` can match nothing before a `.` (`file?.txt` matches `file.txt`).
+    ///
+    /// Unlike `Native`, it doesn't match 8.3 short names.
+    Dos,
+}
+
+/// Unicode normalization form of patterns and file names when they're compared.
+/// See [`Config::normalization`](struct.Config.html#method.normalization).
+#[cfg(feature = "unicode-normalization")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Characters are compared as they are, so `é` doesn't match `e` followed by a combining accent. This is the default.
+    None,
+    /// Both are composed (NFC), as typed on Windows.
+    Nfc,
+    /// Both are decomposed (NFD), as stored by macOS.
+    Nfd,
+}
+
+/// Order of matches of a pattern. See [`Config::sort_order`](struct.Config.html#method.sort_order).
+///
+/// Matches are sorted within each directory, so with `dir*/*` all files of the first directory come first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// By code units, so uppercase letters come before lowercase. This is the default.
+    Lexicographic,
+    /// Ignoring case, like in `dir`.
+    CaseInsensitive,
+    /// Ignoring case, and with numbers compared by their value (`file2` before `file10`), like in Explorer.
+    Natural,
+    /// In the order the file system returns them, which is the fastest.
+    Unsorted,
+}
+
+/// Path separators in matched paths. See [`Config::separators`](struct.Config.html#method.separators).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separators {
+    /// Separators typed in the pattern may be kept, and the ones added between matched components are native (`\`).
+    /// This is the default.
+    Unchanged,
+    /// All separators are backslashes on Windows.
+    Native,
+    /// All separators are forward slashes, e.g. for passing paths to MSYS programs.
+    Forward,
+}
+
+/// Form of matched paths. See [`Config::path_form`](struct.Config.html#method.path_form).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathForm {
+    /// Relative if the pattern was relative. This is the default.
+    AsMatched,
+    /// Joined with the current directory, so that the paths stay valid after the program changes directory.
+    Absolute,
+    /// Absolute, with `.` and `..` removed and symlinks resolved. On Windows these paths begin with `\\?\`.
+    Canonical,
+    /// Absolute, with `.` and `..` removed without touching the file system, like by `GetFullPathNameW`,
+    /// so symlinks are kept. On Windows separators become `\`. It's cheaper than `Canonical`, and gives comparable paths,
+    /// e.g. for removing duplicates or logging.
+    Full,
+}
+
+/// Form of file names in matched paths on Windows. See [`Config::name_form`](struct.Config.html#method.name_form).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameForm {
+    /// Names as typed in the pattern or listed in directories. This is the default.
+    AsMatched,
+    /// 8.3 short names typed in the pattern, like `PROGRA~1`, are replaced with the long names, using `GetLongPathNameW`.
+    Long,
+    /// All names are replaced with their 8.3 short names, using `GetShortPathNameW`,
+    /// for legacy programs that can't handle spaces or non-ASCII characters in paths.
+    /// Names without a short name, e.g. on volumes where they're disabled, are kept long.
+    Short,
+}
+
+/// Filter for matches with the hidden or system attribute.
+/// See [`Config::hidden_files`](struct.Config.html#method.hidden_files).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeFilter {
+    /// Match files regardless of the attribute. This is the default.
+    Include,
+    /// Skip files and directories with the attribute, like `dir` does.
+    Exclude,
+    /// Match only files with the attribute, like `dir /a:h`. Directories leading to them don't need to have it.
+    Only,
+}
+
+/// What happens to arguments naming reserved devices, like `CON` or `NUL`. See [`Config::device_names`](struct.Config.html#method.device_names).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceNames {
+    /// They aren't checked. This is the default.
+    Keep,
+    /// They're kept, and passed to `Config::on_device_name`.
+    Flag,
+    /// They're removed from the arguments, and passed to `Config::on_device_name`.
+    Skip,
+}
+
+/// Kinds of file system entries that wildcards can match. See [`Config::entry_types`](struct.Config.html#method.entry_types).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryTypes {
+    /// Files and directories. This is the default.
+    Both,
+    /// Only files, e.g. for tools that process file contents.
+    Files,
+    /// Only directories, like a pattern ending with a separator.
+    Directories,
+}
+
+/// How arguments are separated in a list read by [`Config::expand_lines`](struct.Config.html#method.expand_lines).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSeparator {
+    /// Every line is split like a command line, so arguments with spaces, or wildcards that must not be expanded, can be quoted.
+    Lines,
+    /// Arguments end with a NUL character, like from `find -print0`, and are taken as they are, like by `wild::expand()`.
+    Nul,
+}
+
+/// Arguments of a program started with an empty command line, or one with only spaces.
+/// See [`Config::empty_command_line`](struct.Config.html#method.empty_command_line).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyCommandLine {
+    /// Just the path of the executable as the program name, like `std::env::args()` gives. This is the default.
+    ProgramPath,
+    /// No arguments at all, not even the program name.
+    Empty,
+}
+
+/// Character that makes the next metacharacter literal in unquoted arguments.
+/// See [`Config::escape_char`](struct.Config.html#method.escape_char).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeChar {
+    /// Only quotes make metacharacters literal. This is the default.
+    None,
+    /// `^`, the escape character of `cmd.exe`
+    Caret,
+    /// `` ` ``, the escape character of PowerShell
+    Backtick,
+}
+
+impl EscapeChar {
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pub(crate) fn code_unit(self) -> Option<u16> {
+        match self {
+            EscapeChar::None => None,
+            EscapeChar::Caret => Some(u16::from(b'^')),
+            EscapeChar::Backtick => Some(u16::from(b'`')),
+        }
+    }
+}
+
+type PredicateFn = dyn Fn(usize, &OsStr) -> bool + Send + Sync;
+
+/// The function of `Config::expand_if`. Closures can't be printed, so it's shown only by name.
+#[derive(Clone)]
+#[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+pub(crate) struct ExpandPredicate(pub Arc<PredicateFn>);
+
+impl fmt::Debug for ExpandPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ExpandPredicate")
+    }
+}
+
+pub(crate) type ErrorFn = dyn Fn(&OsStr, &Path, &io::Error) + Send + Sync;
+pub(crate) type ArgFn = dyn Fn(&OsStr) + Send + Sync;
+pub(crate) type ProgressFn = dyn Fn(Progress) + Send + Sync;
+pub(crate) type TooManyMatchesFn = dyn Fn(&TooManyMatches) + Send + Sync;
+
+/// A function called by the expansion, like `Config::on_error`. Closures can't be printed, so it's shown only by name.
+#[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+pub(crate) struct Callback<F: ?Sized>(pub Arc<F>);
+
+impl<F: ?Sized> Clone for Callback<F> {
+    fn clone(&self) -> Self {
+        Callback(self.0.clone())
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+/// Settings for glob expansion.
+///
+/// The settings only affect Windows and WASI. On other platforms arguments are always passed through as-is,
+/// since the shell has already expanded them.
+///
+/// ```rust
+/// let args = wild::Config::new()
+///     .option_values(wild::OptionValues::Repeat)
+///     .args();
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Config {
+    pub(crate) option_values: OptionValues,
+    pub(crate) slash_options: bool,
+    pub(crate) end_of_options: EndOfOptions,
+    pub(crate) engine: Engine,
+    pub(crate) case_sensitive: bool,
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) normalization: Normalization,
+    pub(crate) brackets: bool,
+    pub(crate) extglob: bool,
+    pub(crate) exact_names: bool,
+    pub(crate) alternate_streams: bool,
+    pub(crate) unique: bool,
+    pub(crate) excludes: Vec<OsString>,
+    pub(crate) negated_args: bool,
+    pub(crate) sort_order: SortOrder,
+    pub(crate) preserve_prefix: bool,
+    pub(crate) base_dir: Option<PathBuf>,
+    pub(crate) separators: Separators,
+    pub(crate) path_form: PathForm,
+    pub(crate) long_path_prefix: bool,
+    pub(crate) name_form: NameForm,
+    pub(crate) hidden_files: AttributeFilter,
+    pub(crate) system_files: AttributeFilter,
+    pub(crate) follow_links: bool,
+    pub(crate) max_results: Option<usize>,
+    pub(crate) on_too_many_matches: Option<Callback<TooManyMatchesFn>>,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) entry_types: EntryTypes,
+    pub(crate) expand_quoted: bool,
+    pub(crate) escape_char: EscapeChar,
+    pub(crate) expand_predicate: Option<ExpandPredicate>,
+    pub(crate) expand_range: (Bound<usize>, Bound<usize>),
+    pub(crate) detect_unix_shell: bool,
+    pub(crate) command_line_source: Option<fn() -> Option<Vec<u16>>>,
+    pub(crate) empty_command_line: EmptyCommandLine,
+    pub(crate) vfs: Option<Arc<dyn Vfs>>,
+    pub(crate) matcher: Option<Arc<dyn PatternMatcher>>,
+    pub(crate) on_error: Option<Callback<ErrorFn>>,
+    pub(crate) on_no_match: Option<Callback<ArgFn>>,
+    pub(crate) device_names: DeviceNames,
+    pub(crate) on_device_name: Option<Callback<ArgFn>>,
+    pub(crate) progress: Option<Callback<ProgressFn>>,
+    pub(crate) cancel_token: Option<CancelToken>,
+    #[cfg(feature = "ignore")]
+    pub(crate) gitignore: bool,
+    /// Set internally by `par_args_os()`
+    #[cfg(feature = "rayon")]
+    pub(crate) parallel: bool,
+    #[cfg(feature = "rayon")]
+    pub(crate) walk_pool: Option<Arc<::rayon::ThreadPool>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            option_values: OptionValues::Whole,
+            slash_options: false,
+            end_of_options: EndOfOptions::Ignore,
+            engine: Engine::Glob,
+            case_sensitive: false,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: Normalization::None,
+            brackets: true,
+            extglob: false,
+            exact_names: false,
+            alternate_streams: false,
+            unique: false,
+            excludes: Vec::new(),
+            negated_args: false,
+            sort_order: SortOrder::Lexicographic,
+            preserve_prefix: false,
+            base_dir: None,
+            separators: Separators::Unchanged,
+            path_form: PathForm::AsMatched,
+            long_path_prefix: false,
+            name_form: NameForm::AsMatched,
+            hidden_files: AttributeFilter::Include,
+            system_files: AttributeFilter::Include,
+            follow_links: false,
+            max_results: None,
+            on_too_many_matches: None,
+            max_depth: None,
+            entry_types: EntryTypes::Both,
+            expand_quoted: false,
+            escape_char: EscapeChar::None,
+            expand_predicate: None,
+            expand_range: (Bound::Unbounded, Bound::Unbounded),
+            detect_unix_shell: false,
+            command_line_source: None,
+            empty_command_line: EmptyCommandLine::ProgramPath,
+            vfs: None,
+            matcher: None,
+            on_error: None,
+            on_no_match: None,
+            device_names: DeviceNames::Keep,
+            on_device_name: None,
+            progress: None,
+            cancel_token: None,
+            #[cfg(feature = "ignore")]
+            gitignore: false,
+            #[cfg(feature = "rayon")]
+            parallel: false,
+            #[cfg(feature = "rayon")]
+            walk_pool: None,
+        }
+    }
+}
+
+/// Settings used by `wild::args()` and other functions without a `Config`
+static GLOBAL: OnceLock<Config> = OnceLock::new();
+
+/// Set by `wild::disable_expansion()`
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn disable_expansion() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether `wild::disable_expansion()` has been called, or the user has set `WILD_NOGLOB` to anything other than `0`
+#[cfg_attr(not(any(test, windows, target_os = "wasi")), allow(dead_code))]
+pub(crate) fn expansion_disabled() -> bool {
+    DISABLED.load(Ordering::Relaxed) || std::env::var_os("WILD_NOGLOB").is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Whether the environment looks like the program has been started from an MSYS2, Git Bash or Cygwin shell,
+/// which have already expanded the arguments. They set `MSYSTEM` or a Unix-style `SHELL`, which `cmd.exe` and PowerShell don't.
+#[cfg_attr(not(any(test, windows, target_os = "wasi")), allow(dead_code))]
+pub(crate) fn launched_from_unix_shell(msystem: Option<OsString>, shell: Option<OsString>) -> bool {
+    msystem.is_some_and(|value| !value.is_empty()) || shell.is_some_and(|value| value.to_string_lossy().starts_with('/'))
+}
+
+impl Config {
+    /// Default settings, same as used by `wild::args()` unless changed with `wild::set_config()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settings installed with `wild::set_config()`, or the defaults once anything has used them
+    pub(crate) fn global() -> &'static Config {
+        GLOBAL.get_or_init(Config::default)
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn set_global(config: Config) -> Result<(), Config> {
+        GLOBAL.set(config)
+    }
+
+    /// Expand only the value part of `--name=value` arguments, e.g. `--include=*.rs`.
+    ///
+    /// The option name must consist of ASCII letters, digits, `-` and `_`.
+    pub fn option_values(mut self, policy: OptionValues) -> Self {
+        self.option_values = policy;
+        self
+    }
+
+    /// Also recognize Windows-style `/name:value` options when expanding option values.
+    pub fn slash_options(mut self, enabled: bool) -> Self {
+        self.slash_options = enabled;
+        self
+    }
+
+    /// Stop expanding arguments after `--`, or conversely, expand only the arguments after it.
+    ///
+    /// The `--` argument itself is never expanded, and option values are not recognized after it.
+    pub fn end_of_options(mut self, policy: EndOfOptions) -> Self {
+        self.end_of_options = policy;
+        self
+    }
+
+    /// Choose how patterns are matched: portable glob syntax, or Windows' native wildcards.
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Match file names case-sensitively. By default matching is case-insensitive, like Windows file systems.
+    ///
+    /// Case-insensitive matching folds characters with the same uppercase table as NTFS,
+    /// so non-ASCII names like `ÉTÉ.txt` match `été.*` in every engine.
+    pub fn case_sensitive(mut self, enabled: bool) -> Self {
+        self.case_sensitive = enabled;
+        self
+    }
+
+    /// Normalize patterns and file names before comparing them, so that `café*` matches files named in either normalization form.
+    /// Requires the `unicode-normalization` feature.
+    ///
+    /// Both forms give the same matches, since canonically equivalent names become identical in each. Matched paths are returned as stored.
+    /// It applies to the `Glob` and `Dos` engines. Names that aren't valid Unicode are compared as they are.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalization(mut self, form: Normalization) -> Self {
+        self.normalization = form;
+        self
+    }
+
+    /// Recognize `[a-z]` character classes in `Engine::Glob` patterns. Enabled by default.
+    ///
+    /// If a pattern with classes matches nothing, it's retried with brackets matching only themselves.
+    ///
+    /// When disabled, only `*` and `?` are special, so file names like `report [final].docx` can be matched
+    /// by `report [final]*` without quoting the brackets. The other engines always treat brackets literally.
+    pub fn brackets(mut self, enabled: bool) -> Self {
+        self.brackets = enabled;
+        self
+    }
+
+    /// Recognize ksh-style extended globs in `Engine::Glob` patterns, like in bash with `shopt -s extglob`. Disabled by default.
+    ///
+    /// `?(a|b)` matches zero or one of the alternatives, `*(a|b)` any number, `+(a|b)` one or more, `@(a|b)` exactly one,
+    /// and `!(a|b)` anything except them. Quoted parentheses and `|` stay literal, e.g. `"+(draft)"*.md`.
+    pub fn extglob(mut self, enabled: bool) -> Self {
+        self.extglob = enabled;
+        self
+    }
+
+    /// Pass an argument through as-is if it names an existing file exactly, even if it contains metacharacters.
+    ///
+    /// For example, if both `[a].txt` and `a.txt` exist, `[a].txt` refers only to the former.
+    pub fn exact_names(mut self, enabled: bool) -> Self {
+        self.exact_names = enabled;
+        self
+    }
+
+    /// Expand patterns of NTFS alternate data streams, like `file.txt:*` or `*.txt:Zone.Identifier`. Defaults to `false`.
+    ///
+    /// The part after the `:` is matched against stream names with Windows wildcard semantics, ignoring case,
+    /// and the part before it against files like any other pattern. The default unnamed stream isn't matched.
+    /// Stream types can be given too, e.g. `*:*:$DATA`. Matches are returned as `file.txt:stream`.
+    pub fn alternate_streams(mut self, enabled: bool) -> Self {
+        self.alternate_streams = enabled;
+        self
+    }
+
+    /// Skip paths that have already been matched by an earlier pattern, e.g. in `*.txt notes*`.
+    ///
+    /// The first occurrence is kept. Arguments that weren't matched in the file system, like repeated flags, are never removed.
+    pub fn unique(mut self, enabled: bool) -> Self {
+        self.unique = enabled;
+        self
+    }
+
+    /// Remove matches of the pattern from all expansions, e.g. `exclude("*.bak")` or `exclude("target/**")`. Can be called repeatedly.
+    ///
+    /// A pattern without separators is matched against file names, and others against whole paths, as they're returned.
+    /// They use the built-in syntax of `*`, `?` and `[a-z]`, with `**` matching any number of directories, and case sensitivity of the `Config`.
+    /// Arguments that aren't matches, like flags, are never removed. A pattern whose matches are all excluded is passed as-is.
+    pub fn exclude<P: AsRef<OsStr>>(mut self, pattern: P) -> Self {
+        self.excludes.push(pattern.as_ref().to_owned());
+        self
+    }
+
+    /// Treat arguments starting with `!`, like `!*.bak`, as exclusions of all other patterns, wherever they are on the command line. Defaults to `false`.
+    ///
+    /// So `* !*.bak` expands to everything except backups. The excluding arguments themselves are removed,
+    /// and work like `exclude()`. Arguments with any quotes, like `"!important"`, are kept as they are.
+    pub fn negated_args(mut self, enabled: bool) -> Self {
+        self.negated_args = enabled;
+        self
+    }
+
+    /// Choose how matches of a pattern are ordered.
+    pub fn sort_order(mut self, order: SortOrder) -> Self {
+        self.sort_order = order;
+        self
+    }
+
+    /// Keep the part of the pattern before the first wildcard spelled exactly as typed, e.g. `.\src\` or `../logs/`.
+    ///
+    /// By default matched paths are rebuilt from their components, which normalizes separators
+    /// and drops redundant ones, so `./src//*.rs` results in `.\src\main.rs`.
+    pub fn preserve_prefix(mut self, enabled: bool) -> Self {
+        self.preserve_prefix = enabled;
+        self
+    }
+
+    /// Match relative patterns in this directory instead of the current directory of the process,
+    /// e.g. for command lines forwarded from another process, like a second instance of a single-instance application.
+    ///
+    /// The matches are joined with the directory, so they can be used regardless of the current directory.
+    /// Patterns with a root or a drive, like `\logs\*` or `D:*.txt`, aren't affected, and arguments that aren't matches are passed as-is.
+    pub fn base_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.base_dir = Some(dir.into());
+        self
+    }
+
+    /// Choose which path separators matched paths use. Both `/` and `\` are always accepted in patterns.
+    ///
+    /// Arguments passed through as-is, e.g. when a pattern doesn't match anything, are not changed.
+    pub fn separators(mut self, style: Separators) -> Self {
+        self.separators = style;
+        self
+    }
+
+    /// Return matches as absolute, full or canonical paths, instead of in the form of the pattern.
+    ///
+    /// Arguments passed through as-is, e.g. when a pattern doesn't match anything, are not changed.
+    pub fn path_form(mut self, form: PathForm) -> Self {
+        self.path_form = form;
+        self
+    }
+
+    /// Return matches too long for legacy Windows APIs as absolute `\\?\` paths.
+    ///
+    /// Matching itself always works in deep directory trees, but programs using
+    /// APIs limited to `MAX_PATH` characters may fail to open such paths unless they're extended-length paths.
+    ///
+    /// Matches with names ending with a dot or a space are always returned as `\\?\` paths, regardless of this setting,
+    /// since Windows would strip the ending from other paths, and open a different file.
+    pub fn long_path_prefix(mut self, enabled: bool) -> Self {
+        self.long_path_prefix = enabled;
+        self
+    }
+
+    /// Convert file names in matched paths to their long or short form, so that messages show the names users know.
+    ///
+    /// Paths that can't be converted, e.g. because short names are disabled on the volume, are left unchanged. No effect outside of Windows.
+    pub fn name_form(mut self, form: NameForm) -> Self {
+        self.name_form = form;
+        self
+    }
+
+    /// Filter wildcard matches by the hidden attribute. Files named explicitly, without wildcards, are never filtered.
+    ///
+    /// Outside of Windows, names starting with a dot count as hidden.
+    pub fn hidden_files(mut self, filter: AttributeFilter) -> Self {
+        self.hidden_files = filter;
+        self
+    }
+
+    /// Filter wildcard matches by the system attribute, like `hidden_files()`.
+    pub fn system_files(mut self, filter: AttributeFilter) -> Self {
+        self.system_files = filter;
+        self
+    }
+
+    /// Make `**` descend into symlinks, junctions and mount points that point to directories. Defaults to `false`.
+    ///
+    /// Links that lead back to a directory already being searched are skipped, so loops can't recurse forever.
+    /// Other wildcards match links to directories regardless of this setting.
+    /// Matches found through a link are reported as `Origin::Linked`.
+    pub fn follow_links(mut self, enabled: bool) -> Self {
+        self.follow_links = enabled;
+        self
+    }
+
+    /// Limit how many directories deep `**` goes. Unlimited by default.
+    ///
+    /// With `0`, `**` only matches the directory it's in, with `1` also its subdirectories, and so on.
+    /// Wildcards after `**` can still match names one level below the deepest directory, so `max_depth(0)` makes `**/*.rs` the same as `*.rs`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Whether patterns match files, directories, or both. Files named explicitly, without wildcards, are never filtered.
+    ///
+    /// The file system reports the types while listing directories, so this is cheaper than checking each result afterwards.
+    pub fn entry_types(mut self, types: EntryTypes) -> Self {
+        self.entry_types = types;
+        self
+    }
+
+    /// Expand wildcards in quoted arguments too, e.g. `"My Documents\*.docx"`. Defaults to `false`.
+    ///
+    /// By default quotes make wildcards literal, as in Unix shells, but Windows users often quote paths only because of spaces.
+    /// With this enabled, quotes can't be used to pass a literal `*` or `?` anymore.
+    pub fn expand_quoted(mut self, enabled: bool) -> Self {
+        self.expand_quoted = enabled;
+        self
+    }
+
+    /// Lets users pass a literal metacharacter without quotes, e.g. `^*.txt` with `EscapeChar::Caret`. Defaults to `EscapeChar::None`.
+    ///
+    /// The escape character is removed before `*`, `?`, `[`, `]`, `{`, `}`, `(`, `)`, `|` or another escape character, and kept everywhere else,
+    /// so it can still appear in file names. Arguments with escapes keep quoted wildcards literal too, even with `expand_quoted()`.
+    ///
+    /// It applies only to command lines parsed by this crate, not to arguments that are already split, like in `Config::expand()`.
+    /// Note that `cmd.exe` itself removes unquoted `^` before the program sees it, so there the user has to type `^^*`.
+    pub fn escape_char(mut self, escape: EscapeChar) -> Self {
+        self.escape_char = escape;
+        self
+    }
+
+    /// Expand only arguments for which the function returns `true`, and pass the others through as-is.
+    ///
+    /// The function gets the index of the argument in its source, where the program name is `0` for `args()`,
+    /// and the argument as given, without the quotes. Applications know which of their arguments are paths,
+    /// e.g. not the value after `--format`, or a subcommand.
+    ///
+    /// ```rust
+    /// // `app grep <pattern> <files>...`
+    /// let args = wild::Config::new().expand_if(|index, _| index >= 3).args();
+    /// ```
+    pub fn expand_if<F>(mut self, predicate: F) -> Self where F: Fn(usize, &OsStr) -> bool + Send + Sync + 'static {
+        self.expand_predicate = Some(ExpandPredicate(Arc::new(predicate)));
+        self
+    }
+
+    /// Expand only arguments with indexes in the range, and pass the others through as-is. All are expanded by default.
+    ///
+    /// Indexes are the same as in `expand_if()`, e.g. `expand_range(2..)` expands everything after the subcommand of `app build *.rs`.
+    /// Both can be used together, and then an argument has to pass both.
+    ///
+    /// ```rust
+    /// let args = wild::Config::new().expand_range(2..).args();
+    /// ```
+    pub fn expand_range<R: RangeBounds<usize>>(mut self, range: R) -> Self {
+        self.expand_range = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Don't expand anything if the program has been started from MSYS2, Git Bash or Cygwin. Defaults to `false`.
+    ///
+    /// These shells expand wildcards themselves, so a second expansion would match patterns that the user has deliberately quoted.
+    /// They're detected by the `MSYSTEM` and `SHELL` environment variables, which are inherited,
+    /// so `cmd.exe` started from such a shell is detected too.
+    pub fn detect_unix_shell(mut self, enabled: bool) -> Self {
+        self.detect_unix_shell = enabled;
+        self
+    }
+
+    /// Read the command line from the given function instead of `GetCommandLineW`, e.g. to test argument handling with any command line.
+    ///
+    /// The function returns the whole command line in UTF-16, including the program name, or `None` if it's not available.
+    /// On non-Windows platforms the command line is only split, without expansion.
+    ///
+    /// ```rust
+    /// fn command_line() -> Option<Vec<u16>> {
+    ///     Some(r#"prog.exe "a b" c"#.encode_utf16().collect())
+    /// }
+    /// let args: Vec<_> = wild::Config::new().command_line_source(command_line).args().collect();
+    /// assert_eq!(args, ["prog.exe", "a b", "c"]);
+    /// ```
+    pub fn command_line_source(mut self, source: fn() -> Option<Vec<u16>>) -> Self {
+        self.command_line_source = Some(source);
+        self
+    }
+
+    /// What to return for an empty command line, which some hosts start programs with, e.g. GUI programs started by services.
+    /// Defaults to `EmptyCommandLine::ProgramPath`.
+    ///
+    /// A command line with only spaces and tabs is empty too. It would otherwise have no arguments, not even the program name.
+    pub fn empty_command_line(mut self, policy: EmptyCommandLine) -> Self {
+        self.empty_command_line = policy;
+        self
+    }
+
+    /// Look for matches in the given file system instead of the disk, e.g. in a `MemoryFs` to test how patterns expand.
+    ///
+    /// `Engine::Native` can only search the disk, so with a custom file system it works like `Engine::Glob`.
+    ///
+    /// ```rust
+    /// let fs = wild::MemoryFs::new(&["a.txt", "b.txt", "c.rs"]);
+    /// let args: Vec<_> = wild::Config::new().vfs(fs).expand(vec!["*.txt".into()]).collect();
+    /// # #[cfg(windows)]
+    /// assert_eq!(args, ["a.txt", "b.txt"]);
+    /// ```
+    pub fn vfs<V: Vfs + 'static>(mut self, vfs: V) -> Self {
+        self.vfs = Some(Arc::new(vfs));
+        self
+    }
+
+    /// Match patterns with the given syntax instead of the `engine()`.
+    ///
+    /// Arguments with `*` or `?` in quotes are still passed as-is.
+    ///
+    /// ```rust
+    /// use std::ffi::OsStr;
+    ///
+    /// /// `%` instead of `*`, for a shell that expands `*` itself
+    /// #[derive(Debug)]
+    /// struct Percent;
+    ///
+    /// impl wild::PatternMatcher for Percent {
+    ///     fn is_pattern(&self, pattern: &OsStr) -> bool {
+    ///         pattern.to_string_lossy().contains('%')
+    ///     }
+    ///
+    ///     fn compile(&self, component: &OsStr) -> Option<wild::NameMatcher> {
+    ///         let component = component.to_string_lossy().into_owned();
+    ///         let (prefix, suffix) = component.split_once('%')?;
+    ///         let (prefix, suffix) = (prefix.to_owned(), suffix.to_owned());
+    ///         Some(Box::new(move |name: &OsStr| {
+    ///             let name = name.to_string_lossy();
+    ///             name.len() >= prefix.len() + suffix.len() && name.starts_with(&prefix) && name.ends_with(&suffix)
+    ///         }))
+    ///     }
+    /// }
+    ///
+    /// let args = wild::Config::new().matcher(Percent).args();
+    /// ```
+    pub fn matcher<M: PatternMatcher + 'static>(mut self, matcher: M) -> Self {
+        self.matcher = Some(Arc::new(matcher));
+        self
+    }
+
+    /// Call the given function for every directory that couldn't be searched, e.g. because of missing permissions,
+    /// with the pattern being expanded, the directory, and the error.
+    ///
+    /// By default such directories are skipped silently, as if they were empty. Directories that don't exist aren't errors.
+    /// The function can print a warning, count or collect the errors, or exit the program.
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// let errors = Arc::new(Mutex::new(Vec::new()));
+    /// let collected = errors.clone();
+    /// let args: Vec<_> = wild::Config::new().on_error(move |pattern, dir, err| {
+    ///     collected.lock().unwrap().push(format!("{}: can't search {}: {}", pattern.to_string_lossy(), dir.display(), err));
+    /// }).args().collect();
+    /// for error in errors.lock().unwrap().iter() {
+    ///     eprintln!("warning: {}", error);
+    /// }
+    /// ```
+    pub fn on_error<F>(mut self, callback: F) -> Self where F: Fn(&OsStr, &Path, &io::Error) + Send + Sync + 'static {
+        self.on_error = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Call the given function with every argument that has unquoted wildcards, but didn't match anything, and is passed on as-is.
+    ///
+    /// Shells and GNU tools warn about such patterns, since they're usually mistyped.
+    ///
+    /// ```rust
+    /// fn warn(pattern: &std::ffi::OsStr) {
+    ///     eprintln!("warning: '{}' did not match any files", pattern.to_string_lossy());
+    /// }
+    /// let args = wild::Config::new().on_no_match(warn).args();
+    /// ```
+    pub fn on_no_match<F>(mut self, callback: F) -> Self where F: Fn(&OsStr) + Send + Sync + 'static {
+        self.on_no_match = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Whether to check matches and literal arguments for reserved device names, like `CON`, `NUL`, `COM1` or `nul.txt`,
+    /// and keep or remove them. Defaults to `DeviceNames::Keep`, which doesn't check them.
+    ///
+    /// Opening such a path opens the device in any directory, so a file matched by a wildcard that was created with such a name
+    /// through a `\\?\` path, or an argument like `con`, can make naive code hang waiting for input or write to nowhere.
+    /// Checked names are passed to `Config::on_device_name`. Option values, like `--out=NUL`, aren't paths, so they aren't checked.
+    ///
+    /// ```rust
+    /// fn warn(arg: &std::ffi::OsStr) {
+    ///     eprintln!("warning: skipping '{}', a reserved device name", arg.to_string_lossy());
+    /// }
+    /// let args = wild::Config::new().device_names(wild::DeviceNames::Skip).on_device_name(warn).args();
+    /// ```
+    pub fn device_names(mut self, policy: DeviceNames) -> Self {
+        self.device_names = policy;
+        self
+    }
+
+    /// Call the given function with every argument that is a reserved device name, if `Config::device_names` checks them.
+    pub fn on_device_name<F>(mut self, callback: F) -> Self where F: Fn(&OsStr) + Send + Sync + 'static {
+        self.on_device_name = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Call the given function after every listed directory and expanded argument, e.g. to show progress of a slow `**` search.
+    ///
+    /// It's called on the thread doing the expansion, so it should return quickly.
+    pub fn progress<F>(mut self, callback: F) -> Self where F: Fn(Progress) + Send + Sync + 'static {
+        self.progress = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Stop the expansion when the token is cancelled, e.g. from a GUI thread.
+    ///
+    /// Listing stops at the next directory, and the iterators return the matches found so far.
+    /// Arguments that haven't been expanded yet are returned as-is.
+    ///
+    /// ```rust
+    /// let token = wild::CancelToken::new();
+    /// let args = wild::Config::new().cancel_token(token.clone()).args();
+    /// // in another thread
+    /// token.cancel();
+    /// ```
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Skip wildcard matches excluded by `.gitignore` and `.ignore` files, and don't search ignored directories with `**`.
+    /// Defaults to `false`. Requires the `ignore` feature.
+    ///
+    /// Rules are read from the directories of the matches and their parents, up to the root of the git repository.
+    /// Hidden files like `.git` aren't ignored by this, use `hidden_files(AttributeFilter::Exclude)` to skip them too.
+    #[cfg(feature = "ignore")]
+    pub fn gitignore(mut self, enabled: bool) -> Self {
+        self.gitignore = enabled;
+        self
+    }
+
+    /// List sibling directories concurrently, in a pool of the given number of threads, or one per CPU if it's `0`.
+    /// Requires the `rayon` feature.
+    ///
+    /// Searching large trees with `**`, especially on network shares, is limited by the latency of each listing,
+    /// so even the sequential iterators like `args()` get their matches much sooner. The matches are in the same order,
+    /// and arguments are still expanded one by one as they're taken. `par_args_os()` expands arguments in parallel too.
+    /// If the threads can't be started, directories are listed one by one.
+    #[cfg(feature = "rayon")]
+    pub fn parallel_walk(mut self, threads: usize) -> Self {
+        self.walk_pool = ::rayon::ThreadPoolBuilder::new().num_threads(threads).build().ok().map(Arc::new);
+        self
+    }
+
+    /// Limit how many paths a single pattern may match, to guard against accidental runs like `C:\**\*`.
+    /// Unlimited by default.
+    ///
+    /// Directories are listed only until there is one match too many, also in the middle of a `**` search,
+    /// so the limit bounds the memory and time a pattern can take too. There's no separate memory limit.
+    ///
+    /// Iterators like `args_os()` then return just the first `limit` matches of the pattern, and pass the `TooManyMatches` error
+    /// naming it to `on_too_many_matches()`. To get the errors instead of the matches, use `checked_args_os()` or `args_vec()`,
+    /// or the `Err` results of `glob_os()`.
+    pub fn max_results(mut self, limit: usize) -> Self {
+        self.max_results = Some(limit);
+        self
+    }
+
+    /// Call the given function for every pattern that matched more paths than allowed by `max_results()`.
+    ///
+    /// ```rust
+    /// fn warn(err: &wild::TooManyMatches) {
+    ///     eprintln!("warning: {}, using only the first ones", err);
+    /// }
+    /// let args = wild::Config::new().max_results(10_000).on_too_many_matches(warn).args();
+    /// ```
+    pub fn on_too_many_matches<F>(mut self, callback: F) -> Self where F: Fn(&TooManyMatches) + Send + Sync + 'static {
+        self.on_too_many_matches = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Returns the program arguments expanded with these settings. See `wild::args()`.
+    ///
+    /// Panics if any argument (or its glob expansion) is not valid Unicode.
+    pub fn args(&self) -> Args {
+        Args::new(self.args_os())
+    }
+
+    /// Returns the program arguments expanded with these settings. See `wild::try_args()`.
+    pub fn try_args(&self) -> TryArgs {
+        TryArgs::new(self.args_os())
+    }
+
+    /// Returns the program arguments expanded with these settings. See `wild::args_lossy()`.
+    pub fn args_lossy(&self) -> ArgsLossy {
+        ArgsLossy::new(self.args_os())
+    }
+
+    /// Returns the program arguments expanded with these settings. See `wild::args_paths()`.
+    pub fn args_paths(&self) -> ArgsPaths {
+        ArgsPaths::new(self.args_os())
+    }
+
+    /// Returns the program arguments expanded with these settings, with metadata. See `wild::args_entries()`.
+    pub fn args_entries(&self) -> ArgsEntries {
+        ArgsEntries::new(self.args_os())
+    }
+
+    /// Returns expansions of the program arguments with these settings. See `wild::expansions()`.
+    pub fn expansions(&self) -> Expansions {
+        Expansions::new(self.args_os())
+    }
+
+    /// Returns the program arguments expanded with these settings. See `wild::args_os()`.
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub fn args_os(&self) -> ArgsOs {
+        match self.command_line_source.and_then(|source| source()) {
+            Some(line) if ::is_blank(&line) => ArgsOs::new(::empty_command_line_args(self)),
+            Some(line) => ArgsOs::new(::parse_command_line(&line)),
+            None => ArgsOs::new(std::env::args_os()),
+        }
+    }
+
+    /// Returns the program arguments expanded with these settings. See `wild::args_os()`.
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub fn args_os(&self) -> ArgsOs {
+        ArgsOs::new(::command_line_args(self.clone()))
+    }
+
+    /// Returns all program arguments expanded with these settings,
+    /// or an error if a pattern matched more paths than allowed by `max_results()`.
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub fn checked_args_os(&self) -> Result<Vec<OsString>, TooManyMatches> {
+        Ok(std::env::args_os().collect())
+    }
+
+    /// Returns all program arguments expanded with these settings,
+    /// or an error if a pattern matched more paths than allowed by `max_results()`.
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub fn checked_args_os(&self) -> Result<Vec<OsString>, TooManyMatches> {
+        let mut args = ::command_line_args(self.clone());
+        let mut checked = Vec::new();
+        while let Some(arg) = args.try_next_with_origin() {
+            checked.push(arg?.0);
+        }
+        Ok(checked)
+    }
+
+    /// Returns all program arguments expanded with these settings, or all errors of the expansion. See `wild::args_vec()`.
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub fn args_vec(&self) -> Result<Vec<OsString>, Error> {
+        Ok(self.args_os().collect())
+    }
+
+    /// Returns all program arguments expanded with these settings, or all errors of the expansion. See `wild::args_vec()`.
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub fn args_vec(&self) -> Result<Vec<OsString>, Error> {
+        ::command_line_args(self.clone()).try_collect()
+    }
+
+    /// Expands arguments read from a list, e.g. of `--files-from`, with these settings. See `wild::expand_lines()`.
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub fn expand_lines<R: Read>(&self, reader: R, separator: ListSeparator) -> io::Result<ArgsOs> {
+        let items = ::read_list(reader, separator)?;
+        let args: Vec<OsString> = match separator {
+            ListSeparator::Lines => items.iter().flat_map(|line| {
+                let line: Vec<u16> = line.encode_utf16().collect();
+                ::parse_command_line(&line).collect::<Vec<_>>()
+            }).collect(),
+            ListSeparator::Nul => items.into_iter().map(OsString::from).collect(),
+        };
+        Ok(ArgsOs::new(args))
+    }
+
+    /// Expands arguments read from a list, e.g. of `--files-from`, with these settings. See `wild::expand_lines()`.
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub fn expand_lines<R: Read>(&self, reader: R, separator: ListSeparator) -> io::Result<ArgsOs> {
+        Ok(ArgsOs::new(::list_args(&::read_list(reader, separator)?, separator, self.clone())))
+    }
+
+    /// Expands arguments from `lpCmdLine` of `WinMain` with these settings. See `wild::args_from_lpcmdline()`.
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub fn args_from_lpcmdline(&self, line: &[u16]) -> ArgsOs {
+        ArgsOs::new(::parse_command_line(line))
+    }
+
+    /// Expands arguments from `lpCmdLine` of `WinMain` with these settings. See `wild::args_from_lpcmdline()`.
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub fn args_from_lpcmdline(&self, line: &[u16]) -> ArgsOs {
+        ArgsOs::new(::split_args(line, self.clone()))
+    }
+
+    /// Returns the program arguments expanded in parallel with these settings. See `wild::par_args_os()`.
+    #[cfg(all(feature = "rayon", not(any(windows, target_os = "wasi"))))]
+    pub fn par_args_os(&self) -> ::rayon::vec::IntoIter<OsString> {
+        use rayon::iter::IntoParallelIterator;
+        std::env::args_os().collect::<Vec<_>>().into_par_iter()
+    }
+
+    /// Returns the program arguments expanded in parallel with these settings. See `wild::par_args_os()`.
+    #[cfg(all(feature = "rayon", any(windows, target_os = "wasi")))]
+    pub fn par_args_os(&self) -> ::rayon::vec::IntoIter<OsString> {
+        use rayon::iter::IntoParallelIterator;
+        ::command_line_args(self.clone()).par_expand().into_par_iter()
+    }
+
+    /// Returns the program arguments expanded with these settings as an async `Stream`. See `wild::args_stream()`.
+    #[cfg(feature = "stream")]
+    pub fn args_stream(&self) -> ::ArgsStream {
+        ::ArgsStream::new(self.args_os())
+    }
+
+    /// Compiles the pattern with these settings, to match names without the file system. See `wild::Matcher`.
+    pub fn compile(&self, pattern: &OsStr) -> Matcher {
+        Matcher::with_config(pattern, self)
+    }
+
+    /// Expands a single pattern with these settings. See `wild::glob_os()`.
+    pub fn glob_os(&self, pattern: &OsStr) -> GlobOs {
+        GlobOs::new(pattern.to_owned(), self.clone())
+    }
+
+    /// Expands arguments from any source with these settings. See `wild::expand()`.
+    pub fn expand<I>(&self, args: I) -> Expand<I::IntoIter> where I: IntoIterator<Item = OsString> {
+        Expand::new(args.into_iter(), self.clone())
+    }
+}
@@ -0,0 +1,41 @@
+//! Reserved DOS device names, like `CON` and `NUL`, which open a device instead of a file in any directory
+use std::ffi::OsStr;
+
+const DEVICES: &[&str] = &["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"];
+
+/// Whether the last component of the path is a device name. As in Windows before version 11, an extension,
+/// a stream name and trailing spaces are ignored, so `nul.txt` and `CON :x` are devices too.
+pub(crate) fn is_device_name(path: &OsStr) -> bool {
+    let path = match path.to_str() {
+        Some(path) => path,
+        // all device names are ASCII
+        None => return false,
+    };
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    // `D:con` is in the current directory of `D:`
+    let name = match name.as_bytes() {
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => &name[2..],
+        _ => name,
+    };
+    let name = name.split(['.', ':']).next().unwrap_or(name).trim_end_matches(' ');
+    if DEVICES.iter().any(|device| name.eq_ignore_ascii_case(device)) {
+        return true;
+    }
+    // `COM1` to `COM9` and `LPT1` to `LPT9`, also with superscript digits `¹`, `²` and `³`
+    match name.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("COM") || prefix.eq_ignore_ascii_case("LPT") => {
+            matches!(&name[3..], "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "¹" | "²" | "³")
+        },
+        _ => false,
+    }
+}
+
+#[test]
+fn detects_device_names() {
+    for name in &["CON", "nul", "nul.txt", r"C:\logs\Aux.log", "dir/com1", "LPT9.tar.gz", "COM¹", "con :stream", "D:prn", "conout$"] {
+        assert!(is_device_name(OsStr::new(name)), "{}", name);
+    }
+    for name in &["console", "COM0", "COM10", "nul/readme.md", "x.nul", "lpt", "C:", "a.con"] {
+        assert!(!is_device_name(OsStr::new(name)), "{}", name);
+    }
+}
@@ -0,0 +1,181 @@
+//! Windows wildcard semantics in pure Rust, emulating `FsRtlIsNameInExpression`
+use std::ffi::OsStr;
+#[cfg(windows)]
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+#[cfg(test)]
+use std::path::PathBuf;
+#[cfg(not(windows))]
+use globiter::LossyOsStrExt;
+use walk::{has_wildcards, walk, DirCache, Entry, Found, Matcher, Part};
+#[cfg(test)]
+use walk::paths;
+use case::upcase;
+use config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Char(u16),
+    /// `*`, any number of characters
+    Star,
+    /// `DOS_STAR`, any number of characters up to the last `.`
+    DosStar,
+    /// `DOS_QM`, any character, or nothing before a `.` or the end
+    DosQm,
+    /// `DOS_DOT`, a `.`, or nothing at the end
+    DosDot,
+}
+
+/// A pattern component compiled into an NT expression
+pub(crate) struct DosPattern {
+    tokens: Vec<Token>,
+}
+
+const DOT: u16 = b'.' as u16;
+
+impl DosPattern {
+    /// Translates wildcards the same way `FindFirstFileExW` does before passing them to the kernel
+    pub fn new(pattern: &OsStr) -> Self {
+        let pattern: Vec<u16> = pattern.encode_wide().collect();
+        let tokens = pattern.iter().enumerate().map(|(i, &c)| {
+            let next = pattern.get(i + 1).cloned();
+            match c {
+                c if c == u16::from(b'?') => Token::DosQm,
+                DOT if next.is_none() || next == Some(u16::from(b'*')) || next == Some(u16::from(b'?')) => Token::DosDot,
+                c if c == u16::from(b'*') && next == Some(DOT) => Token::DosStar,
+                c if c == u16::from(b'*') => Token::Star,
+                c => Token::Char(c),
+            }
+        }).collect();
+        Self { tokens }
+    }
+
+    pub fn matches(&self, name: &OsStr, case_sensitive: bool) -> bool {
+        let name: Vec<u16> = name.encode_wide().collect();
+        let mut memo = vec![None; (self.tokens.len() + 1) * (name.len() + 1)];
+        let eq = if case_sensitive { u16::eq } else { eq_ignore_case };
+        matches_at(&self.tokens, &name, 0, 0, eq, &mut memo)
+    }
+}
+
+fn eq_ignore_case(a: &u16, b: &u16) -> bool {
+    upcase(*a) == upcase(*b)
+}
+
+fn matches_at(tokens: &[Token], name: &[u16], t: usize, n: usize, eq: fn(&u16, &u16) -> bool, memo: &mut Vec<Option<bool>>) -> bool {
+    let key = t * (name.len() + 1) + n;
+    if let Some(result) = memo[key] {
+        return result;
+    }
+    let at_end = n == name.len();
+    let result = match tokens.get(t) {
+        None => at_end,
+        Some(&Token::Char(c)) => !at_end && eq(&c, &name[n]) && matches_at(tokens, name, t + 1, n + 1, eq, memo),
+        Some(&Token::Star) => matches_at(tokens, name, t + 1, n, eq, memo) ||
+            (!at_end && matches_at(tokens, name, t, n + 1, eq, memo)),
+        Some(&Token::DosStar) => matches_at(tokens, name, t + 1, n, eq, memo) ||
+            // can't consume the last dot
+            (!at_end && (name[n] != DOT || name[n + 1..].contains(&DOT)) && matches_at(tokens, name, t, n + 1, eq, memo)),
+        Some(&Token::DosQm) => if at_end || name[n] == DOT {
+            matches_at(tokens, name, t + 1, n, eq, memo)
+        } else {
+            matches_at(tokens, name, t + 1, n + 1, eq, memo)
+        },
+        Some(&Token::DosDot) => if at_end {
+            matches_at(tokens, name, t + 1, n, eq, memo)
+        } else {
+            name[n] == DOT && matches_at(tokens, name, t + 1, n + 1, eq, memo)
+        },
+    };
+    memo[key] = Some(result);
+    result
+}
+
+/// Windows wildcard semantics
+pub(crate) struct DosMatcher {
+    pub case_sensitive: bool,
+    #[cfg(feature = "unicode-normalization")]
+    pub normalization: ::config::Normalization,
+}
+
+impl DosMatcher {
+    fn new(config: &Config) -> Self {
+        Self {
+            case_sensitive: config.case_sensitive,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: config.normalization,
+        }
+    }
+}
+
+#[cfg(not(feature = "unicode-normalization"))]
+impl Matcher for DosMatcher {
+    type Pattern = DosPattern;
+
+    fn compile(&self, component: &OsStr) -> Part<DosPattern> {
+        if has_wildcards(component) {
+            Part::Pattern(DosPattern::new(component))
+        } else {
+            Part::Literal
+        }
+    }
+
+    fn list(&self, dir: &Path, pattern: &DosPattern, cache: &DirCache) -> Vec<Entry> {
+        cache.list_matching(dir, |name| pattern.matches(name, self.case_sensitive))
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+impl Matcher for DosMatcher {
+    type Pattern = DosPattern;
+
+    fn compile(&self, component: &OsStr) -> Part<DosPattern> {
+        if has_wildcards(component) || ::case::needs_normalization(component, self.normalization) {
+            Part::Pattern(DosPattern::new(&::case::normalize(component, self.normalization)))
+        } else {
+            Part::Literal
+        }
+    }
+
+    fn list(&self, dir: &Path, pattern: &DosPattern, cache: &DirCache) -> Vec<Entry> {
+        cache.list_matching(dir, |name| pattern.matches(&::case::normalize(name, self.normalization), self.case_sensitive))
+    }
+}
+
+/// Expands a pattern with Windows wildcard semantics, like `Engine::Native` but without matching 8.3 names.
+pub(crate) fn find(pattern: &OsStr, config: &Config, cache: &DirCache) -> Vec<Found> {
+    walk(pattern, &DosMatcher::new(config), config, cache)
+}
+
+#[cfg(test)]
+fn dos_match(pattern: &str, name: &str) -> bool {
+    DosPattern::new(OsStr::new(pattern)).matches(OsStr::new(name), false)
+}
+
+#[test]
+fn dos_semantics() {
+    assert!(dos_match("*.*", "abc"));
+    assert!(dos_match("*.*", "a.b.c"));
+    assert!(dos_match("*.*", ".gitignore"));
+    assert!(dos_match("*.", "abc"));
+    assert!(!dos_match("*.", "abc.txt"));
+    assert!(dos_match("*.txt", "a.b.TXT"));
+    assert!(!dos_match("*.txt", "a.txt.bak"));
+    assert!(dos_match("a?.txt", "a.txt"));
+    assert!(dos_match("a?.txt", "ab.txt"));
+    assert!(!dos_match("a?.txt", "abc.txt"));
+    assert!(dos_match("????", "ab"));
+    assert!(!dos_match("????", "abcde"));
+    assert!(dos_match("*", "ÉTÉ"));
+    assert!(dos_match("été", "ÉTÉ"));
+    assert!(dos_match("[a]*", "[A].txt"));
+    assert!(!DosPattern::new(OsStr::new("*.TXT")).matches(OsStr::new("a.txt"), true));
+}
+
+#[test]
+fn finds_with_dos_semantics() {
+    assert_eq!(vec![PathBuf::from("Cargo.toml")], paths(find(OsStr::new("cargo.tom?"), &Config::new(), &DirCache::default())));
+    assert!(paths(find(OsStr::new("cargo.tom?"), &Config::new().case_sensitive(true), &DirCache::default())).is_empty());
+    assert_eq!(vec![PathBuf::from("src/dos.rs")], paths(find(OsStr::new("s?c/d?s.*"), &Config::new(), &DirCache::default())));
+    assert!(paths(find(OsStr::new("*."), &Config::new(), &DirCache::default())).contains(&PathBuf::from("src")));
+}
@@ -0,0 +1,81 @@
+//! Drive-relative paths like `D:*.txt`, which are relative to the current directory of drive `D:`
+use std::ffi::{OsStr, OsString};
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+#[cfg(not(windows))]
+use globiter::{LossyOsStrExt, LossyOsStringExt};
+use globiter::{push_escaped, Arg};
+
+fn is_separator(c: u16) -> bool {
+    c == u16::from(b'\\') || c == u16::from(b'/')
+}
+
+/// Whether the path starts with a drive letter that isn't followed by a separator
+fn is_drive_relative(path: &[u16]) -> bool {
+    match path {
+        [letter, colon, rest @ ..] => *letter < 128 && (*letter as u8).is_ascii_alphabetic() &&
+            *colon == u16::from(b':') && !rest.first().is_some_and(|&c| is_separator(c)),
+        _ => false,
+    }
+}
+
+/// Replaces the `D:` of a drive-relative argument with the current directory of that drive.
+///
+/// `current_dir_of` gets the `D:` prefix and returns the absolute path it refers to.
+pub(crate) fn resolve<F>(arg: &Arg, current_dir_of: F) -> Option<Arg> where F: FnOnce(&OsStr) -> Option<OsString> {
+    let text: Vec<u16> = arg.text().encode_wide().collect();
+    if !is_drive_relative(&text) {
+        return None;
+    }
+    let mut dir: Vec<u16> = current_dir_of(&OsString::from_wide(&text[..2]))?.encode_wide().collect();
+    if !dir.last().is_some_and(|&c| is_separator(c)) {
+        dir.push(u16::from(b'\\'));
+    }
+    // letters and `:` are never escaped, so the pattern starts with the same `D:`
+    let pattern: Vec<u16> = arg.pattern.encode_wide().collect();
+    let mut escaped_dir = Vec::with_capacity(dir.len());
+    for &c in &dir {
+        push_escaped(&mut escaped_dir, c);
+    }
+    Some(Arg::new(
+        OsString::from_wide(&[&escaped_dir[..], &pattern[2..]].concat()),
+        OsString::from_wide(&[&dir[..], &text[2..]].concat()),
+        arg.quoted,
+        arg.quoted_wildcard,
+    ))
+}
+
+/// Absolute path, resolved the same way as by `cmd.exe`, using per-drive current directories
+#[cfg(windows)]
+pub(crate) fn full_path(path: &OsStr) -> Option<OsString> {
+    let path: Vec<u16> = path.encode_wide().chain(Some(0)).collect();
+    let mut buf = vec![0u16; 261];
+    loop {
+        let len = unsafe {
+            ::sys::GetFullPathNameW(path.as_ptr(), buf.len() as u32, buf.as_mut_ptr(), ::std::ptr::null_mut())
+        } as usize;
+        if len == 0 {
+            return None;
+        }
+        if len < buf.len() {
+            return Some(OsString::from_wide(&buf[..len]));
+        }
+        // too small; `len` includes the terminator
+        buf.resize(len, 0);
+    }
+}
+
+#[test]
+fn resolves_drive_relative() {
+    let current_dir_of = |drive: &OsStr| {
+        assert_eq!("D:", drive);
+        Some(OsString::from("D:\\work\\[old]"))
+    };
+    let arg = resolve(&Arg::from(OsString::from("D:*.txt")), current_dir_of).unwrap();
+    assert_eq!("D:\\work\\[old]\\*.txt", &*arg.text());
+    assert_eq!("D:\\work\\[[]old[]]\\*.txt", arg.pattern);
+    assert!(resolve(&Arg::from(OsString::from("D:\\*.txt")), current_dir_of).is_none());
+    assert!(resolve(&Arg::from(OsString::from("D:/*.txt")), current_dir_of).is_none());
+    assert!(resolve(&Arg::from(OsString::from("*.txt")), current_dir_of).is_none());
+    assert!(resolve(&Arg::from(OsString::from("DD:*.txt")), current_dir_of).is_none());
+}
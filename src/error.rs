@@ -0,0 +1,81 @@
+use std::error::Error as StdError;
+use std::ffi::OsString;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// A pattern matched more paths than allowed by `Config::max_results`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TooManyMatches {
+    /// The argument as given, without the quotes
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::lossy"))]
+    pub pattern: OsString,
+    /// The configured limit
+    pub limit: usize,
+}
+
+impl fmt::Display for TooManyMatches {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pattern `{}` matches more than {} paths", self.pattern.to_string_lossy(), self.limit)
+    }
+}
+
+impl StdError for TooManyMatches {}
+
+/// A directory searched by a pattern couldn't be read. Part of `wild::Error`.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct UnreadableDir {
+    /// The argument as given, without the quotes
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::lossy"))]
+    pub pattern: OsString,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::lossy"))]
+    pub dir: PathBuf,
+    /// Serialized as its message
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::display"))]
+    pub error: io::Error,
+}
+
+impl fmt::Display for UnreadableDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pattern `{}` can't search `{}`: {}", self.pattern.to_string_lossy(), self.dir.display(), self.error)
+    }
+}
+
+impl StdError for UnreadableDir {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// All problems of an expansion, collected instead of stopping at the first one. Returned by `wild::args_vec()`.
+///
+/// It's never empty.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Error {
+    /// Patterns that matched more paths than allowed by `Config::max_results`
+    pub too_many_matches: Vec<TooManyMatches>,
+    /// Directories that patterns couldn't search, e.g. because of permissions.
+    /// Without this error they'd silently have no matches.
+    pub unreadable_dirs: Vec<UnreadableDir>,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let messages = self.too_many_matches.iter().map(ToString::to_string)
+            .chain(self.unreadable_dirs.iter().map(ToString::to_string));
+        for (i, message) in messages.enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            f.write_str(&message)?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Error {}
@@ -0,0 +1,56 @@
+//! Patterns removing matches, from `Config::exclude()` and `!pattern` arguments
+use std::path::Path;
+use config::Config;
+use namematch::Matcher;
+
+/// Compiled exclusion patterns of one expansion
+pub(crate) struct Exclusions {
+    /// Patterns without a separator match the file name, others the whole path
+    patterns: Vec<(bool, Matcher)>,
+}
+
+impl Exclusions {
+    pub fn new(config: &Config) -> Self {
+        let patterns = config.excludes.iter().map(|pattern| {
+            let matcher = Matcher::with_config(pattern, config);
+            (matcher.is_single_name(), matcher)
+        }).collect();
+        Self { patterns }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.patterns.iter().any(|&(name_only, ref matcher)| if name_only {
+            path.file_name().is_some_and(|name| matcher.is_match(name))
+        } else {
+            matcher.is_match(path.as_os_str())
+        })
+    }
+}
+
+#[test]
+fn excludes_names_and_paths() {
+    let exclusions = |patterns: &[&str]| Exclusions::new(&patterns.iter().fold(Config::new(), |config, pattern| config.exclude(pattern)));
+    let bak = exclusions(&["*.BAK"]);
+    assert!(bak.is_excluded(Path::new("notes.bak")));
+    assert!(bak.is_excluded(Path::new("./src/old/notes.bak")));
+    assert!(!bak.is_excluded(Path::new("notes.bak/readme.md")));
+    let paths = exclusions(&["target/**", "src/*/mod.rs"]);
+    assert!(paths.is_excluded(Path::new("target/debug/wild")));
+    assert!(paths.is_excluded(Path::new("./src/walk/mod.rs")));
+    assert!(!paths.is_excluded(Path::new("src/mod.rs")));
+    assert!(!paths.is_excluded(Path::new("lib/target/debug")));
+    assert!(!exclusions(&[]).is_excluded(Path::new("a")));
+}
+
+#[test]
+fn excludes_with_matcher_settings() {
+    let exclusions = |config: Config| Exclusions::new(&config.exclude("[draft]*"));
+    assert!(exclusions(Config::new().brackets(false)).is_excluded(Path::new("docs/[draft] notes.md")));
+    assert!(!exclusions(Config::new().brackets(false)).is_excluded(Path::new("docs/d notes.md")));
+    assert!(exclusions(Config::new()).is_excluded(Path::new("docs/d notes.md")));
+    assert!(!exclusions(Config::new().case_sensitive(true)).is_excluded(Path::new("D.md")));
+}
@@ -0,0 +1,96 @@
+//! C API for programs in other languages, enabled by the `ffi` feature. The header is `include/wild.h`.
+use std::os::raw::c_int;
+use std::ptr;
+use parser::{from_wide, split_program_name, to_wide};
+use config::Config;
+
+/// UTF-16 code unit, which is `wchar_t` on Windows. The header declares it as `uint16_t`, since `wchar_t` is 32-bit on Unix.
+pub type WChar = u16;
+
+/// Splits and glob-expands a UTF-16 command line, like `wild::args_os()` does with the command line of the current process.
+///
+/// Returns an array of `*argc` NUL-terminated arguments followed by a null pointer, like `argv` of `main`.
+/// The array has to be freed with `wild_free_argv()`. Returns null if `command_line` is null.
+///
+/// The program name, if any, is the first argument. Like in `wild::args_os()`, it's split by its own rules and never expanded.
+/// Outside of Windows the arguments are only split.
+///
+/// # Safety
+///
+/// `command_line` must be null or a NUL-terminated string, and `argc` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn wild_expand_command_line(command_line: *const WChar, argc: *mut c_int) -> *mut *mut WChar {
+    if command_line.is_null() {
+        return ptr::null_mut();
+    }
+    let line = ::std::slice::from_raw_parts(command_line, wide_len(command_line));
+    let (program_name, rest) = split_program_name(line);
+    let program_name = if line.is_empty() { None } else { Some(from_wide(program_name)) };
+    let mut argv: Vec<*mut WChar> = program_name.into_iter().chain(Config::global().args_from_lpcmdline(rest))
+        .map(|arg| Box::into_raw(to_wide(&arg).into_iter().chain(Some(0)).collect::<Box<[WChar]>>()) as *mut WChar)
+        .collect();
+    if !argc.is_null() {
+        *argc = argv.len() as c_int;
+    }
+    argv.push(ptr::null_mut());
+    Box::into_raw(argv.into_boxed_slice()) as *mut *mut WChar
+}
+
+/// Frees an array returned by `wild_expand_command_line()`. Does nothing if `argv` is null.
+///
+/// # Safety
+///
+/// `argv` must have been returned by `wild_expand_command_line()`, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn wild_free_argv(argv: *mut *mut WChar) {
+    if argv.is_null() {
+        return;
+    }
+    let mut len = 0;
+    while !(*argv.add(len)).is_null() {
+        let arg = *argv.add(len);
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(arg, wide_len(arg) + 1)));
+        len += 1;
+    }
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(argv, len + 1)));
+}
+
+unsafe fn wide_len(s: *const WChar) -> usize {
+    let mut len = 0;
+    while *s.add(len) != 0 {
+        len += 1;
+    }
+    len
+}
+
+#[cfg(test)]
+fn expanded(line: &str) -> Vec<String> {
+    let line: Vec<WChar> = line.encode_utf16().chain(Some(0)).collect();
+    unsafe {
+        let mut argc = 0;
+        let argv = wild_expand_command_line(line.as_ptr(), &mut argc);
+        let args = (0..argc as usize).map(|i| {
+            let arg = *argv.add(i);
+            String::from_utf16(::std::slice::from_raw_parts(arg, wide_len(arg))).unwrap()
+        }).collect();
+        assert!((*argv.add(argc as usize)).is_null());
+        wild_free_argv(argv);
+        args
+    }
+}
+
+#[test]
+fn expand_and_free() {
+    assert_eq!(expanded(r#"prog.exe "a b" c"#), ["prog.exe", "a b", "c"]);
+    assert!(expanded("").is_empty());
+    unsafe {
+        assert!(wild_expand_command_line(ptr::null(), &mut 0).is_null());
+    }
+}
+
+#[test]
+fn keeps_program_name() {
+    assert_eq!(expanded("Cargo.tom? x"), ["Cargo.tom?", "x"]);
+    assert_eq!(expanded(r#""C:\[x]\*\prog.exe" "a b""#), [r"C:\[x]\*\prog.exe", "a b"]);
+    assert_eq!(expanded(r#""C:\dir\" x"#), [r"C:\dir\", "x"]);
+}
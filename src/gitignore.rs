@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+/// Rules from `.gitignore` and `.ignore` files of one directory
+#[derive(Debug)]
+struct DirRules {
+    rules: Gitignore,
+    /// The directory contains `.git`, so rules of its parents don't apply
+    is_repo_root: bool,
+}
+
+/// Ignore rules of directories, loaded once per directory as they're needed
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IgnoreRules {
+    dirs: Arc<Mutex<HashMap<PathBuf, Arc<DirRules>>>>,
+}
+
+impl IgnoreRules {
+    fn for_dir(&self, dir: &Path) -> Arc<DirRules> {
+        if let Some(rules) = self.dirs.lock().unwrap_or_else(PoisonError::into_inner).get(dir) {
+            return rules.clone();
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        for name in &[".gitignore", ".ignore"] {
+            let file = dir.join(name);
+            if file.exists() {
+                // like git, use whatever rules could be parsed
+                let _ = builder.add(file);
+            }
+        }
+        let rules = Arc::new(DirRules {
+            rules: builder.build().unwrap_or_else(|_| Gitignore::empty()),
+            is_repo_root: dir.join(".git").exists(),
+        });
+        self.dirs.lock().unwrap_or_else(PoisonError::into_inner).insert(dir.to_owned(), rules.clone());
+        rules
+    }
+
+    /// Whether rules of the path's parent directories, up to the root of its repository, exclude it.
+    /// Rules of deeper directories take precedence.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = absolute(path);
+        for dir in path.ancestors().skip(1) {
+            let rules = self.for_dir(dir);
+            match rules.rules.matched(&path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {},
+            }
+            if rules.is_repo_root {
+                break;
+            }
+        }
+        false
+    }
+}
+
+/// Joined with the current directory, without `.` and `..`, so that its ancestors are the real parent directories
+fn absolute(path: &Path) -> PathBuf {
+    let path = ::std::env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_owned());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => { normalized.pop(); },
+            _ => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[test]
+fn ignores_build_output() {
+    let rules = IgnoreRules::default();
+    assert!(rules.is_ignored(Path::new("target"), true));
+    assert!(rules.is_ignored(Path::new("src/../target"), true));
+    assert!(!rules.is_ignored(Path::new("src"), true));
+    assert!(!rules.is_ignored(Path::new("src/lib.rs"), false));
+}
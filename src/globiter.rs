@@ -1,51 +1,316 @@
-use std::ffi::OsString;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
 use parser;
+use config::EscapeChar;
+use pattern::{ESCAPED, METACHARACTERS};
 
+#[derive(Debug, Clone)]
 pub(crate) struct Arg {
     pub pattern: OsString,
-    pub text: OsString,
+    /// How to get the argument as given from the pattern. It's needed only if the pattern isn't expanded, so it's derived on demand.
+    literal: Literal,
+    /// Some of it was in quotes
+    pub quoted: bool,
+    /// `*` or `?` was in quotes or escaped, so it can't match any Windows file name
+    pub quoted_wildcard: bool,
+    /// Some metacharacter was escaped with the escape character, so the argument keeps its quoting even with `Config::expand_quoted`
+    pub char_escaped: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    /// Nothing was escaped, so it's the pattern
+    Pattern,
+    /// Only quoted metacharacters were escaped, so they can be unescaped
+    Unescaped,
+    /// The text, if it can't be told from the pattern, e.g. an unquoted `[*]` next to escaped quoted characters
+    Text(OsString),
 }
 
 /// Iterator retuning glob-escaped arguments. Call `args()` to obtain it.
 #[must_use]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct GlobArgs<'a> {
     line: &'a [u16],
+    /// Buffers reused for every argument, so that only the final strings are allocated
+    text: Vec<u16>,
+    pattern: Vec<u16>,
+    /// From `Config::escape_char`
+    escape: Option<u16>,
 }
 
 #[cfg(windows)]
-use std::os::windows::ffi::OsStringExt;
-
-/// This is used only in tests on non-Windows
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 #[cfg(not(windows))]
-trait LossyOsStringExt {
-    fn from_wide(wide: &[u16]) -> OsString {
-        OsString::from(String::from_utf16_lossy(wide))
+pub(crate) use parser::{LossyOsStrExt, LossyOsStringExt};
+
+/// An argument that didn't come from the command line, so none of its metacharacters are quoted
+impl From<OsString> for Arg {
+    fn from(text: OsString) -> Self {
+        Arg {
+            pattern: text,
+            literal: Literal::Pattern,
+            quoted: false,
+            quoted_wildcard: false,
+            char_escaped: false,
+        }
     }
 }
 
-#[cfg(not(windows))]
-impl LossyOsStringExt for OsString {}
+fn is_option_name_char(c: u16) -> bool {
+    (c < 128 && (c as u8).is_ascii_alphanumeric()) || c == u16::from(b'-') || c == u16::from(b'_')
+}
+
+impl Arg {
+    pub fn new(pattern: OsString, text: OsString, quoted: bool, quoted_wildcard: bool) -> Self {
+        let literal = if pattern == text { Literal::Pattern } else { Literal::Text(text) };
+        Arg { pattern, literal, quoted, quoted_wildcard, char_escaped: false }
+    }
+
+    /// The argument as given, without the quotes
+    pub fn text(&self) -> Cow<'_, OsStr> {
+        match self.literal {
+            Literal::Pattern => Cow::Borrowed(&self.pattern),
+            Literal::Unescaped => Cow::Owned(unescape(&self.pattern)),
+            Literal::Text(ref text) => Cow::Borrowed(text),
+        }
+    }
+
+    pub fn into_text(self) -> OsString {
+        match self.literal {
+            Literal::Pattern => self.pattern,
+            Literal::Unescaped => unescape(&self.pattern),
+            Literal::Text(text) => text,
+        }
+    }
+
+    /// Splits `--name=value` (or `/name:value` if `slash` is set) into the option prefix,
+    /// including the separator, and the value.
+    ///
+    /// Option names are never escaped, so the prefix has the same length in `pattern` and `text`.
+    pub fn split_option(&self, slash: bool) -> Option<(OsString, Arg)> {
+        let text: Vec<u16> = self.text().encode_wide().collect();
+        let (name_start, separator) = if text.starts_with(&[u16::from(b'-'), u16::from(b'-')]) {
+            (2, b'=')
+        } else if slash && text.first() == Some(&u16::from(b'/')) {
+            (1, b':')
+        } else {
+            return None;
+        };
+        let name_len = text[name_start..].iter().take_while(|&&c| is_option_name_char(c)).count();
+        let value_start = name_start + name_len + 1;
+        if name_len == 0 || text.get(value_start - 1) != Some(&u16::from(separator)) {
+            return None;
+        }
+        let pattern: Vec<u16> = self.pattern.encode_wide().collect();
+        let value = match self.literal {
+            Literal::Text(_) => Arg::new(OsString::from_wide(&pattern[value_start..]), OsString::from_wide(&text[value_start..]), self.quoted, self.quoted_wildcard),
+            ref literal => Arg {
+                pattern: OsString::from_wide(&pattern[value_start..]),
+                literal: literal.clone(),
+                quoted: self.quoted,
+                quoted_wildcard: self.quoted_wildcard,
+                char_escaped: self.char_escaped,
+            },
+        };
+        Some((OsString::from_wide(&text[..value_start]), value))
+    }
+
+    /// The same argument with quoted metacharacters treated like unquoted ones.
+    ///
+    /// The escape character is explicit, so an argument using it is kept as it is.
+    pub fn without_quoting(self) -> Arg {
+        if self.char_escaped {
+            return self;
+        }
+        Arg {
+            quoted: self.quoted,
+            pattern: self.into_text(),
+            literal: Literal::Pattern,
+            quoted_wildcard: false,
+            char_escaped: false,
+        }
+    }
+
+    /// Whether the pattern has any unquoted `*`, `?` or `[`, or with the `globset` feature, `{`
+    pub fn has_metacharacters(&self) -> bool {
+        let pattern: Vec<u16> = self.pattern.encode_wide().collect();
+        let mut i = 0;
+        while i < pattern.len() {
+            if is_escape(&pattern[i..]) {
+                i += 3;
+                continue;
+            }
+            if METACHARACTERS.iter().any(|&b| pattern[i] == u16::from(b)) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+
+    /// The pattern with unquoted `[` and `]` escaped, so that they match only themselves.
+    ///
+    /// Quoted metacharacters are already escaped like `[?]` or `[[]`, and are kept as they are.
+    pub fn pattern_with_literal_brackets(&self) -> OsString {
+        let pattern: Vec<u16> = self.pattern.encode_wide().collect();
+        let mut out = Vec::with_capacity(pattern.len());
+        let mut i = 0;
+        while i < pattern.len() {
+            if is_escape(&pattern[i..]) {
+                out.extend_from_slice(&pattern[i..i + 3]);
+                i += 3;
+                continue;
+            }
+            let c = pattern[i];
+            if c == u16::from(b'[') || c == u16::from(b']') {
+                out.extend_from_slice(&[u16::from(b'['), c, u16::from(b']')]);
+            } else {
+                out.push(c);
+            }
+            i += 1;
+        }
+        OsString::from_wide(&out)
+    }
+}
+
+/// Appends the character to a glob pattern, escaped so that it matches only itself
+pub(crate) fn push_escaped(pattern: &mut Vec<u16>, c: u16) {
+    if ESCAPED.iter().any(|&b| c == u16::from(b)) {
+        pattern.extend_from_slice(&[u16::from(b'['), c, u16::from(b']')]);
+    } else {
+        pattern.push(c);
+    }
+}
+
+/// The pattern with escapes of quoted metacharacters like `[*]` replaced by the characters
+fn unescape(pattern: &OsStr) -> OsString {
+    let pattern = pattern.as_encoded_bytes();
+    let mut text = Vec::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i..] {
+            [b'[', c, b']', ..] if ESCAPED.contains(&c) => {
+                text.push(c);
+                i += 3;
+            },
+            _ => {
+                text.push(pattern[i]);
+                i += 1;
+            },
+        }
+    }
+    // safe, since only ASCII characters are removed
+    unsafe { OsString::from_encoded_bytes_unchecked(text) }
+}
+
+/// Whether the pattern starts with an escaped quoted metacharacter
+fn is_escape(pattern: &[u16]) -> bool {
+    match pattern {
+        [open, c, close, ..] => *open == u16::from(b'[') && *close == u16::from(b']') &&
+            ESCAPED.iter().any(|&b| *c == u16::from(b)),
+        _ => false,
+    }
+}
+
+/// An argument being parsed into the reused buffers of `GlobArgs`
+struct Parsing<'b> {
+    text: &'b mut Vec<u16>,
+    /// Filled only once a quoted metacharacter has to be escaped, since until then it's the same as the text
+    pattern: &'b mut Vec<u16>,
+    escaped: bool,
+    /// An unquoted `[` could be taken for an escape, so then the text can't be derived from the pattern
+    unquoted_bracket: bool,
+    quoted: bool,
+    quoted_wildcard: bool,
+    escape: Option<u16>,
+    /// The previous unquoted character was the escape character
+    after_escape: bool,
+    char_escaped: bool,
+}
+
+impl<'b> Parsing<'b> {
+    /// Adds a character that matches only itself
+    fn push_literal(&mut self, c: u16) {
+        self.text.push(c);
+        if !self.escaped {
+            self.escaped = true;
+            self.pattern.extend_from_slice(&self.text[..self.text.len() - 1]);
+        }
+        push_escaped(self.pattern, c);
+    }
+
+    fn push(&mut self, c: u16) {
+        self.text.push(c);
+        if self.escaped {
+            self.pattern.push(c);
+        }
+    }
+
+    /// Adds the escape character itself, if nothing that it could escape follows it
+    fn push_pending_escape(&mut self) {
+        if self.after_escape {
+            self.after_escape = false;
+            if let Some(escape) = self.escape {
+                self.push(escape);
+            }
+        }
+    }
+}
 
 impl<'a> Iterator for GlobArgs<'a> {
     type Item = Arg;
     fn next(&mut self) -> Option<Self::Item> {
-        let (arg, rest) = parser::next_arg(self.line, (vec![], vec![]), |&mut (ref mut arg, ref mut text), c, quoted| {
-            text.push(c);
-            match c as u8 {
-                b'?' | b'*' | b'[' | b']' if quoted && c < 256 => {
-                    arg.push(u16::from(b'['));
-                    arg.push(c);
-                    arg.push(u16::from(b']'));
-                },
-                _ => arg.push(c),
-            };
+        self.text.clear();
+        self.pattern.clear();
+        let parsing = Parsing {
+            text: &mut self.text,
+            pattern: &mut self.pattern,
+            escaped: false,
+            unquoted_bracket: false,
+            quoted: false,
+            quoted_wildcard: false,
+            escape: self.escape,
+            after_escape: false,
+            char_escaped: false,
+        };
+        let (arg, rest) = parser::next_arg(self.line, parsing, |arg, c, quoted| {
+            let is_metacharacter = ESCAPED.iter().any(|&b| c == u16::from(b));
+            if arg.after_escape && !quoted && (is_metacharacter || Some(c) == arg.escape) {
+                arg.after_escape = false;
+                arg.char_escaped = true;
+                arg.quoted_wildcard |= c == u16::from(b'*') || c == u16::from(b'?');
+                arg.push_literal(c);
+                return;
+            }
+            arg.push_pending_escape();
+            if !quoted && Some(c) == arg.escape {
+                arg.after_escape = true;
+                return;
+            }
+            arg.quoted |= quoted;
+            arg.quoted_wildcard |= quoted && (c == u16::from(b'*') || c == u16::from(b'?'));
+            arg.unquoted_bracket |= !quoted && c == u16::from(b'[');
+            if quoted && is_metacharacter {
+                arg.push_literal(c);
+            } else {
+                arg.push(c);
+            }
         });
         self.line = rest;
-        arg.map(|(pattern, text)| Arg {
-            pattern: OsString::from_wide(&pattern),
-            text: OsString::from_wide(&text),
-        })
+        let mut arg = arg?;
+        arg.push_pending_escape();
+        let (pattern, literal) = match (arg.escaped, arg.unquoted_bracket) {
+            (false, _) => (OsString::from_wide(arg.text), Literal::Pattern),
+            (true, false) => (OsString::from_wide(arg.pattern), Literal::Unescaped),
+            (true, true) => (OsString::from_wide(arg.pattern), Literal::Text(OsString::from_wide(arg.text))),
+        };
+        Some(Arg { pattern, literal, quoted: arg.quoted, quoted_wildcard: arg.quoted_wildcard, char_escaped: arg.char_escaped })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = parser::count_args(self.line, false);
+        (len, Some(len))
     }
 }
 
@@ -53,7 +318,89 @@ impl<'a> GlobArgs<'a> {
     /// UTF-16/UCS2 string from `GetCommandLineW`
     #[allow(dead_code)]
     pub(crate) fn new(line: &'a [u16]) -> Self {
-        Self { line }
+        Self { line, text: Vec::new(), pattern: Vec::new(), escape: None }
+    }
+
+    /// Makes the character escape a following unquoted metacharacter, or itself. Elsewhere it's kept as it is.
+    #[allow(dead_code)]
+    pub(crate) fn escape(mut self, escape: EscapeChar) -> Self {
+        self.escape = escape.code_unit();
+        self
+    }
+
+    /// Skips the program name, parsed by its own rules, so that it's never expanded
+    pub(crate) fn skip_program_name(&mut self) {
+        self.line = parser::split_program_name(self.line).1;
     }
 }
 
+/// Source of the program's arguments
+#[derive(Debug, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) enum CommandLine {
+    /// Parsed from `GetCommandLineW`
+    Parsed(GlobArgs<'static>),
+    /// From `std::env::args_os()`, because the command line isn't available. Quoting is unknown, so they must not be expanded.
+    Fallback(::std::vec::IntoIter<OsString>),
+    /// Parsed from a string that isn't kept, e.g. `lpCmdLine` of `WinMain`
+    Split(::std::vec::IntoIter<Arg>),
+}
+
+impl Iterator for CommandLine {
+    type Item = Arg;
+
+    fn next(&mut self) -> Option<Arg> {
+        match *self {
+            CommandLine::Parsed(ref mut args) => args.next(),
+            CommandLine::Fallback(ref mut args) => args.next().map(Arg::from),
+            CommandLine::Split(ref mut args) => args.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match *self {
+            CommandLine::Parsed(ref args) => args.size_hint(),
+            CommandLine::Fallback(ref args) => args.size_hint(),
+            CommandLine::Split(ref args) => args.size_hint(),
+        }
+    }
+}
+
+impl CommandLine {
+    /// Skips the first argument without expanding it. Only the raw command line has the program name parsed by its own rules.
+    pub(crate) fn skip_program_name(&mut self) {
+        match *self {
+            CommandLine::Parsed(ref mut args) => args.skip_program_name(),
+            _ => {
+                self.next();
+            },
+        }
+    }
+}
+
+#[test]
+fn keeps_text_only_when_ambiguous() {
+    let line: Vec<u16> = r#"plain "quoted name" "*.txt" a"?"b [*]"[" "[]" x"#.encode_utf16().collect();
+    let args: Vec<_> = GlobArgs::new(&line).collect();
+    let kept: Vec<_> = args.iter().map(|arg| match arg.literal {
+        Literal::Pattern => "pattern",
+        Literal::Unescaped => "unescaped",
+        Literal::Text(_) => "text",
+    }).collect();
+    assert_eq!(vec!["pattern", "pattern", "unescaped", "unescaped", "text", "unescaped", "pattern"], kept);
+    assert_eq!(vec!["plain", "quoted name", "[*].txt", "a[?]b", "[*][[]", "[[][]]", "x"], args.iter().map(|arg| arg.pattern.to_str().unwrap()).collect::<Vec<_>>());
+    assert_eq!(vec!["plain", "quoted name", "*.txt", "a?b", "[*][", "[]", "x"], args.iter().map(|arg| arg.text().into_owned().into_string().unwrap()).collect::<Vec<_>>());
+}
+
+#[test]
+fn strips_escape_char() {
+    let line: Vec<u16> = r#"^*.txt a^^b ^a ^"*" "^*" x^ ^[1]"?""#.encode_utf16().collect();
+    let args: Vec<_> = GlobArgs::new(&line).escape(EscapeChar::Caret).collect();
+    assert_eq!(vec!["[*].txt", "a^b", "^a", "^[*]", "^[*]", "x^", "[[]1][?]"], args.iter().map(|arg| arg.pattern.to_str().unwrap()).collect::<Vec<_>>());
+    assert_eq!(vec!["*.txt", "a^b", "^a", "^*", "^*", "x^", "[1]?"], args.iter().map(|arg| arg.text().into_owned().into_string().unwrap()).collect::<Vec<_>>());
+    assert_eq!(vec![true, true, false, false, false, false, true], args.iter().map(|arg| arg.char_escaped).collect::<Vec<_>>());
+    assert!(args[0].quoted_wildcard && !args[0].has_metacharacters() && !args[0].quoted);
+    assert_eq!("[*].txt", args[0].clone().without_quoting().pattern);
+    let plain: Vec<_> = GlobArgs::new(&line).map(|arg| arg.into_text().into_string().unwrap()).collect();
+    assert_eq!(vec!["^*.txt", "a^^b", "^a", "^*", "^*", "x^", "^[1]?"], plain);
+}
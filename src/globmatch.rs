@@ -0,0 +1,138 @@
+//! Unix-like glob syntax on top of the shared directory walker
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path};
+#[cfg(test)]
+use std::path::PathBuf;
+use walk::{walk, DirCache, Entry, Found, Matcher, Part};
+#[cfg(test)]
+use walk::paths;
+use case::fold;
+use wildcard::has_groups;
+use config::Config;
+use namematch::{compile, is_match, Pattern};
+
+/// Glob syntax, with case folding done the same way as by the file system
+pub(crate) struct GlobMatcher {
+    pub case_sensitive: bool,
+    pub extglob: bool,
+    #[cfg(feature = "unicode-normalization")]
+    pub normalization: ::config::Normalization,
+}
+
+/// Whether all components of the pattern are valid
+fn is_valid(pattern: &OsStr, extglob: bool) -> bool {
+    Path::new(pattern).components().all(|component| match component {
+        Component::Normal(name) => name == "**" || !has_metacharacters(name, extglob) || compile(name, extglob).is_some(),
+        _ => true,
+    })
+}
+
+fn has_metacharacters(component: &OsStr, extglob: bool) -> bool {
+    component.as_encoded_bytes().iter().any(|b| b"*?[{".contains(b)) || (extglob && has_groups(component))
+}
+
+impl GlobMatcher {
+    fn new(config: &Config) -> Self {
+        Self {
+            case_sensitive: config.case_sensitive,
+            extglob: config.extglob,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: config.normalization,
+        }
+    }
+
+    /// Whether the component without metacharacters is still matched against directory entries
+    #[cfg(feature = "unicode-normalization")]
+    fn is_literal(&self, component: &OsStr) -> bool {
+        !has_metacharacters(component, self.extglob) && !::case::needs_normalization(component, self.normalization)
+    }
+
+    #[cfg(not(feature = "unicode-normalization"))]
+    fn is_literal(&self, component: &OsStr) -> bool {
+        !has_metacharacters(component, self.extglob)
+    }
+
+    fn folded(&self, s: &OsStr) -> OsString {
+        #[cfg(feature = "unicode-normalization")]
+        let normalized = ::case::normalize(s, self.normalization);
+        #[cfg(feature = "unicode-normalization")]
+        let s = &*normalized;
+        if self.case_sensitive {
+            s.to_owned()
+        } else {
+            fold(s)
+        }
+    }
+}
+
+impl Matcher for GlobMatcher {
+    type Pattern = Pattern;
+
+    fn compile(&self, component: &OsStr) -> Part<Pattern> {
+        if component == "**" {
+            return Part::Recursive;
+        }
+        if self.is_literal(component) {
+            return Part::Literal;
+        }
+        match compile(&self.folded(component), self.extglob) {
+            Some(pattern) => Part::Pattern(pattern),
+            None => Part::Literal,
+        }
+    }
+
+    fn list(&self, dir: &Path, pattern: &Pattern, cache: &DirCache) -> Vec<Entry> {
+        cache.list_matching(dir, |name| is_match(pattern, &self.folded(name)))
+    }
+}
+
+/// Expands a glob pattern. Invalid patterns have no matches.
+pub(crate) fn find(pattern: &OsStr, config: &Config, cache: &DirCache) -> Vec<Found> {
+    if !is_valid(pattern, config.extglob) {
+        return Vec::new();
+    }
+    walk(pattern, &GlobMatcher::new(config), config, cache)
+}
+
+#[test]
+fn matches_with_case_folding() {
+    assert_eq!(vec![PathBuf::from("README.md")], paths(find(OsStr::new("read*.MD"), &Config::new(), &DirCache::default())));
+    assert!(paths(find(OsStr::new("read*.MD"), &Config::new().case_sensitive(true), &DirCache::default())).is_empty());
+    assert_eq!(vec![PathBuf::from("Cargo.toml")], paths(find(OsStr::new("[A-C]argo.tom?"), &Config::new().case_sensitive(true), &DirCache::default())));
+    // invalid in `glob`, but not in `globset`
+    #[cfg(not(feature = "globset"))]
+    assert!(paths(find(OsStr::new("src/***"), &Config::new(), &DirCache::default())).is_empty());
+}
+
+#[cfg(feature = "globset")]
+#[test]
+fn matches_alternatives() {
+    assert_eq!(vec![PathBuf::from("Cargo.toml"), PathBuf::from("README.md")], paths(find(OsStr::new("{readme,cargo}.{md,toml}"), &Config::new(), &DirCache::default())));
+    assert!(paths(find(OsStr::new("{readme"), &Config::new(), &DirCache::default())).is_empty());
+}
+
+#[cfg(windows)]
+#[test]
+fn matches_unpaired_surrogates() {
+    use std::os::windows::ffi::OsStringExt;
+    let name = OsString::from_wide(&[0xD800, u16::from(b'.'), u16::from(b'a')]);
+    let other = OsString::from_wide(&[0xDC00, u16::from(b'.'), u16::from(b'a')]);
+    assert!(is_match(&compile(OsStr::new("?.a"), false).unwrap(), &name));
+    assert!(is_match(&compile(OsStr::new("*a"), false).unwrap(), &name));
+    let mut pattern = OsString::from_wide(&[0xD800]);
+    pattern.push("*");
+    assert!(is_match(&compile(&pattern, false).unwrap(), &name));
+    assert!(!is_match(&compile(&pattern, false).unwrap(), &other));
+    assert!(!is_match(&compile(OsStr::new("\u{FFFD}.a"), false).unwrap(), &name));
+    assert!(has_metacharacters(&pattern, false) && !has_metacharacters(&name, false));
+}
+
+#[test]
+fn skips_missing_prefix() {
+    let cache = DirCache::default();
+    assert!(paths(find(OsStr::new("missing/dir/*.rs"), &Config::new().preserve_prefix(true), &cache)).is_empty());
+    assert!(paths(find(OsStr::new("./src/missing/**/*.rs"), &Config::new(), &cache)).is_empty());
+    assert_eq!(0, cache.len());
+    assert!(paths(find(OsStr::new("./src/../src/w*.rs"), &Config::new(), &cache)).contains(&PathBuf::from("./src/../src/walk.rs")));
+    assert!(paths(find(OsStr::new("src/*.rs"), &Config::new().preserve_prefix(true), &cache)).contains(&PathBuf::from("src/walk.rs")));
+}
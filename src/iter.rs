@@ -0,0 +1,497 @@
+use std::ffi::OsString;
+use std::iter::FusedIterator;
+use std::ops::Index;
+use std::path::PathBuf;
+use vfs::VfsMetadata;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(any(windows, target_os = "wasi"))]
+use argsiter;
+#[cfg(any(windows, target_os = "wasi"))]
+use globiter::Arg;
+
+/// Iterator of glob-expanded arguments as `String`s. Returned by `wild::args()`.
+///
+/// Panics if any argument (or its glob expansion) is not valid Unicode.
+///
+/// It can be cloned to iterate the arguments more than once.
+#[derive(Debug, Clone)]
+pub struct Args {
+    inner: ArgsOs,
+}
+
+impl Args {
+    pub(crate) fn new(inner: ArgsOs) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for Args {
+    type Item = String;
+
+    #[inline]
+    fn next(&mut self) -> Option<String> {
+        self.inner.next().map(|s| s.into_string().unwrap())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl FusedIterator for Args {}
+
+/// Iterator of glob-expanded arguments as `String`s, or the original `OsString` if it's not valid Unicode.
+/// Returned by `wild::try_args()`.
+#[derive(Debug, Clone)]
+pub struct TryArgs {
+    inner: ArgsOs,
+}
+
+impl TryArgs {
+    pub(crate) fn new(inner: ArgsOs) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for TryArgs {
+    type Item = Result<String, OsString>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(OsString::into_string)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl FusedIterator for TryArgs {}
+
+/// Iterator of glob-expanded arguments as `String`s, with invalid Unicode replaced by `U+FFFD`.
+/// Returned by `wild::args_lossy()`.
+#[derive(Debug, Clone)]
+pub struct ArgsLossy {
+    inner: ArgsOs,
+}
+
+impl ArgsLossy {
+    pub(crate) fn new(inner: ArgsOs) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for ArgsLossy {
+    type Item = String;
+
+    #[inline]
+    fn next(&mut self) -> Option<String> {
+        self.inner.next().map(|s| match s.into_string() {
+            Ok(s) => s,
+            Err(s) => s.to_string_lossy().into_owned(),
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl FusedIterator for ArgsLossy {}
+
+/// Where an item of `wild::args_paths()` came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Origin {
+    /// The path has been found in the file system by matching a glob pattern.
+    Matched,
+    /// Like `Matched`, but a wildcard matched a symlink or junction on the way to it.
+    Linked,
+    /// The argument has been passed through as-is, e.g. because it was quoted or the pattern didn't match anything.
+    /// Expanded option values (`--name=match`) are literals too, since they aren't paths.
+    Literal,
+}
+
+/// Iterator of glob-expanded arguments as `PathBuf`s, each with a marker whether it was matched in the file system.
+/// Returned by `wild::args_paths()`.
+#[derive(Debug, Clone)]
+pub struct ArgsPaths {
+    inner: ArgsOs,
+}
+
+impl ArgsPaths {
+    pub(crate) fn new(inner: ArgsOs) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for ArgsPaths {
+    type Item = (PathBuf, Origin);
+
+    #[cfg(any(windows, target_os = "wasi"))]
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.inner.next_with_origin().map(|(arg, origin)| (PathBuf::from(arg), origin))
+    }
+
+    /// The shell has done the matching, so existing paths are assumed to be its matches
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|arg| {
+            let path = PathBuf::from(arg);
+            let origin = if path.symlink_metadata().is_ok() { Origin::Matched } else { Origin::Literal };
+            (path, origin)
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl FusedIterator for ArgsPaths {}
+
+/// A glob-expanded argument with the metadata of the file it names. Returned by `wild::args_entries()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct MatchedEntry {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::lossy"))]
+    pub path: PathBuf,
+    pub origin: Origin,
+    /// Size, timestamps and attributes, if they were learned while matching,
+    /// so that they don't have to be read again. On Windows they come with directory listings.
+    ///
+    /// It's `None` for literal arguments, and for matches the file system didn't provide it for.
+    pub metadata: Option<VfsMetadata>,
+}
+
+/// Iterator of glob-expanded arguments with their metadata. Returned by `wild::args_entries()`.
+#[derive(Debug, Clone)]
+pub struct ArgsEntries {
+    inner: ArgsOs,
+}
+
+impl ArgsEntries {
+    pub(crate) fn new(inner: ArgsOs) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for ArgsEntries {
+    type Item = MatchedEntry;
+
+    #[cfg(any(windows, target_os = "wasi"))]
+    #[inline]
+    fn next(&mut self) -> Option<MatchedEntry> {
+        self.inner.inner.next_entry().map(|(arg, origin, metadata)| MatchedEntry { path: PathBuf::from(arg), origin, metadata })
+    }
+
+    /// The shell has done the matching, so existing paths are assumed to be its matches
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    fn next(&mut self) -> Option<MatchedEntry> {
+        self.inner.next().map(|arg| {
+            let path = PathBuf::from(arg);
+            let origin = if path.symlink_metadata().is_ok() { Origin::Matched } else { Origin::Literal };
+            let metadata = if origin == Origin::Matched { path.metadata().ok().map(|m| VfsMetadata::from(&m)) } else { None };
+            MatchedEntry { path, origin, metadata }
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl FusedIterator for ArgsEntries {}
+
+/// Expansion of one command-line argument. Returned by `wild::expansions()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Expansion {
+    /// The argument as given, without the quotes
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::lossy"))]
+    pub original: OsString,
+    /// The `--name=` part of an option, if only its value has been expanded (see `Config::option_values`)
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::lossy_option"))]
+    pub option: Option<OsString>,
+    /// Paths matched by the argument. If it's empty, the argument is used as-is.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "::serde_ext::lossy_vec"))]
+    pub matches: Vec<PathBuf>,
+    /// For each of the `matches`, whether a wildcard matched a symlink or junction on the way to it
+    /// (see `Config::follow_links`)
+    pub linked: Vec<bool>,
+    /// For each of the `matches`, its metadata if it was learned while matching (see `wild::args_entries()`)
+    pub metadata: Vec<Option<VfsMetadata>>,
+    /// Whether any part of the argument was in quotes
+    pub was_quoted: bool,
+}
+
+impl Expansion {
+    #[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+    pub(crate) fn literal(arg: ::globiter::Arg) -> Self {
+        Self {
+            was_quoted: arg.quoted,
+            original: arg.into_text(),
+            option: None,
+            matches: Vec::new(),
+            linked: Vec::new(),
+            metadata: Vec::new(),
+        }
+    }
+}
+
+/// Iterator of `Expansion`s, one per original command-line argument. Returned by `wild::expansions()`.
+#[derive(Debug, Clone)]
+pub struct Expansions {
+    inner: ArgsOs,
+}
+
+impl Expansions {
+    pub(crate) fn new(inner: ArgsOs) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for Expansions {
+    type Item = Expansion;
+
+    #[cfg(any(windows, target_os = "wasi"))]
+    #[inline]
+    fn next(&mut self) -> Option<Expansion> {
+        self.inner.inner.next_expanded()
+    }
+
+    /// The shell has done the matching, so existing paths are assumed to be its matches
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    fn next(&mut self) -> Option<Expansion> {
+        self.inner.next().map(|arg| {
+            let path = PathBuf::from(&arg);
+            let exists = path.symlink_metadata().is_ok();
+            Expansion {
+                original: arg,
+                option: None,
+                linked: if exists { vec![false] } else { Vec::new() },
+                metadata: if exists { vec![path.metadata().ok().map(|m| VfsMetadata::from(&m))] } else { Vec::new() },
+                matches: if exists { vec![path] } else { Vec::new() },
+                was_quoted: false,
+            }
+        })
+    }
+
+    #[cfg(any(windows, target_os = "wasi"))]
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.inner.expansions_size_hint()
+    }
+
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl FusedIterator for Expansions {}
+
+/// Iterator of glob-expanded arguments as `OsString`s. Returned by `wild::args_os()`.
+///
+/// It can be cloned to iterate the arguments more than once.
+#[derive(Debug, Clone)]
+pub struct ArgsOs {
+    #[cfg(any(windows, target_os = "wasi"))]
+    inner: argsiter::Args,
+    /// `std::env::ArgsOs` can't be cloned
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    inner: std::vec::IntoIter<OsString>,
+}
+
+impl ArgsOs {
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub(crate) fn new(inner: argsiter::Args) -> Self {
+        Self { inner }
+    }
+
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub(crate) fn new<I: IntoIterator<Item = OsString>>(inner: I) -> Self {
+        Self { inner: inner.into_iter().collect::<Vec<_>>().into_iter() }
+    }
+
+    /// Skips the program name
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub(crate) fn skip_program_name(mut self) -> Self {
+        self.inner.next();
+        self
+    }
+}
+
+impl Iterator for ArgsOs {
+    type Item = OsString;
+
+    #[inline]
+    fn next(&mut self) -> Option<OsString> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl FusedIterator for ArgsOs {}
+
+impl ArgsOs {
+    /// Expands all remaining arguments up front, so that their number is known,
+    /// and they can be iterated from the end or indexed, e.g. by argument parsers that need `len()`.
+    ///
+    /// ```rust
+    /// let args = wild::args_os().eager();
+    /// let last = args.len().checked_sub(1).map(|i| &args[i]);
+    /// ```
+    pub fn eager(self) -> ArgsEager {
+        ArgsEager { inner: self.collect::<Vec<_>>().into_iter() }
+    }
+}
+
+/// Fully expanded arguments as `OsString`s. Returned by `ArgsOs::eager()`.
+///
+/// Unlike the other iterators, it has an exact length, can be iterated from both ends, and indexed.
+/// Indexes are relative to the arguments not iterated over yet.
+#[derive(Debug, Clone)]
+pub struct ArgsEager {
+    inner: std::vec::IntoIter<OsString>,
+}
+
+impl ArgsEager {
+    /// The remaining arguments
+    pub fn as_slice(&self) -> &[OsString] {
+        self.inner.as_slice()
+    }
+}
+
+impl Iterator for ArgsEager {
+    type Item = OsString;
+
+    #[inline]
+    fn next(&mut self) -> Option<OsString> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ArgsEager {
+    #[inline]
+    fn next_back(&mut self) -> Option<OsString> {
+        self.inner.next_back()
+    }
+}
+
+impl ExactSizeIterator for ArgsEager {}
+
+impl FusedIterator for ArgsEager {}
+
+impl Index<usize> for ArgsEager {
+    type Output = OsString;
+
+    fn index(&self, index: usize) -> &OsString {
+        &self.as_slice()[index]
+    }
+}
+
+/// Iterator of paths matched by a single pattern. Returned by `wild::glob_os()`.
+///
+/// The pattern is expanded on the first call to `next()`.
+#[derive(Debug)]
+pub struct GlobOs {
+    #[cfg_attr(not(any(windows, target_os = "wasi")), allow(dead_code))]
+    pending: Option<(OsString, ::Config)>,
+    results: ::std::vec::IntoIter<Result<PathBuf, ::Error>>,
+}
+
+impl GlobOs {
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub(crate) fn new(pattern: OsString, config: ::Config) -> Self {
+        Self { pending: Some((pattern, config)), results: Vec::new().into_iter() }
+    }
+
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub(crate) fn new(pattern: OsString, _config: ::Config) -> Self {
+        Self { pending: None, results: vec![Ok(PathBuf::from(pattern))].into_iter() }
+    }
+}
+
+impl Iterator for GlobOs {
+    type Item = Result<PathBuf, ::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        #[cfg(any(windows, target_os = "wasi"))]
+        if let Some((pattern, config)) = self.pending.take() {
+            self.results = argsiter::glob(pattern, &config).into_iter();
+        }
+        self.results.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.pending {
+            // the pattern itself, if nothing else
+            Some(_) => (1, None),
+            None => self.results.size_hint(),
+        }
+    }
+}
+
+impl FusedIterator for GlobOs {}
+
+#[cfg(any(windows, target_os = "wasi"))]
+type ArgsFrom<I> = std::iter::Map<I, fn(OsString) -> Arg>;
+
+/// Iterator of glob-expanded arguments from any source. Returned by `wild::expand()`.
+///
+/// It can be cloned if the source iterator can.
+#[derive(Debug, Clone)]
+pub struct Expand<I> {
+    #[cfg(any(windows, target_os = "wasi"))]
+    inner: argsiter::Args<ArgsFrom<I>>,
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    inner: I,
+}
+
+impl<I: Iterator<Item = OsString>> Expand<I> {
+    #[cfg(any(windows, target_os = "wasi"))]
+    pub(crate) fn new(args: I, config: ::Config) -> Self {
+        Self {
+            inner: argsiter::Args::new(Some(args.map(Arg::from as fn(OsString) -> Arg)), config),
+        }
+    }
+
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    pub(crate) fn new(args: I, _config: ::Config) -> Self {
+        Self { inner: args }
+    }
+}
+
+impl<I: Iterator<Item = OsString>> Iterator for Expand<I> {
+    type Item = OsString;
+
+    #[inline]
+    fn next(&mut self) -> Option<OsString> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I: FusedIterator<Item = OsString>> FusedIterator for Expand<I> {}
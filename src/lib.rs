@@ -1,4 +1,4 @@
-//! Emulates glob (wildcard) argument expansion on Windows. No-op on other platforms.
+//! Emulates glob (wildcard) argument expansion on Windows and WASI. No-op on other platforms.
 //!
 //! Unix shells expand command-line arguments like `a*`, `file.???` and pass them expanded to applications.
 //! On Windows `cmd.exe` doesn't do that, so this crate emulates the expansion there.
@@ -7,6 +7,9 @@
 //! The glob syntax on Windows is limited to `*`, `?`, and `[a-z]`/`[!a-z]` ranges.
 //! Glob characters in quotes (`"*"`) are not expanded.
 //!
+//! WASI runtimes don't expand arguments either, so they're expanded there too, in the directories preopened by the runtime.
+//! WASI passes arguments already split, so quotes can't be told apart, and all wildcards are expanded.
+//!
 //! Parsing of quoted arguments precisely follows Windows native syntax (`CommandLineToArgvW`, specifically)
 //! with all its weirdness.
 //!
@@ -16,23 +19,271 @@
 //!
 //! Use `wild::args_os()` instead of  `std::env::args_os()`.
 //!
-//! If you use [clap](https://crates.rs/crates/clap), use `.get_matches_from(wild::args())` instead of `.get_matches()`.
+//! Use `wild::try_args()` to get an error instead of a panic for file names that aren't valid Unicode,
+//! or `wild::args_lossy()` to get them with replacement characters.
+//!
+//! If you use [clap](https://crates.rs/crates/clap), use `.get_matches_from(wild::args())` instead of `.get_matches()`,
+//! or enable the `clap` feature and use `.get_matches_wild()` from `wild::CommandExt`.
+//! For clap's derive API, use `wild::parse::<Cli>()` instead of `Cli::parse()`.
+//!
+//! With the `macros` feature, `#[wild::main] fn main(args: Vec<String>)` gets the expanded arguments directly.
+//! If you use [argh](https://crates.rs/crates/argh), enable the `argh` feature and use `wild::from_env()` instead of `argh::from_env()`.
+//! If you use [bpaf](https://crates.rs/crates/bpaf), enable the `bpaf` feature and use `.run_wild()` from `wild::OptionParserExt` instead of `.run()`.
+//! If you use [camino](https://crates.rs/crates/camino), enable the `camino` feature and use `wild::args_utf8_paths()` to get `Utf8PathBuf`s.
+//!
+//! Use `wild::Config` to adjust how arguments are expanded, e.g. `wild::Config::new().option_values(wild::OptionValues::Repeat).args()`
+//! to expand `--include=*.rs`-style option values.
+//! `Config::vfs()` with `wild::MemoryFs` tests how patterns expand without touching the disk.
+//! `wild::set_config()` applies a `Config` to `wild::args()` and the other functions, including calls made by libraries.
+//!
+//! Setting the `WILD_NOGLOB=1` environment variable, or calling `wild::disable_expansion()`, turns the expansion off.
+//! `wild::parse_command_line()` splits any command line the same way, without expanding it, and `wild::join()` does the reverse.
+//! `wild::parse_command_line_wide()` returns the arguments as UTF-16 borrowed from the command line.
+//! `wild::Matcher` tests names from other sources, like archives, against a pattern with the same syntax, on all platforms.
+//!
+//! With the `rayon` feature, `wild::par_args_os()` expands patterns in parallel, which helps with slow network drives,
+//! and `Config::parallel_walk()` lists directories concurrently, e.g. for `**` in large trees.
+//! With the `stream` feature, `wild::args_stream()` returns a `Stream` for use in async code.
+//! The `ffi` feature exports a C API, declared in `include/wild.h`, for C and C++ programs that would otherwise link `setargv.obj`.
+//! With the `always-expand` feature, `wild::args_always_expanded()` expands wildcards on Unix too, for programs started without a shell.
+//! The `run` feature builds the `wild-run` program: `wild-run findstr TODO src\*.rs` runs `findstr` with the expanded arguments.
+//! With the `raw-dylib` feature, Win32 functions are imported from the system DLLs without import libraries, for toolchains that lack them.
+//! The `verify-parser` feature compares the parsing of the command line with Windows' own `CommandLineToArgvW`, and panics if they differ.
+//! Patterns are matched by a built-in matcher, which needs no dependencies. With the `glob` feature the `glob` crate is used instead, as in older versions,
+//! and with the `globset` feature the `globset` crate, which adds `{a,b}` alternations.
+//! With the `unicode-normalization` feature, `Config::normalization()` makes names like `café` match whether they're stored composed or decomposed,
+//! e.g. when files were synced from macOS.
+//! With the `serde` feature, `Expansion`, `MatchedEntry`, `Error` and other reports can be serialized, e.g. to log the arguments as JSON.
+//! With the `ignore` feature, `Config::gitignore()` makes wildcards skip files excluded by `.gitignore`, like ripgrep does.
+//! With the `tracing` feature, parsing of the command line, patterns, listed directories and numbers of matches are logged with `tracing`,
+//! which helps to find out why a pattern didn't match.
 
-#[cfg(any(test,windows))]
+#[cfg(feature = "glob")]
 extern crate glob;
 
-#[cfg(any(test,windows))]
+#[cfg(feature = "clap")]
+extern crate clap;
+
+#[cfg(feature = "argh")]
+extern crate argh;
+
+#[cfg(feature = "camino")]
+extern crate camino;
+
+#[cfg(feature = "macros")]
+extern crate wild_macros;
+
+#[cfg(feature = "serde")]
+extern crate serde;
+
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+#[cfg(feature = "bpaf")]
+extern crate bpaf;
+
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+#[cfg(feature = "globset")]
+extern crate globset;
+
+#[cfg(feature = "stream")]
+extern crate blocking;
+
+#[cfg(feature = "stream")]
+extern crate futures_core;
+
+#[cfg(all(feature = "ignore", any(test, windows, target_os = "wasi", feature = "always-expand")))]
+extern crate ignore;
+
+#[cfg(feature = "unicode-normalization")]
+extern crate unicode_normalization;
+
+#[cfg(all(test, feature = "stream"))]
+extern crate futures_lite;
+
 mod parser;
+pub use parser::{CommandLineArgs, CommandLineArgsWide, Syntax};
 
-#[cfg(any(test,windows))]
+mod cmd;
+
+mod powershell;
+
+mod quote;
+
+mod pattern;
+pub use pattern::{contains_glob, escape_literal, is_pattern};
+
+#[cfg(feature = "ffi")]
+mod ffi;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
 mod argsiter;
 
-#[cfg(any(test,windows))]
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
 mod globiter;
 
-// Iterator types
-type _StringIter = Box<Iterator<Item=String>>;
-type _OsStringIter = Box<Iterator<Item=std::ffi::OsString>>;
+#[cfg(windows)]
+mod sys;
+
+#[cfg(windows)]
+mod native;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+mod walk;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+mod dos;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+mod globmatch;
+
+#[cfg_attr(not(any(test, windows, target_os = "wasi", feature = "always-expand")), allow(dead_code))]
+mod wildcard;
+
+mod namematch;
+pub use namematch::Matcher;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+mod exclude;
+
+#[cfg_attr(not(any(test, windows, target_os = "wasi", feature = "always-expand")), allow(dead_code))]
+mod case;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+mod order;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+mod longpath;
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+mod devices;
+
+#[cfg(any(test,windows))]
+mod drive;
+
+#[cfg(any(test,windows))]
+mod streams;
+
+#[cfg(windows)]
+mod names;
+
+#[cfg(all(feature = "ignore", any(test, windows, target_os = "wasi", feature = "always-expand")))]
+mod gitignore;
+
+#[cfg(test)]
+mod mockfs;
+
+#[cfg(test)]
+mod tempdir;
+
+#[cfg(any(windows, target_os = "wasi"))]
+use std::sync::{Mutex, PoisonError};
+
+mod config;
+#[cfg(feature = "unicode-normalization")]
+pub use config::Normalization;
+pub use config::{AttributeFilter, Config, DeviceNames, EmptyCommandLine, EndOfOptions, Engine, EntryTypes, EscapeChar, ListSeparator, NameForm, OptionValues, PathForm, Separators, SortOrder};
+
+mod error;
+pub use error::{Error, TooManyMatches, UnreadableDir};
+
+mod vfs;
+pub use vfs::{MemoryFs, OsFs, Vfs, VfsEntry, VfsMetadata};
+
+mod matcher;
+pub use matcher::{NameMatcher, PatternMatcher};
+
+mod progress;
+pub use progress::{CancelToken, Progress};
+
+mod iter;
+pub use iter::{Args, ArgsEager, ArgsEntries, ArgsLossy, ArgsOs, ArgsPaths, Expand, Expansion, Expansions, GlobOs, MatchedEntry, Origin, TryArgs};
+
+#[cfg(feature = "clap")]
+mod clap_ext;
+#[cfg(feature = "clap")]
+pub use clap_ext::{parse, try_parse, CommandExt};
+
+#[cfg(feature = "argh")]
+mod argh_ext;
+#[cfg(feature = "argh")]
+pub use argh_ext::{from_env, from_env_lossy};
+
+#[cfg(feature = "macros")]
+mod main_ext;
+#[cfg(feature = "macros")]
+pub use main_ext::MainArgs;
+
+/// Passes glob-expanded arguments to `main`, so that they can't be read with `std::env::args()` by mistake. Requires the `macros` feature.
+///
+/// `main` can take them as `Vec<String>`, `Vec<OsString>`, `Vec<PathBuf>`, `wild::Args` or `wild::ArgsOs` (see `wild::MainArgs`).
+/// Without arguments, they're expanded before `main` runs, like with `wild::preload()`, and later calls to `wild::args()` return the same matches.
+///
+/// ```rust
+/// #[wild::main]
+/// fn main(args: Vec<String>) {
+///     for arg in &args[1..] {
+///         println!("{}", arg);
+///     }
+/// }
+/// ```
+///
+/// The arguments are the same as `wild::args_os()` returns:
+///
+/// ```rust
+/// #[wild::main]
+/// fn main(args: Vec<std::ffi::OsString>) {
+///     assert!(!args.is_empty());
+///     assert_eq!(args, wild::args_os().collect::<Vec<_>>());
+/// }
+/// ```
+///
+/// `main` without arguments can still return a `Result`:
+///
+/// ```rust
+/// #[wild::main]
+/// fn main() -> Result<(), std::io::Error> {
+///     assert!(wild::args().next().is_some());
+///     Ok(())
+/// }
+/// ```
+///
+/// The attribute takes no options, and `main` can't take anything but the arguments:
+///
+/// ```rust,compile_fail
+/// #[wild::main(unique)]
+/// fn main() {}
+/// ```
+///
+/// ```rust,compile_fail
+/// #[wild::main]
+/// fn main(args: Vec<String>, env: Vec<String>) {}
+/// ```
+#[cfg(feature = "macros")]
+pub use wild_macros::main;
+
+#[cfg(feature = "serde")]
+mod serde_ext;
+
+#[cfg(feature = "camino")]
+mod camino_ext;
+#[cfg(feature = "camino")]
+pub use camino_ext::{args_utf8_paths, ArgsUtf8Paths, NonUtf8};
+
+#[cfg(feature = "bpaf")]
+mod bpaf_ext;
+#[cfg(feature = "bpaf")]
+pub use bpaf_ext::OptionParserExt;
+
+#[cfg(feature = "stream")]
+mod stream;
+#[cfg(feature = "stream")]
+pub use stream::ArgsStream;
 
 /// Returns an iterator of glob-expanded command-line arguments. Equivalent of `std::env::args()`/`std::env::args_os`.
 ///
@@ -40,14 +291,12 @@ type _OsStringIter = Box<Iterator<Item=std::ffi::OsString>>;
 /// assuming expansion has already been done by the shell.
 ///
 /// On Windows it emulates the glob expansion itself.
-/// The iterator will parse arguments incrementally and access
-/// the file system as it parses. This allows reading potentially huge lists of
-/// filenames, but it's not an atomic snapshot (use `.collect()` if you need that).
+/// All arguments are expanded on the first call, and later calls return the same ones, until `wild::refresh()`.
 ///
 /// Note that `args()` (just as `std::env::args()`) will panic if OsString glob expansions are not convertible to normal Strings (UTF-8-type).
 #[cfg(not(windows))]
-pub fn args() -> _StringIter {
-    Box::new( std::env::args() )
+pub fn args() -> Args {
+    Args::new(shared_args_os())
 }
 
 /// Returns the program arguments (glob-expanded for Windows) as a [`String`] iterator.
@@ -57,17 +306,154 @@ pub fn args() -> _StringIter {
 /// [`String`]: https://doc.rust-lang.org/std/string/struct.String.html
 /// [`OsString`]: https://doc.rust-lang.org/std/ffi/struct.OsString.html
 #[cfg(windows)]
-pub fn args() -> _StringIter {
-    Box::new(
-        args_os().map(|s| s.into_string().unwrap())
-    )
+pub fn args() -> Args {
+    Args::new(shared_args_os())
+}
+
+/// Returns the program arguments glob-expanded on all platforms, as a `String` iterator. Requires the `always-expand` feature.
+///
+/// Unix shells expand wildcards, but programs started without a shell, e.g. with `posix_spawn` or by some CI runners, get them unexpanded.
+/// Arguments on Unix are passed already split, so it's not known which were quoted, and all wildcards are expanded.
+/// Patterns that don't match anything are kept as-is, so arguments expanded by a shell are usually unchanged.
+///
+/// On Windows and WASI it's the same as `wild::args()`.
+#[cfg(feature = "always-expand")]
+pub fn args_always_expanded() -> Args {
+    #[cfg(any(windows, target_os = "wasi"))]
+    {
+        args()
+    }
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    {
+        Args::new(ArgsOs::new(split_argv_args(Config::global().clone())))
+    }
+}
+
+/// Returns the program arguments (glob-expanded for Windows) as an iterator of `Result<String, OsString>`.
+///
+/// Unlike `args()`, it doesn't panic when an argument (or a file it matched) is not valid Unicode,
+/// and gives back the offending [`OsString`](https://doc.rust-lang.org/std/ffi/struct.OsString.html) instead.
+///
+/// ```rust
+/// for arg in wild::try_args() {
+///     match arg {
+///         Ok(arg) => println!("{}", arg),
+///         Err(name) => eprintln!("error: {} is not a valid Unicode file name", name.to_string_lossy()),
+///     }
+/// }
+/// ```
+pub fn try_args() -> TryArgs {
+    TryArgs::new(shared_args_os())
+}
+
+/// Returns the program arguments (glob-expanded for Windows) as a `String` iterator,
+/// replacing invalid Unicode with `U+FFFD` (`�`) instead of panicking like `args()`.
+///
+/// Use it with argument parsers that only accept `String`s, when passing every file through,
+/// even with a mangled name, is preferable to an error.
+pub fn args_lossy() -> ArgsLossy {
+    ArgsLossy::new(shared_args_os())
+}
+
+/// Returns the program arguments (glob-expanded for Windows) as `PathBuf`s, each paired with an `Origin`
+/// telling whether it was found in the file system, or passed through as a literal (e.g. a pattern that didn't match anything).
+///
+/// On non-Windows platforms the shell has already done the matching, so paths that exist are reported as `Origin::Matched`.
+///
+/// ```rust
+/// for (path, origin) in wild::args_paths().skip(1) {
+///     if origin == wild::Origin::Literal && !path.exists() {
+///         eprintln!("warning: {} not found", path.display());
+///     }
+/// }
+/// ```
+pub fn args_paths() -> ArgsPaths {
+    ArgsPaths::new(shared_args_os())
+}
+
+/// Like `wild::args_paths()`, but with the size, timestamps and attributes of matched files,
+/// which on Windows come with the directory listings, so tools don't have to read them again.
+/// Over network drives this saves a round trip per file.
+///
+/// On non-Windows platforms the metadata of paths that exist is read.
+///
+/// ```rust
+/// let total: u64 = wild::args_entries().skip(1).filter_map(|entry| entry.metadata).map(|m| m.len).sum();
+/// ```
+pub fn args_entries() -> ArgsEntries {
+    ArgsEntries::new(shared_args_os())
+}
+
+/// Returns the program arguments (glob-expanded for Windows) without the program name, as an `OsString` iterator.
+///
+/// It's the same as `wild::args_os().skip(1)`, except that on Windows the program name is parsed by its own rules,
+/// like `CommandLineToArgvW` does, and it's never expanded.
+///
+/// ```rust
+/// for arg in wild::args_skip_program() {
+///     println!("{}", arg.to_string_lossy());
+/// }
+/// ```
+pub fn args_skip_program() -> ArgsOs {
+    #[cfg(any(windows, target_os = "wasi"))]
+    {
+        shared_args(true)
+    }
+    #[cfg(not(any(windows, target_os = "wasi")))]
+    {
+        shared_args_os().skip_program_name()
+    }
+}
+
+/// Returns the name of the program as it was started, i.e. the first argument, without any expansion.
+///
+/// On Windows it's parsed from the command line by the special rules for the program name: backslashes don't escape quotes in it,
+/// so e.g. `"C:\Program Files\app.exe"` is returned as `C:\Program Files\app.exe`.
+pub fn program_name() -> std::ffi::OsString {
+    let line = Config::global().command_line_source.and_then(|source| source());
+    #[cfg(windows)]
+    let line = line.or_else(|| raw_command_line().map(<[u16]>::to_vec));
+    match line {
+        Some(line) if !is_blank(&line) => parser::from_wide(parser::split_program_name(&line).0),
+        _ => std::env::args_os().next().unwrap_or_default(),
+    }
+}
+
+/// Returns all program arguments (glob-expanded for Windows) at once, or an error listing every pattern that failed:
+/// patterns matching more paths than allowed by `Config::max_results()`, and directories that couldn't be read.
+///
+/// Unlike the iterators, which pass unreadable directories over silently, this gives either the complete arguments or all the problems.
+///
+/// ```rust
+/// match wild::args_vec() {
+///     Ok(args) => println!("{} arguments", args.len()),
+///     Err(err) => eprintln!("error: {}", err),
+/// }
+/// ```
+pub fn args_vec() -> Result<Vec<std::ffi::OsString>, Error> {
+    Config::global().args_vec()
+}
+
+/// Returns one `Expansion` per original program argument, with all the paths it matched,
+/// e.g. to report how many files each pattern matched.
+///
+/// On non-Windows platforms the shell has already expanded the arguments, so the original patterns are unknown.
+/// Each argument is reported as matching itself if it exists.
+///
+/// ```rust
+/// for e in wild::expansions().skip(1) {
+///     println!("{} matched {} files", e.original.to_string_lossy(), e.matches.len());
+/// }
+/// ```
+pub fn expansions() -> Expansions {
+    Expansions::new(shared_args_os())
 }
 
 /// Returns the program arguments (glob-expanded for Windows) as an [`OsString`](https://doc.rust-lang.org/std/ffi/struct.OsString.html) iterator.
 /// # fn args_os()
 #[cfg(not(windows))]
-pub fn args_os() -> _OsStringIter {
-    Box::new( std::env::args_os() )
+pub fn args_os() -> ArgsOs {
+    shared_args_os()
 }
 
 /// Returns the program arguments (glob-expanded for Windows) as an [`OsString`] iterator.
@@ -75,14 +461,246 @@ pub fn args_os() -> _OsStringIter {
 /// [`String`]: https://doc.rust-lang.org/std/string/struct.String.html
 /// [`OsString`]: https://doc.rust-lang.org/std/ffi/struct.OsString.html
 #[cfg(windows)]
-pub fn args_os() -> _OsStringIter {
-    use argsiter::Args;
-    Box::new(
-        Args {
-            args: globs(),
-            current_arg_globs: None,
+pub fn args_os() -> ArgsOs {
+    shared_args_os()
+}
+
+/// Returns the program arguments (glob-expanded for Windows) as a [rayon](https://crates.rs/crates/rayon) parallel iterator.
+///
+/// All patterns, and all directories that a pattern has to search, are expanded in parallel before this function returns.
+/// The order of arguments is the same as in `wild::args_os()`.
+#[cfg(feature = "rayon")]
+pub fn par_args_os() -> rayon::vec::IntoIter<std::ffi::OsString> {
+    Config::global().par_args_os()
+}
+
+/// Returns the program arguments (glob-expanded for Windows) as an async `Stream` of `OsString`s.
+///
+/// File system access happens on a blocking thread pool, so it's safe to use in async code with any runtime.
+#[cfg(feature = "stream")]
+pub fn args_stream() -> ArgsStream {
+    ArgsStream::new(shared_args_os())
+}
+
+/// Glob-expands arguments from any source, e.g. a config file or an IPC message.
+///
+/// Each item is a single argument that has already been split, so unlike in `wild::args_os()`, there's no quoting
+/// and all glob metacharacters in it are active. As with the command line, patterns that don't match anything are passed through as-is.
+///
+/// On non-Windows platforms it returns the arguments unchanged, so it can be used unconditionally.
+pub fn expand<I>(args: I) -> Expand<I::IntoIter> where I: IntoIterator<Item = std::ffi::OsString> {
+    Config::global().expand(args)
+}
+
+/// Glob-expands a single pattern, e.g. from a config file, exactly like an unquoted command-line argument with the same settings.
+///
+/// There's no quoting, so all glob metacharacters in it are active, and it's never split into an option and its value.
+/// As with arguments, a pattern that doesn't match anything is returned as-is.
+/// Directories that can't be searched, or too many matches, are reported together in one `wild::Error` after the paths.
+///
+/// On non-Windows platforms it returns the pattern unchanged, like the shell-expanded arguments.
+///
+/// ```rust
+/// # fn main() -> Result<(), wild::Error> {
+/// for path in wild::glob_os("src/*.rs".as_ref()) {
+///     println!("{}", path?.display());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn glob_os(pattern: &std::ffi::OsStr) -> GlobOs {
+    Config::global().glob_os(pattern)
+}
+
+/// Arguments expanded by the first call to `wild::args()` or a similar function, with and without the program name,
+/// which all later calls return again.
+#[cfg(any(windows, target_os = "wasi"))]
+static SHARED: Mutex<[Option<Vec<argsiter::Planned>>; 2]> = Mutex::new([None, None]);
+
+#[cfg(any(windows, target_os = "wasi"))]
+fn shared_args_os() -> ArgsOs {
+    shared_args(false)
+}
+
+#[cfg(any(windows, target_os = "wasi"))]
+fn shared_args(skip_program_name: bool) -> ArgsOs {
+    let config = Config::global();
+    let mut shared = SHARED.lock().unwrap_or_else(PoisonError::into_inner);
+    let expanded = shared[usize::from(skip_program_name)].get_or_insert_with(|| {
+        let mut args = command_line_args(config.clone());
+        if skip_program_name {
+            args.skip_program_name();
         }
-    )
+        args.expand_all()
+    });
+    ArgsOs::new(argsiter::Args::expanded(expanded.clone(), config.clone()))
+}
+
+#[cfg(not(any(windows, target_os = "wasi")))]
+fn shared_args_os() -> ArgsOs {
+    Config::global().args_os()
+}
+
+/// Expands the program arguments now, so that later calls to `wild::args()` and similar functions
+/// return the same matches even if files are created or deleted in the meantime, e.g. by the program itself.
+///
+/// The arguments are expanded only once anyway, so it only moves the work to the start of the program. Undone by `wild::refresh()`.
+pub fn preload() {
+    #[cfg(any(windows, target_os = "wasi"))]
+    {
+        shared_args_os();
+    }
+}
+
+/// Makes subsequent calls to `wild::args()` and similar functions expand the arguments again.
+///
+/// Otherwise they're expanded only once, on the first call, so that wildcards match the same files in all calls,
+/// and libraries reading the arguments again don't repeat the work. Iterators that have already been created are not affected.
+pub fn refresh() {
+    #[cfg(any(windows, target_os = "wasi"))]
+    {
+        *SHARED.lock().unwrap_or_else(PoisonError::into_inner) = [None, None];
+    }
+}
+
+/// Splits a command line into arguments exactly like `CommandLineToArgvW` does, without any glob expansion.
+///
+/// This is a safe replacement for `CommandLineToArgvW`, e.g. for `lpCmdLine` of `WinMain`, or command lines received from other processes.
+/// It works on all platforms, but outside of Windows unpaired surrogates in the arguments are replaced with `U+FFFD`.
+///
+/// ```rust
+/// let line: Vec<u16> = r#"prog.exe "a b" c\"d *.txt"#.encode_utf16().collect();
+/// let args: Vec<_> = wild::parse_command_line(&line).collect();
+/// assert_eq!(args, ["prog.exe", "a b", "c\"d", "*.txt"]);
+/// ```
+pub fn parse_command_line(line: &[u16]) -> CommandLineArgs<'_> {
+    CommandLineArgs::new(line, Syntax::CommandLineToArgvW)
+}
+
+/// Splits a command line into arguments like `wild::parse_command_line()`, but returns them as UTF-16 slices of the `line`,
+/// without converting them to `OsString`s.
+///
+/// An argument is copied only if it had quotes that had to be removed, so this is for embedders doing their own matching or transcoding.
+///
+/// ```rust
+/// use std::borrow::Cow;
+/// let line: Vec<u16> = r#"prog.exe "a b" *.txt"#.encode_utf16().collect();
+/// let args: Vec<Cow<[u16]>> = wild::parse_command_line_wide(&line).collect();
+/// assert_eq!(String::from_utf16_lossy(&args[1]), "a b");
+/// assert!(matches!(args[2], Cow::Borrowed(_)));
+/// ```
+pub fn parse_command_line_wide(line: &[u16]) -> CommandLineArgsWide<'_> {
+    CommandLineArgsWide::new(line)
+}
+
+/// Splits a command line into arguments following the given rules, without any glob expansion.
+///
+/// ```rust
+/// let line: Vec<u16> = r#"prog.exe a^&b "c & d" > out.txt & echo done"#.encode_utf16().collect();
+/// let args: Vec<_> = wild::parse_command_line_with(&line, wild::Syntax::Cmd).collect();
+/// assert_eq!(args, ["prog.exe", "a&b", "c & d"]);
+/// ```
+pub fn parse_command_line_with(line: &[u16], syntax: Syntax) -> CommandLineArgs<'_> {
+    CommandLineArgs::new(line, syntax)
+}
+
+/// Glob-expands arguments read from a file or stdin, e.g. for a `--files-from` option, with the same rules as the command line.
+///
+/// With `ListSeparator::Lines`, every line is split and unquoted like a command line, so `"*.txt"` is kept literal,
+/// and with `ListSeparator::Nul`, every NUL-terminated item is one argument, like from `find -print0`.
+/// The list must be UTF-8, otherwise an `InvalidData` error is returned. Unlike `wild::args()`, there's no program name.
+///
+/// The whole list is read and split before this returns, so that read errors and invalid UTF-8 anywhere in it are returned here,
+/// rather than ending the arguments early. Only the expansion of the patterns is lazy, so a very long list is held in memory.
+///
+/// On non-Windows platforms the arguments are only split, like the other functions don't expand them either.
+///
+/// ```rust
+/// let list = b"Cargo.toml \"my notes.txt\"\r\nsrc/*.rs\n";
+/// let args: Vec<_> = wild::expand_lines(&list[..], wild::ListSeparator::Lines).unwrap().collect();
+/// assert_eq!(args[..2], ["Cargo.toml", "my notes.txt"]);
+/// ```
+pub fn expand_lines<R: std::io::Read>(reader: R, separator: ListSeparator) -> std::io::Result<ArgsOs> {
+    Config::global().expand_lines(reader, separator)
+}
+
+/// Items of a list of arguments: lines without the line ending, or NUL-terminated items, which are never empty.
+fn read_list<R: std::io::Read>(mut reader: R, separator: ListSeparator) -> std::io::Result<Vec<String>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(&text);
+    Ok(match separator {
+        ListSeparator::Lines => text.lines().map(str::to_owned).collect(),
+        ListSeparator::Nul => text.split('\0').filter(|item| !item.is_empty()).map(str::to_owned).collect(),
+    })
+}
+
+/// Arguments of a list, parsed like a command line if they're in lines
+#[cfg(any(test, windows, target_os = "wasi"))]
+fn list_args(items: &[String], separator: ListSeparator, config: Config) -> argsiter::Args {
+    let args: Vec<_> = match separator {
+        ListSeparator::Lines => items.iter().flat_map(|line| globiter::GlobArgs::new(&line.encode_utf16().collect::<Vec<_>>()).escape(config.escape_char).collect::<Vec<_>>()).collect(),
+        ListSeparator::Nul => items.iter().map(|item| globiter::Arg::from(std::ffi::OsString::from(item))).collect(),
+    };
+    argsiter::Args::new(Some(globiter::CommandLine::Split(args.into_iter())), config)
+}
+
+/// Glob-expands arguments from `lpCmdLine` of `WinMain`, which GUI programs get instead of the full command line.
+///
+/// Unlike `wild::args_os()`, the first item is not the program name, since `lpCmdLine` doesn't include it,
+/// and the first argument is parsed like all the others. Use `wild::args_from_lpcmdline_wide()` for `lpCmdLine` of `wWinMain`.
+///
+/// On non-Windows platforms the arguments are only split, like the other functions don't expand them either.
+pub fn args_from_lpcmdline(line: &str) -> ArgsOs {
+    let line: Vec<u16> = line.encode_utf16().collect();
+    args_from_lpcmdline_wide(&line)
+}
+
+/// Same as `wild::args_from_lpcmdline()`, for the UTF-16 `lpCmdLine` of `wWinMain`.
+pub fn args_from_lpcmdline_wide(line: &[u16]) -> ArgsOs {
+    Config::global().args_from_lpcmdline(line)
+}
+
+/// Quotes the argument for a Windows command line, if it needs it, so that `CommandLineToArgvW`
+/// and `wild::parse_command_line()` turn it back into exactly the same string.
+///
+/// ```rust
+/// assert_eq!(wild::quote(r"C:\My Files\".as_ref()), r#""C:\My Files\\""#);
+/// ```
+pub fn quote(arg: &std::ffi::OsStr) -> std::ffi::OsString {
+    quote::quote(arg)
+}
+
+/// Quotes the arguments and joins them with spaces into a Windows command line, e.g. for spawning a process with a raw command line.
+/// It's the inverse of `wild::parse_command_line()`.
+///
+/// The program name is quoted the same way, which works as long as it doesn't contain quotes or end with a backslash,
+/// since `CreateProcess` doesn't support escapes in it.
+pub fn join<I, S>(args: I) -> std::ffi::OsString where I: IntoIterator<Item = S>, S: AsRef<std::ffi::OsStr> {
+    quote::join(args)
+}
+
+/// Sets the `Config` used by `wild::args()`, `wild::args_os()` and all other functions of this crate that don't take one,
+/// including those of the parser integrations.
+///
+/// This lets an application choose the expansion policy for libraries that read the arguments on its behalf.
+/// It can be set only once, before any of these functions is called, otherwise the config is returned back as an error.
+///
+/// ```rust
+/// wild::set_config(wild::Config::new().case_sensitive(true)).expect("args not used yet");
+/// ```
+// the config is handed back as-is, so it can be reused
+#[allow(clippy::result_large_err)]
+pub fn set_config(config: Config) -> Result<(), Config> {
+    Config::set_global(config)
+}
+
+/// Turns off glob expansion for the rest of the program, so all functions return arguments exactly as parsed by `CommandLineToArgvW`.
+///
+/// Users can do the same by setting the `WILD_NOGLOB` environment variable to `1`, e.g. to pass a literal `*` to a program that doesn't handle quoting.
+/// Iterators that have already been created are not affected.
+pub fn disable_expansion() {
+    config::disable_expansion();
 }
 
 /// Parses `GetCommandLineW` the same way as `CommandLineToArgvW`,
@@ -92,23 +710,154 @@ pub fn args_os() -> _OsStringIter {
 #[cfg(windows)]
 #[inline]
 fn globs() -> Option<globiter::GlobArgs<'static>> {
-    raw_command_line().map(|cmd| globiter::GlobArgs::new(cmd))
+    raw_command_line().map(globiter::GlobArgs::new)
 }
 
+/// Program arguments to be expanded with the config.
+///
+/// Without the raw command line, arguments from the standard library are used as-is.
 #[cfg(windows)]
-extern "system" {
-    fn GetCommandLineW() -> *const u16;
+fn command_line_args(config: Config) -> argsiter::Args {
+    if let Some(source) = config.command_line_source {
+        if let Some(line) = source() {
+            #[cfg(feature = "tracing")]
+            ::tracing::debug!(command_line = %String::from_utf16_lossy(&line), "parsing command line from Config::command_line_source");
+            if is_blank(&line) {
+                return blank_command_line_args(config);
+            }
+            return split_args(&line, config);
+        }
+    } else if raw_command_line().is_some_and(is_blank) {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!("command line is empty");
+        return blank_command_line_args(config);
+    } else if let Some(args) = globs() {
+        #[cfg(feature = "verify-parser")]
+        verify_parser(raw_command_line().unwrap_or_default());
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(command_line = %String::from_utf16_lossy(raw_command_line().unwrap_or_default()), "parsing command line");
+        return argsiter::Args::new(Some(globiter::CommandLine::Parsed(args.escape(config.escape_char))), config);
+    }
+    #[cfg(feature = "tracing")]
+    ::tracing::debug!("command line not available, using std::env::args_os() without expansion");
+    literal_args(std::env::args_os().collect(), config)
+}
+
+/// Arguments that must not be expanded, because their quoting is unknown
+#[cfg(any(windows, target_os = "wasi"))]
+fn literal_args(args: Vec<std::ffi::OsString>, config: Config) -> argsiter::Args {
+    let mut args = argsiter::Args::new(Some(globiter::CommandLine::Fallback(args.into_iter())), config);
+    args.disabled = true;
+    args
+}
+
+/// Arguments of a command line without even the program name, which some hosts start programs with
+#[cfg(any(windows, target_os = "wasi"))]
+fn blank_command_line_args(config: Config) -> argsiter::Args {
+    literal_args(empty_command_line_args(&config), config)
+}
+
+/// Whether the command line has only spaces and tabs, if anything
+fn is_blank(line: &[u16]) -> bool {
+    line.iter().all(|&c| c == u16::from(b' ') || c == u16::from(b'\t'))
+}
+
+/// Arguments used instead of a blank command line, as set by `Config::empty_command_line()`
+fn empty_command_line_args(config: &Config) -> Vec<std::ffi::OsString> {
+    match config.empty_command_line {
+        EmptyCommandLine::ProgramPath => std::env::current_exe().map(std::path::PathBuf::into_os_string).into_iter().collect(),
+        EmptyCommandLine::Empty => Vec::new(),
+    }
+}
+
+/// Program arguments to be expanded with the config.
+///
+/// WASI has no shell that would expand them either.
+#[cfg(target_os = "wasi")]
+fn command_line_args(config: Config) -> argsiter::Args {
+    if let Some(line) = config.command_line_source.and_then(|source| source()) {
+        if is_blank(&line) {
+            return blank_command_line_args(config);
+        }
+        return split_args(&line, config);
+    }
+    split_argv_args(config)
+}
+
+/// Arguments from `std::env::args_os()` to be expanded with the config.
+///
+/// They're passed already split, so it's not known which of them were quoted.
+#[cfg(any(target_os = "wasi", all(feature = "always-expand", not(windows))))]
+fn split_argv_args(config: Config) -> argsiter::Args {
+    let args: Vec<_> = std::env::args_os().map(globiter::Arg::from).collect();
+    argsiter::Args::new(Some(globiter::CommandLine::Split(args.into_iter())), config)
+}
+
+/// Panics if the command line is split differently than by `CommandLineToArgvW`. Enabled by the `verify-parser` feature.
+///
+/// The program name is skipped, because `CommandLineToArgvW` parses it by different rules.
+#[cfg(all(windows, feature = "verify-parser"))]
+fn verify_parser(line: &[u16]) {
+    let parsed: Vec<_> = globiter::GlobArgs::new(line).skip(1).map(|arg| arg.into_text()).collect();
+    let expected: Vec<_> = command_line_to_argv(line).into_iter().skip(1).collect();
+    assert_eq!(expected, parsed, "wild split the command line `{}` differently than CommandLineToArgvW", String::from_utf16_lossy(line));
+}
+
+/// Arguments split by Windows itself
+#[cfg(all(windows, feature = "verify-parser"))]
+fn command_line_to_argv(line: &[u16]) -> Vec<std::ffi::OsString> {
+    use std::os::windows::ffi::OsStringExt;
+    let line: Vec<u16> = line.iter().cloned().chain(Some(0)).collect();
+    let mut count = 0;
+    unsafe {
+        let argv = sys::CommandLineToArgvW(line.as_ptr(), &mut count);
+        if argv.is_null() {
+            return Vec::new();
+        }
+        let args = (0..count as usize).map(|i| {
+            let arg = *argv.add(i);
+            let mut len = 0;
+            while *arg.add(len) != 0 {
+                len += 1;
+            }
+            std::ffi::OsString::from_wide(std::slice::from_raw_parts(arg, len))
+        }).collect();
+        sys::LocalFree(argv.cast());
+        args
+    }
+}
+
+/// Arguments parsed from a command line that isn't kept, e.g. `lpCmdLine`, so it's parsed upfront.
+#[cfg(any(test, windows, target_os = "wasi"))]
+fn split_args(line: &[u16], config: Config) -> argsiter::Args {
+    let args: Vec<_> = globiter::GlobArgs::new(line).escape(config.escape_char).collect();
+    argsiter::Args::new(Some(globiter::CommandLine::Split(args.into_iter())), config)
+}
+
+/// Whether the raw command line, needed to tell quoted arguments apart, is available.
+///
+/// In some embedded or hosted environments Windows doesn't provide it. Then arguments are taken from `std::env::args_os()`
+/// and returned without any expansion. Always `true` on other platforms, where arguments are never expanded.
+pub fn command_line_available() -> bool {
+    #[cfg(windows)]
+    {
+        raw_command_line().is_some()
+    }
+    #[cfg(not(windows))]
+    {
+        true
+    }
 }
 
 #[cfg(windows)]
 fn raw_command_line() -> Option<&'static [u16]> {
     unsafe {
-        let line_ptr = GetCommandLineW();
+        let line_ptr = sys::GetCommandLineW();
         if line_ptr.is_null() {
             return None;
         }
         let mut len = 0;
-        while *line_ptr.offset(len as isize) != 0 {
+        while *line_ptr.add(len) != 0 {
             len += 1;
         }
         Some(std::slice::from_raw_parts(line_ptr, len))
@@ -128,17 +877,142 @@ fn parsed(s: &str) -> String {
 fn unquoted(s: &str) -> String {
     let t: Vec<_> = s.encode_utf16().collect();
     let args: Vec<_> = globiter::GlobArgs::new(&t)
-        .map(|s| s.text.to_string_lossy().to_string())
+        .map(|s| s.text().to_string_lossy().to_string())
         .collect();
     args.join(";")
 }
 
+#[test]
+fn test_program_name() {
+    let split = |line: &str| {
+        let line: Vec<u16> = line.encode_utf16().collect();
+        let (name, rest) = parser::split_program_name(&line);
+        (String::from_utf16(name).unwrap(), String::from_utf16(rest).unwrap())
+    };
+    assert_eq!(("prog.exe".to_owned(), " *.txt".to_owned()), split("prog.exe *.txt"));
+    assert_eq!((r"C:\Program Files\app".to_owned(), r#" "a b""#.to_owned()), split(r#""C:\Program Files\app" "a b""#));
+    assert_eq!(("a*b".to_owned(), "\tc".to_owned()), split("a*b\tc"));
+    assert_eq!(("unclosed x".to_owned(), String::new()), split("\"unclosed x"));
+    let line: Vec<u16> = r#""C:\dir" *""#.encode_utf16().collect();
+    let mut args = globiter::GlobArgs::new(&line);
+    args.skip_program_name();
+    assert_eq!(vec!["*"], args.map(|arg| arg.into_text().into_string().unwrap()).collect::<Vec<_>>());
+    assert!(!program_name().is_empty());
+    assert_eq!(args_os().count() - 1, args_skip_program().count());
+}
+
+#[test]
+fn test_parse_command_line() {
+    let line: Vec<u16> = r#"  "quo\"ted"  "*" \\server\"#.encode_utf16().collect();
+    assert_eq!(vec!["quo\"ted", "*", "\\\\server\\"], parse_command_line(&line).collect::<Vec<_>>());
+    assert_eq!(3, parse_command_line(&line).len());
+    assert_eq!(0, parse_command_line(&[]).count());
+    let line: Vec<u16> = r#""a""b c" d"#.encode_utf16().collect();
+    assert_eq!(vec!["a\"b", "c d"], parse_command_line(&line).collect::<Vec<_>>());
+    assert_eq!(vec!["a\"b c", "d"], parse_command_line_with(&line, Syntax::Crt2008).collect::<Vec<_>>());
+    assert_eq!(2, parse_command_line_with(&line, Syntax::Crt2008).len());
+}
+
+#[test]
+fn test_parse_command_line_wide() {
+    use std::borrow::Cow;
+    for line in [r#"  "quo\"ted"  "*" \\server\ a\\"b c" d\e  "#, "", "   ", r#"x"" "" y"#] {
+        let line: Vec<u16> = line.encode_utf16().collect();
+        let expected: Vec<_> = parse_command_line(&line).collect();
+        let args: Vec<_> = parse_command_line_wide(&line).map(|arg| parser::from_wide(&arg)).collect();
+        assert_eq!(expected, args);
+        assert_eq!(expected.len(), parse_command_line_wide(&line).len());
+    }
+    let line: Vec<u16> = r#"a\b "c" d"#.encode_utf16().collect();
+    let borrowed: Vec<_> = parse_command_line_wide(&line).map(|arg| matches!(arg, Cow::Borrowed(_))).collect();
+    assert_eq!(vec![true, false, true], borrowed);
+}
+
+#[test]
+fn test_expand_lines() {
+    let items = read_list(&b"\xEF\xBB\xBFCargo.tom? \"*.md\"\r\n\n  src/lib.r? \n"[..], ListSeparator::Lines).unwrap();
+    assert_eq!(vec!["Cargo.tom? \"*.md\"", "", "  src/lib.r? "], items);
+    let args: Vec<_> = list_args(&items, ListSeparator::Lines, Config::new()).collect();
+    assert_eq!(vec!["Cargo.toml", "*.md", "src/lib.rs"], args);
+    let items = read_list(&b"Cargo.tom?\0\"*.md\"\0a b\0"[..], ListSeparator::Nul).unwrap();
+    let args: Vec<_> = list_args(&items, ListSeparator::Nul, Config::new()).collect();
+    assert_eq!(vec!["Cargo.toml", "\"*.md\"", "a b"], args);
+    assert_eq!(std::io::ErrorKind::InvalidData, read_list(&b"\xFF"[..], ListSeparator::Lines).unwrap_err().kind());
+    assert_eq!(2, expand_lines(&b"a\n\"b c\""[..], ListSeparator::Lines).unwrap().count());
+}
+
+#[test]
+fn test_empty_command_line() {
+    let blank = Config::new().command_line_source(|| Some(" \t ".encode_utf16().collect()));
+    assert_eq!(vec![std::env::current_exe().unwrap().into_os_string()], blank.args_os().collect::<Vec<_>>());
+    assert_eq!(0, blank.clone().empty_command_line(EmptyCommandLine::Empty).args_os().count());
+    let empty = Config::new().command_line_source(|| Some(Vec::new())).empty_command_line(EmptyCommandLine::Empty);
+    assert_eq!(0, empty.args_os().count());
+    assert!(is_blank(&[]) && !is_blank(&[u16::from(b'a')]));
+}
+
+#[test]
+fn test_split_args() {
+    let line: Vec<u16> = r#"Cargo.tom? "*.md" --"#.encode_utf16().collect();
+    let args: Vec<_> = split_args(&line, Config::new()).collect();
+    assert_eq!(vec!["Cargo.toml", "*.md", "--"], args);
+}
+
+#[test]
+fn test_iterators_are_send_sync() {
+    fn assert_send_sync<T: Send + Sync + std::fmt::Debug + std::iter::FusedIterator>() {}
+    assert_send_sync::<Args>();
+    assert_send_sync::<ArgsOs>();
+    assert_send_sync::<TryArgs>();
+    assert_send_sync::<ArgsLossy>();
+    assert_send_sync::<ArgsPaths>();
+    assert_send_sync::<ArgsEntries>();
+    assert_send_sync::<ArgsEager>();
+    assert_send_sync::<Expansions>();
+    assert_send_sync::<Expand<std::vec::IntoIter<std::ffi::OsString>>>();
+    assert_send_sync::<CommandLineArgs>();
+}
+
+#[test]
+fn test_eager() {
+    let args: Vec<_> = args_os().collect();
+    let mut eager = args_os().eager();
+    assert_eq!(args.len(), eager.len());
+    assert_eq!(args[..], *eager.as_slice());
+    assert_eq!(Some(&args[0]), eager.next().as_ref());
+    assert_eq!(args.len() - 1, eager.len());
+    assert!(eager.rev().eq(args[1..].iter().rev().cloned()));
+}
+
+#[test]
+fn test_set_config_once() {
+    // other tests may have used the global config already
+    let _ = set_config(Config::new());
+    assert!(set_config(Config::new()).is_err());
+    // `Config` isn't comparable, since it holds function pointers and a `Vfs`, but the defaults have none
+    assert_eq!(format!("{:?}", Config::new()), format!("{:?}", Config::global()));
+}
+
 #[test]
 #[cfg(windows)]
 fn test_actual_args() {
     assert!(globs().expect("args found").count() >= 1);
 }
 
+#[test]
+#[cfg(feature = "always-expand")]
+fn test_args_always_expanded() {
+    assert!(args_always_expanded().count() >= 1);
+}
+
+#[test]
+#[cfg(all(windows, feature = "verify-parser"))]
+fn test_verify_parser() {
+    for line in &[r#"x a "b c" d\"e"#, r#"x "f\\" g\\h "\"i\\\" j"#, r#"x *.txt "*.md" --k="l m""#] {
+        verify_parser(&line.encode_utf16().collect::<Vec<_>>());
+    }
+}
+
 #[test]
 fn test_parse_1() {
     assert_eq!(r#"漢字"#, parsed("漢字"));
@@ -198,3 +1072,20 @@ fn test_parse_multi() {
     assert_eq!(r#"\\;\""#, parsed(r#"\\\\"       \\\"  "#));
     assert_eq!("x;    ", parsed(r#" x  "    "#));
 }
+
+#[test]
+fn test_parse_backslashes_before_space() {
+    assert_eq!(r#"a\;b"#, parsed(r#"a\ b"#));
+    assert_eq!(r#"C:\dir\\;*.txt"#, parsed(r#"C:\dir\\ *.txt"#));
+    assert_eq!(r#"a\ b"#, parsed(r#""a\ b""#));
+}
+
+#[test]
+fn test_parse_tabs() {
+    assert_eq!("a;b;c\\;d;e\tf", parsed("\ta\t\tb \tc\\\t\"d\"\t\"e\tf\"\t"));
+    let line: Vec<u16> = "prog.exe\ta\tb\\\tc".encode_utf16().collect();
+    assert_eq!(vec!["prog.exe", "a", "b\\", "c"], parse_command_line(&line).collect::<Vec<_>>());
+    let args: Vec<_> = parse_command_line_wide(&line).map(|arg| parser::from_wide(&arg)).collect();
+    assert_eq!(vec!["prog.exe", "a", "b\\", "c"], args);
+    assert_eq!(4, parse_command_line_wide(&line).len());
+}
@@ -0,0 +1,89 @@
+//! Extended-length `\\?\` paths, which aren't limited to `MAX_PATH`
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf, Prefix};
+
+/// Paths this long may not work with Windows APIs unless they're extended-length paths.
+/// It's less than `MAX_PATH` (260), since directory paths need room for a file name.
+pub(crate) const LEGACY_LIMIT: usize = 248;
+
+#[cfg(windows)]
+pub(crate) const MAX_PATH: usize = 260;
+
+/// Length in UTF-16 code units, as counted by Windows
+pub(crate) fn wide_len(path: &Path) -> usize {
+    path.to_string_lossy().encode_utf16().count()
+}
+
+/// Whether a name in the path ends with a dot or a space. Windows strips them from normal paths,
+/// so only a `\\?\` path refers to such a file, e.g. one created by a Unix tool or through a `\\?\` path.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn needs_verbatim(path: &Path) -> bool {
+    path.components().any(|component| match component {
+        Component::Normal(name) => matches!(name.as_encoded_bytes().last(), Some(b'.' | b' ')),
+        _ => false,
+    })
+}
+
+/// Converts the path to an absolute `\\?\` path.
+///
+/// Such paths aren't normalized by Windows, so `.` and `..` are resolved here and all separators become `\`.
+pub(crate) fn extended(path: &Path) -> PathBuf {
+    let absolute;
+    let path = if path.is_absolute() {
+        path
+    } else {
+        match ::std::env::current_dir() {
+            Ok(dir) => {
+                absolute = dir.join(path);
+                &absolute
+            },
+            Err(_) => return path.to_owned(),
+        }
+    };
+    let mut out = OsString::new();
+    let mut parts: Vec<&::std::ffi::OsStr> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Verbatim(_) | Prefix::VerbatimUNC(..) | Prefix::VerbatimDisk(_) | Prefix::DeviceNS(_) => return path.to_owned(),
+                Prefix::UNC(server, share) => {
+                    out.push(r"\\?\UNC\");
+                    out.push(server);
+                    out.push(r"\");
+                    out.push(share);
+                },
+                Prefix::Disk(_) => {
+                    out.push(r"\\?\");
+                    out.push(prefix.as_os_str());
+                },
+            },
+            Component::RootDir | Component::CurDir => {},
+            Component::ParentDir => { parts.pop(); },
+            Component::Normal(name) => parts.push(name),
+        }
+    }
+    for name in parts {
+        out.push(r"\");
+        out.push(name);
+    }
+    PathBuf::from(out)
+}
+
+#[test]
+fn detects_trailing_dots_and_spaces() {
+    assert!(needs_verbatim(Path::new("dir./file.txt")));
+    assert!(needs_verbatim(Path::new("dir/notes ")));
+    assert!(needs_verbatim(Path::new("../a/...")));
+    assert!(!needs_verbatim(Path::new("./a/../b.txt")));
+    assert!(!needs_verbatim(Path::new(".hidden/.x")));
+}
+
+#[cfg(windows)]
+#[test]
+fn extends_paths() {
+    assert_eq!(Path::new(r"\\?\C:\a\c"), extended(Path::new(r"C:\a\.\b\..\c")));
+    assert_eq!(Path::new(r"\\?\C:\a\b"), extended(Path::new("C:/a/b")));
+    assert_eq!(Path::new(r"\\?\UNC\server\share\logs\x.log"), extended(Path::new(r"\\server\share\logs\x.log")));
+    assert_eq!(Path::new(r"\\?\C:\a"), extended(Path::new(r"\\?\C:\a")));
+    assert!(extended(Path::new("src")).starts_with(r"\\?\"));
+}
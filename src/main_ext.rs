@@ -0,0 +1,42 @@
+//! Arguments of `main` with the `#[wild::main]` attribute
+use std::ffi::OsString;
+use std::path::PathBuf;
+use iter::{Args, ArgsOs};
+
+/// Types that `main` with `#[wild::main]` can take: `Vec<String>`, `Vec<OsString>`, `Vec<PathBuf>`, or the `wild::Args` and `wild::ArgsOs` iterators.
+///
+/// Like in `wild::args()`, the first argument is the program name. `Vec<String>` panics if an argument isn't valid Unicode.
+pub trait MainArgs {
+    /// Glob-expanded arguments of the program
+    fn main_args() -> Self;
+}
+
+impl MainArgs for Vec<String> {
+    fn main_args() -> Self {
+        ::args().collect()
+    }
+}
+
+impl MainArgs for Vec<OsString> {
+    fn main_args() -> Self {
+        ::args_os().collect()
+    }
+}
+
+impl MainArgs for Vec<PathBuf> {
+    fn main_args() -> Self {
+        ::args_os().map(PathBuf::from).collect()
+    }
+}
+
+impl MainArgs for Args {
+    fn main_args() -> Self {
+        ::args()
+    }
+}
+
+impl MainArgs for ArgsOs {
+    fn main_args() -> Self {
+        ::args_os()
+    }
+}
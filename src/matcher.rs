@@ -0,0 +1,51 @@
+//! Custom pattern syntax, plugged in with `Config::matcher()`
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+use std::path::Path;
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+use walk::{DirCache, Entry, Matcher, Part};
+
+/// Matches file names against one path component of a pattern. Returned by `PatternMatcher::compile()`.
+pub type NameMatcher = Box<dyn Fn(&OsStr) -> bool + Send + Sync>;
+
+/// Pattern syntax used instead of the `Engine`s, e.g. regular expressions. See [`Config::matcher`](struct.Config.html#method.matcher).
+///
+/// Patterns are matched one path component at a time, so `src/*/mod.rs` lists only `src` and its subdirectories.
+/// Directories are listed by wild, using the `Vfs` of the `Config`, and a `**` component matches any number of directories.
+pub trait PatternMatcher: fmt::Debug + Send + Sync {
+    /// Rewrites an argument before it's expanded, e.g. to add a default extension. It's unchanged by default.
+    fn translate(&self, arg: &OsStr) -> OsString {
+        arg.to_owned()
+    }
+
+    /// Whether the translated argument has to be expanded. Other arguments are passed through without touching the file system.
+    fn is_pattern(&self, pattern: &OsStr) -> bool;
+
+    /// A function matching file names against a single path component of the pattern,
+    /// or `None` if the component is a literal name.
+    fn compile(&self, component: &OsStr) -> Option<NameMatcher>;
+}
+
+/// Adapts a `PatternMatcher` to the directory walker
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+pub(crate) struct CustomMatcher<'a>(pub &'a dyn PatternMatcher);
+
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+impl<'a> Matcher for CustomMatcher<'a> {
+    type Pattern = NameMatcher;
+
+    fn compile(&self, component: &OsStr) -> Part<NameMatcher> {
+        if component == "**" {
+            return Part::Recursive;
+        }
+        match self.0.compile(component) {
+            Some(matches) => Part::Pattern(matches),
+            None => Part::Literal,
+        }
+    }
+
+    fn list(&self, dir: &Path, pattern: &NameMatcher, cache: &DirCache) -> Vec<Entry> {
+        cache.list_matching(dir, pattern)
+    }
+}
@@ -0,0 +1,347 @@
+//! Tests of expansion in an in-memory file system, including paths that can't be created on the test machine, like UNC shares
+use argsiter::Args;
+use config::{AttributeFilter, Config, EscapeChar};
+use globiter::{Arg, GlobArgs};
+use vfs::MemoryFs;
+
+/// Expands the command line in the mock file system
+fn expanded(fs: MemoryFs, cmd: &str, config: Config) -> Vec<String> {
+    let cmd = cmd.encode_utf16().collect::<Vec<_>>();
+    let args = Args::new(Some(GlobArgs::new(&cmd).collect::<Vec<Arg>>().into_iter()), config.vfs(fs));
+    args.map(|arg| arg.to_string_lossy().into_owned()).collect()
+}
+
+#[test]
+fn mock_absolute_paths() {
+    let fs = || MemoryFs::new(&["/srv/logs/a.log", "/srv/logs/b.txt", "/srv/logs/old/c.log", "rel/d.log"]);
+    assert_eq!(vec!["x", "/srv/logs/a.log", "rel/d.log", "/srv/no/*.log"], expanded(fs(), "x /srv/logs/*.log r?l/*.log /srv/no/*.log", Config::new()));
+    assert_eq!(vec!["x", "/srv/logs/a.log", "/srv/logs/old/c.log"], expanded(fs(), "x /srv/**/*.log", Config::new()));
+    assert_eq!(vec!["x", "/srv/logs/b.txt"], expanded(fs(), "x /s?v/logs/*.*t", Config::new().engine(::Engine::Dos)));
+}
+
+#[test]
+fn memory_attributes_and_errors() {
+    let fs = || MemoryFs::new(&["b.rs", "a.rs", ".c.rs", "d.rs", "locked/e.rs", "open/f.rs"]).hidden("d.rs").system("b.rs").unreadable("locked");
+    assert_eq!(vec!["x", ".c.rs", "a.rs", "b.rs", "d.rs"], expanded(fs(), "x *.rs", Config::new()));
+    assert_eq!(vec!["x", ".c.rs", "a.rs"], expanded(fs(), "x *.rs", Config::new().hidden_files(AttributeFilter::Exclude).system_files(AttributeFilter::Exclude)));
+    assert_eq!(vec!["x", "open/f.rs"], expanded(fs(), "x */*.rs", Config::new()));
+}
+
+#[test]
+fn memory_errors_are_reported() {
+    use std::sync::{Arc, Mutex};
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let collected = errors.clone();
+    let config = Config::new().on_error(move |pattern, dir, err| {
+        collected.lock().unwrap().push((pattern.to_owned(), dir.to_owned(), err.kind()));
+    });
+    let fs = MemoryFs::new(&["locked/a.rs", "open/b.rs"]).unreadable("locked");
+    assert_eq!(vec!["x", "open/b.rs", "missing/*.rs"], expanded(fs, "x */*.rs missing/*.rs", config));
+    assert_eq!(vec![("*/*.rs".into(), "locked".into(), ::std::io::ErrorKind::PermissionDenied)], *errors.lock().unwrap());
+}
+
+#[test]
+fn memory_progress_and_cancel() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use progress::{CancelToken, Progress};
+    static DIRS: AtomicUsize = AtomicUsize::new(0);
+    static MATCHES: AtomicUsize = AtomicUsize::new(0);
+    fn progress(progress: Progress) {
+        DIRS.store(progress.dirs_visited, Ordering::SeqCst);
+        MATCHES.store(progress.matches, Ordering::SeqCst);
+    }
+    let fs = || MemoryFs::new(&["a/1.rs", "a/2.rs", "b/3.rs"]);
+    assert_eq!(vec!["x", "a/1.rs", "a/2.rs", "b/3.rs"], expanded(fs(), "x */*.rs", Config::new().progress(progress)));
+    assert_eq!((3, 3), (DIRS.load(Ordering::SeqCst), MATCHES.load(Ordering::SeqCst)));
+
+    let token = CancelToken::new();
+    token.cancel();
+    assert_eq!(vec!["x", "*/*.rs"], expanded(fs(), "x */*.rs", Config::new().cancel_token(token)));
+}
+
+#[test]
+fn memory_custom_matcher() {
+    use std::ffi::OsStr;
+    use matcher::{NameMatcher, PatternMatcher};
+
+    /// Names with the given extension, written as `.ext`
+    #[derive(Debug)]
+    struct Extension;
+
+    impl PatternMatcher for Extension {
+        fn is_pattern(&self, pattern: &OsStr) -> bool {
+            pattern.to_string_lossy().contains("/.") || pattern.to_string_lossy().starts_with('.')
+        }
+
+        fn compile(&self, component: &OsStr) -> Option<NameMatcher> {
+            let extension = component.to_str()?.strip_prefix('.')?.to_owned();
+            Some(Box::new(move |name: &OsStr| ::std::path::Path::new(name).extension() == Some(OsStr::new(&extension))))
+        }
+    }
+
+    let fs = || MemoryFs::new(&["a.rs", "b.md", "src/c.rs", "src/d/e.rs"]);
+    assert_eq!(vec!["x", "a.rs", "src/c.rs", "*.md"], expanded(fs(), "x .rs src/.rs *.md", Config::new().matcher(Extension)));
+    assert_eq!(vec!["x", "a.rs", "src/c.rs", "src/d/e.rs"], expanded(fs(), "x **/.rs", Config::new().matcher(Extension)));
+}
+
+#[cfg(windows)]
+fn share() -> MemoryFs {
+    MemoryFs::new(&[r"\\server\share\logs\a.log", r"\\server\share\logs\b.txt", r"\\server\share\logs\old\c.log"])
+}
+
+#[cfg(windows)]
+#[test]
+fn unc_paths() {
+    assert_eq!(vec!["x", r"\\server\share\logs\a.log"], expanded(share(), r"x \\server\share\logs\*.log", Config::new()));
+    assert_eq!(vec!["x", r"\\server\share\logs\a.log", r"\\server\share\logs\old\c.log"], expanded(share(), r"x \\server\share\**\*.log", Config::new()));
+    // the root is kept as typed
+    assert_eq!(vec!["x", r"//server/share\logs\a.log"], expanded(share(), r"x //server/share/l?gs/*.log", Config::new()));
+    assert_eq!(vec!["x", r"\\server\share\logs\b.txt"], expanded(share(), r"x \\server\share\*\*.txt", Config::new().engine(::Engine::Dos)));
+}
+
+#[cfg(windows)]
+#[test]
+fn unc_prefix_is_not_a_pattern() {
+    assert_eq!(vec!["x", r"\\serv?r\share\logs\*.log", r"\\server\*\logs\*.log"], expanded(share(), r"x \\serv?r\share\logs\*.log \\server\*\logs\*.log", Config::new()));
+}
+
+#[cfg(windows)]
+#[test]
+fn verbatim_unc_paths() {
+    let fs = || MemoryFs::new(&[r"\\?\UNC\server\share\logs\a.log", r"\\?\UNC\server\share\logs\b.txt"]);
+    assert_eq!(vec!["x", r"\\?\UNC\server\share\logs\a.log"], expanded(fs(), r"x \\?\UNC\server\share\logs\*.log", Config::new()));
+    assert_eq!(vec!["x", r"\\?\UNC\server\share\logs\a.log"], expanded(fs(), r"x \\?\UNC\server\share\logs\*.log", Config::new().preserve_prefix(true)));
+    assert_eq!(vec!["x", r"\\?\UNC\server\share\logs\b.txt"], expanded(fs(), r"x \\?\UNC\server\share\l?gs\*.txt", Config::new().engine(::Engine::Dos)));
+}
+
+#[test]
+fn memory_metadata() {
+    let fs = MemoryFs::new(&["src/lib.rs", "src/bin/app.rs"]);
+    let cmd = "x src/* */lib.rs src/**".encode_utf16().collect::<Vec<_>>();
+    let mut args = Args::new(Some(GlobArgs::new(&cmd).collect::<Vec<Arg>>().into_iter()), Config::new().vfs(fs));
+    let mut next = || args.next_entry().map(|(arg, _, metadata)| (arg.into_string().unwrap(), metadata.map(|m| m.is_dir)));
+    assert_eq!(Some(("x".to_owned(), None)), next());
+    assert_eq!(Some(("src/bin".to_owned(), Some(true))), next());
+    assert_eq!(Some(("src/lib.rs".to_owned(), Some(false))), next());
+    assert_eq!(Some(("src/lib.rs".to_owned(), Some(false))), next());
+    assert_eq!(Some(("src".to_owned(), Some(true))), next());
+    assert_eq!(Some(("src/bin".to_owned(), Some(true))), next());
+    assert_eq!(None, next());
+}
+
+#[test]
+fn collects_all_errors() {
+    let fs = MemoryFs::new(&["locked/a.rs", "open/b.rs", "open/c.rs"]).unreadable("locked");
+    let cmd = "x l*/*.rs open/*.rs missing/*.rs".encode_utf16().collect::<Vec<_>>();
+    let args = |config: Config| Args::new(Some(GlobArgs::new(&cmd).collect::<Vec<Arg>>().into_iter()), config).try_collect();
+    let err = args(Config::new().vfs(fs.clone()).max_results(1)).unwrap_err();
+    assert_eq!(1, err.too_many_matches.len());
+    assert_eq!("open/*.rs", err.too_many_matches[0].pattern);
+    assert_eq!(1, err.unreadable_dirs.len());
+    assert_eq!("l*/*.rs", err.unreadable_dirs[0].pattern);
+    assert_eq!(::std::path::Path::new("locked"), err.unreadable_dirs[0].dir);
+    assert!(err.to_string().contains("`open/*.rs` matches more than 1 paths; pattern `l*/*.rs` can't search `locked`"));
+    let fs = MemoryFs::new(&["open/b.rs"]);
+    assert_eq!(vec!["x", "l*/*.rs", "open/b.rs", "missing/*.rs"], args(Config::new().vfs(fs)).unwrap());
+}
+
+#[test]
+fn reports_failed_entries() {
+    use std::sync::{Arc, Mutex};
+    let fs = MemoryFs::new(&["dir/a.rs", "dir/b.rs", "dir/c.rs", "other/d.rs"]).unreadable_entry("dir/b.rs");
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let collected = errors.clone();
+    let config = Config::new().on_error(move |_, dir, err| collected.lock().unwrap().push((dir.to_owned(), err.kind())));
+    assert_eq!(vec!["x", "dir/*.rs", "other/d.rs"], expanded(fs.clone(), "x dir/*.rs other/*.rs", config));
+    assert_eq!(vec![("dir".into(), ::std::io::ErrorKind::InvalidData)], *errors.lock().unwrap());
+    let cmd = "x d*/*.rs".encode_utf16().collect::<Vec<_>>();
+    let err = Args::new(Some(GlobArgs::new(&cmd).collect::<Vec<Arg>>().into_iter()), Config::new().vfs(fs)).try_collect().unwrap_err();
+    assert_eq!(::std::path::Path::new("dir"), err.unreadable_dirs[0].dir);
+}
+
+#[test]
+fn memory_base_dir() {
+    let fs = || MemoryFs::new(&["srv/a.txt", "srv/sub/b.txt", "c.txt", "/abs/d.txt"]);
+    let config = || Config::new().base_dir("srv");
+    assert_eq!(vec!["x", "c.txt"], expanded(fs(), "x *.txt", Config::new()));
+    assert_eq!(vec!["x", "srv/a.txt", "srv/sub/b.txt", "/abs/d.txt", "-v"], expanded(fs(), "x *.txt s?b/*.txt /abs/*.txt -v", config()));
+    assert_eq!(vec!["x", "srv/sub"], expanded(fs(), "x **", config()));
+    assert_eq!(vec!["x", "srv/a.txt", "sub/x*"], expanded(fs(), "x ?.txt sub/x*", config().engine(::Engine::Dos).exact_names(true)));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn memory_parallel_walk() {
+    let fs = || MemoryFs::new(&["a/1.rs", "a/b/2.rs", "a/b/c/3.rs", "d/4.rs", "d/e/5.txt", "f/6.rs", "7.rs"]);
+    let cmd = "x **/*.rs */*/*.* ** -v";
+    let sequential = expanded(fs(), cmd, Config::new());
+    assert_eq!(sequential, expanded(fs(), cmd, Config::new().parallel_walk(4)));
+    assert_eq!(sequential, expanded(fs(), cmd, Config::new().parallel_walk(0)));
+    assert!(sequential.contains(&"a/b/c/3.rs".to_owned()));
+}
+
+#[test]
+fn memory_expand_if() {
+    let fs = || MemoryFs::new(&["a.rs", "b.rs"]);
+    let config = || Config::new().expand_if(|index, arg| index != 2 && arg != "--");
+    assert_eq!(vec!["x", "a.rs", "b.rs", "*.rs", "a.rs", "b.rs"], expanded(fs(), "x *.rs *.rs *.rs", config()));
+    assert_eq!(vec!["x", "a.rs", "b.rs", "--", "a.rs", "b.rs"], expanded(fs(), "x *.rs -- *.rs", config()));
+    assert_eq!(vec!["x", "*.rs", "a.rs", "b.rs", "a.rs", "b.rs"], expanded(fs(), "x *.rs *.rs *.rs", Config::new().expand_range(2..)));
+    assert_eq!(vec!["x", "a.rs", "b.rs", "*.rs", "*.rs"], expanded(fs(), "x *.rs *.rs *.rs", Config::new().expand_range(..2)));
+    assert_eq!(vec!["x", "*.rs", "*.rs", "a.rs", "b.rs"], expanded(fs(), "x *.rs *.rs *.rs", config().expand_range(2..=3)));
+}
+
+#[test]
+fn memory_extglob() {
+    let fs = || MemoryFs::new(&["a.rs", "b.rs", "c.txt", "@(a).md", "src/x.rs", "lib/y.rs"]);
+    let config = || Config::new().extglob(true);
+    assert_eq!(vec!["x", "@(a|b).rs"], expanded(fs(), "x @(a|b).rs", Config::new()));
+    assert_eq!(vec!["x", "a.rs", "b.rs"], expanded(fs(), "x @(a|b).rs", config()));
+    assert_eq!(vec!["x", "@(a).md", "c.txt", "lib", "src"], expanded(fs(), "x !(*.rs)", config()));
+    assert_eq!(vec!["x", "lib/y.rs", "src/x.rs"], expanded(fs(), "x +(src|lib)/*.rs", config()));
+    assert_eq!(vec!["x", "@(a).md"], expanded(fs(), r#"x "@(a)"*"#, config()));
+}
+
+#[test]
+fn memory_exclusions() {
+    let fs = || MemoryFs::new(&["a.rs", "a.bak", "b.BAK", "src/c.rs", "src/d.bak", "target/e.rs"]);
+    assert_eq!(vec!["x", "a.rs", "src/c.rs"], expanded(fs(), "x *.rs **/*.rs", Config::new().exclude("*.bak").exclude("target/**").unique(true)));
+    assert_eq!(vec!["x", "a.rs", "src", "target", "-v"], expanded(fs(), "x * !*.bak -v", Config::new().negated_args(true)));
+    assert_eq!(vec!["x", "src/d.bak"], expanded(fs(), "x !*.rs **/*.??? !a*", Config::new().negated_args(true).case_sensitive(true).exclude("*.BAK")));
+    assert_eq!(vec!["x", "a.bak", "b.BAK", "!*.bak"], expanded(fs(), "x *.bak !*.bak", Config::new()));
+    assert_eq!(vec!["x", "*.bak", "!a*"], expanded(fs(), r#"x *.bak "!a*""#, Config::new().negated_args(true).exclude("?.bak")));
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn memory_normalization() {
+    use config::Normalization;
+    let fs = || MemoryFs::new(&["cafe\u{301}/menu.txt", "cafe\u{301}.txt"]);
+    let cmd = "x caf\u{e9}* caf\u{e9}/*.txt";
+    assert_eq!(vec!["x", "caf\u{e9}*", "caf\u{e9}/*.txt"], expanded(fs(), cmd, Config::new()));
+    let matches = vec!["x", "cafe\u{301}", "cafe\u{301}.txt", "cafe\u{301}/menu.txt"];
+    assert_eq!(matches, expanded(fs(), cmd, Config::new().normalization(Normalization::Nfc)));
+    assert_eq!(matches, expanded(fs(), cmd, Config::new().normalization(Normalization::Nfd).engine(::Engine::Dos)));
+}
+
+#[test]
+fn memory_no_match() {
+    use std::sync::Mutex;
+    static UNMATCHED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    fn record(pattern: &::std::ffi::OsStr) {
+        UNMATCHED.lock().unwrap().push(pattern.to_string_lossy().into_owned());
+    }
+    let fs = || MemoryFs::new(&["a.rs", "b.txt"]);
+    let args = expanded(fs(), r#"x *.csv "*.rs" plain *.rs --out=*.md *.t?t "q"*.log"#, Config::new().option_values(::OptionValues::Repeat).on_no_match(record));
+    assert_eq!(vec!["x", "*.csv", "*.rs", "plain", "a.rs", "--out=*.md", "b.txt", "q*.log"], args);
+    assert_eq!(vec!["*.csv", "*.md", "q*.log"], *UNMATCHED.lock().unwrap());
+}
+
+#[test]
+fn memory_escape_char() {
+    let fs = MemoryFs::new(&["a.rs", "b.rs", "`weird`.rs"]);
+    let cmd: Vec<u16> = "x *.rs `*.rs ``weird``.rs `\"*\".rs".encode_utf16().collect();
+    let args = GlobArgs::new(&cmd).escape(EscapeChar::Backtick).collect::<Vec<Arg>>();
+    let expanded: Vec<_> = Args::new(Some(args.into_iter()), Config::new().expand_quoted(true).vfs(fs)).collect();
+    assert_eq!(vec!["x", "`weird`.rs", "a.rs", "b.rs", "*.rs", "`weird`.rs", "`weird`.rs"], expanded);
+}
+
+#[test]
+fn memory_glob() {
+    let fs = || MemoryFs::new(&["src/a.rs", "src/b.rs", "--x=c.rs", "locked/d.rs"]).unreadable("locked");
+    let glob = |pattern: &str, config: Config| ::argsiter::glob(pattern.into(), &config.vfs(fs()));
+    let paths = |results: Vec<Result<::std::path::PathBuf, ::Error>>| results.into_iter().map(|path| path.unwrap().to_string_lossy().into_owned()).collect::<Vec<_>>();
+    assert_eq!(vec!["src/a.rs", "src/b.rs"], paths(glob("src/*.rs", Config::new())));
+    assert_eq!(vec!["--x=c.rs"], paths(glob("--x=*.rs", Config::new().option_values(::OptionValues::Repeat))));
+    assert_eq!(vec!["src/*.txt"], paths(glob("src/*.txt", Config::new())));
+    let mut results = glob("*/*.rs", Config::new());
+    let error = results.pop().unwrap().unwrap_err();
+    assert_eq!(vec!["src/a.rs", "src/b.rs"], paths(results));
+    assert_eq!("*/*.rs", error.unreadable_dirs[0].pattern);
+    let error = glob("src/*.rs", Config::new().max_results(1)).pop().unwrap().unwrap_err();
+    assert_eq!(1, error.too_many_matches.len());
+}
+
+#[test]
+fn memory_device_names() {
+    use std::sync::Mutex;
+    static FLAGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    fn record(arg: &::std::ffi::OsStr) {
+        FLAGGED.lock().unwrap().push(arg.to_string_lossy().into_owned());
+    }
+    let fs = || MemoryFs::new(&["a.rs", "con", "logs/nul.txt"]);
+    let cmd = "x * logs/* NUL --out=NUL";
+    assert_eq!(vec!["x", "a.rs", "con", "logs", "logs/nul.txt", "NUL", "--out=NUL"], expanded(fs(), cmd, Config::new()));
+    assert_eq!(vec!["x", "a.rs", "logs", "--out=NUL"], expanded(fs(), cmd, Config::new().device_names(::DeviceNames::Skip).on_device_name(record)));
+    assert_eq!(vec!["con", "logs/nul.txt", "NUL"], *FLAGGED.lock().unwrap());
+    assert_eq!(7, expanded(fs(), cmd, Config::new().device_names(::DeviceNames::Flag)).len());
+}
+
+#[test]
+fn memory_max_results() {
+    let fs = || MemoryFs::new(&["a/1.rs", "a/2.rs", "a/b/3.rs", "a/b/c/4.rs", "d/5.rs"]);
+    assert_eq!(vec!["x", "a/1.rs", "a/2.rs", "a/b/3.rs", "y"], expanded(fs(), "x **/*.rs y", Config::new().max_results(3)));
+    let cmd = "x **/*.rs".encode_utf16().collect::<Vec<_>>();
+    let args = || Args::new(Some(GlobArgs::new(&cmd).collect::<Vec<Arg>>().into_iter()), Config::new().vfs(fs()).max_results(2));
+    assert_eq!(1, args().try_collect().unwrap_err().too_many_matches.len());
+    let mut iter = args();
+    assert_eq!(Some("x".into()), iter.next());
+    assert_eq!(Some("a/1.rs".into()), iter.next());
+    // listing stopped at the third match, without reading `a/b/c` and `d`
+    assert_eq!(3, iter.cache.len());
+    let err = iter.try_collect().unwrap_err();
+    assert_eq!("**/*.rs", err.too_many_matches[0].pattern);
+}
+
+#[test]
+fn memory_max_results_reports_every_pattern() {
+    use std::sync::{Arc, Mutex};
+    let fs = MemoryFs::new(&["a/1.rs", "a/2.rs", "b/3.rs", "b/4.rs"]);
+    let cmd = "x a/*.rs b/*.rs".encode_utf16().collect::<Vec<_>>();
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let collected = reported.clone();
+    let config = Config::new().vfs(fs).max_results(1).on_too_many_matches(move |err| collected.lock().unwrap().push(err.pattern.clone()));
+    let mut iter = Args::new(Some(GlobArgs::new(&cmd).collect::<Vec<Arg>>().into_iter()), config);
+    assert_eq!(vec!["x", "a/1.rs", "b/3.rs"], iter.by_ref().collect::<Vec<_>>());
+    assert_eq!(vec!["a/*.rs", "b/*.rs"], *reported.lock().unwrap());
+    let err = iter.try_collect().unwrap_err();
+    assert_eq!(vec!["a/*.rs", "b/*.rs"], err.too_many_matches.iter().map(|err| err.pattern.clone()).collect::<Vec<_>>());
+}
+
+#[test]
+fn falls_back_to_literal_brackets() {
+    let fs = MemoryFs::new(&["dir/[draft]notes.md"]);
+    assert_eq!(vec!["x", "dir/[draft]notes.md", "dir/[draft]*.txt"], expanded(fs, "x dir/[draft]no*.md dir/[draft]*.txt", Config::new()));
+}
+
+#[test]
+fn exact_names() {
+    let fs = || MemoryFs::new(&["dir/[a].txt", "dir/a.txt"]);
+    let cmd = "x dir/[a].txt dir/[a].t?t";
+    assert_eq!(vec!["x", "dir/a.txt", "dir/a.txt"], expanded(fs(), cmd, Config::new()));
+    assert_eq!(vec!["x", "dir/[a].txt", "dir/a.txt"], expanded(fs(), cmd, Config::new().exact_names(true)));
+}
+
+#[test]
+fn hidden_files() {
+    let fs = || MemoryFs::new(&[".hidden.txt", ".git/config.txt", "visible.txt"]).hidden(".hidden.txt").hidden(".git");
+    let cmd = "x *.txt **/c*.txt";
+    assert_eq!(vec!["x", ".hidden.txt", "visible.txt", ".git/config.txt"], expanded(fs(), cmd, Config::new()));
+    assert_eq!(vec!["x", "visible.txt", "**/c*.txt"], expanded(fs(), cmd, Config::new().hidden_files(AttributeFilter::Exclude)));
+    assert_eq!(vec!["x", ".hidden.txt", "**/c*.txt"], expanded(fs(), cmd, Config::new().hidden_files(AttributeFilter::Only)));
+}
+
+#[test]
+fn max_depth() {
+    let fs = || MemoryFs::new(&["top.txt", "a/mid.txt", "a/b/deep.txt"]);
+    assert_eq!(vec!["x", "top.txt", "a/mid.txt", "a/b/deep.txt"], expanded(fs(), "x **/*.txt", Config::new()));
+    assert_eq!(vec!["x", "top.txt", "a/mid.txt"], expanded(fs(), "x **/*.txt", Config::new().max_depth(1)));
+    assert_eq!(vec!["x", "top.txt"], expanded(fs(), "x **/*.txt", Config::new().max_depth(0)));
+}
+
+#[test]
+fn trailing_separator() {
+    let fs = || MemoryFs::new(&["sub.txt", "Cargo.toml"]).dir("sub");
+    assert_eq!(vec!["x", "sub", "sub.txt", "sub/", "sub/", "sub/"], expanded(fs(), "x sub* sub*/ */ sub/", Config::new()));
+    assert_eq!(vec!["x", "Cargo.toml*/"], expanded(fs(), "x Cargo.toml*/", Config::new()));
+}
@@ -0,0 +1,224 @@
+//! Glob patterns of single path components, shared by the directory walker and `wild::Matcher`
+use std::ffi::{OsStr, OsString};
+use case::fold;
+use config::Config;
+#[cfg(any(feature = "glob", feature = "globset"))]
+use wildcard::has_groups;
+
+/// The built-in matcher, used unless a glob crate is enabled
+#[cfg(not(any(feature = "glob", feature = "globset")))]
+pub(crate) type Pattern = ::wildcard::Pattern;
+
+/// With the `glob` feature the `glob` crate is used, as in older versions
+#[cfg(all(feature = "glob", not(feature = "globset")))]
+type CratePattern = ::glob::Pattern;
+
+/// With the `globset` feature, it's used instead, which adds `{a,b}` alternations
+#[cfg(feature = "globset")]
+type CratePattern = ::globset::GlobMatcher;
+
+#[cfg(any(feature = "glob", feature = "globset"))]
+#[derive(Debug, Clone)]
+pub(crate) enum Pattern {
+    Crate(CratePattern),
+    /// The crates take only `str`, so components with unpaired surrogates are matched by the built-in matcher.
+    /// So are components with `U+FFFD`, which would match the lossy conversion of any unpaired surrogate,
+    /// and extended glob groups, which the crates don't have.
+    Wide(::wildcard::Pattern),
+}
+
+#[cfg(not(any(feature = "glob", feature = "globset")))]
+pub(crate) fn compile(component: &OsStr, extglob: bool) -> Option<Pattern> {
+    Pattern::new(component, extglob)
+}
+
+#[cfg(any(feature = "glob", feature = "globset"))]
+pub(crate) fn compile(component: &OsStr, extglob: bool) -> Option<Pattern> {
+    match component.to_str() {
+        Some(text) if !(text.contains(char::REPLACEMENT_CHARACTER) || extglob && has_groups(component)) => compile_str(text).map(Pattern::Crate),
+        _ => ::wildcard::Pattern::new(component, extglob).map(Pattern::Wide),
+    }
+}
+
+#[cfg(all(feature = "glob", not(feature = "globset")))]
+fn compile_str(component: &str) -> Option<CratePattern> {
+    CratePattern::new(component).ok()
+}
+
+#[cfg(feature = "globset")]
+fn compile_str(component: &str) -> Option<CratePattern> {
+    // a backslash is a path separator on Windows, so it can't be an escape
+    ::globset::GlobBuilder::new(component).backslash_escape(false).build().ok().map(|glob| glob.compile_matcher())
+}
+
+#[cfg(not(any(feature = "glob", feature = "globset")))]
+pub(crate) fn is_match(pattern: &Pattern, name: &OsStr) -> bool {
+    pattern.matches(name)
+}
+
+#[cfg(any(feature = "glob", feature = "globset"))]
+pub(crate) fn is_match(pattern: &Pattern, name: &OsStr) -> bool {
+    match *pattern {
+        // a surrogate can be matched only by a wildcard, which matches `U+FFFD` too
+        Pattern::Crate(ref pattern) => is_match_str(pattern, &name.to_string_lossy()),
+        Pattern::Wide(ref pattern) => pattern.matches(name),
+    }
+}
+
+#[cfg(all(feature = "glob", not(feature = "globset")))]
+fn is_match_str(pattern: &CratePattern, name: &str) -> bool {
+    const OPTIONS: ::glob::MatchOptions = ::glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: false,
+        require_literal_leading_dot: false,
+    };
+    pattern.matches_with(name, &OPTIONS)
+}
+
+#[cfg(feature = "globset")]
+fn is_match_str(pattern: &CratePattern, name: &str) -> bool {
+    pattern.is_match(name)
+}
+
+/// A component of a `Matcher` pattern
+#[derive(Debug, Clone)]
+enum Part {
+    Name(Pattern),
+    /// Invalid patterns match only themselves, like invalid arguments are passed as-is
+    Literal(OsString),
+    /// `**`, any number of directories
+    Recursive,
+}
+
+/// A glob pattern compiled once, to test names from other sources, like archives or directory listings received over a network,
+/// without touching the file system.
+///
+/// It has the same syntax as an unquoted argument with `Engine::Glob`, and uses the case sensitivity, `brackets`, `extglob`
+/// and normalization settings of the `Config`. Settings that need the file system, like `hidden_files`, don't apply.
+///
+/// Patterns and names are split into components at both `/` and `\`, and `**` matches any number of directories.
+/// A pattern without a separator matches only names without one, like `*.rs` on the command line matches only in the current directory.
+///
+/// ```rust
+/// use std::ffi::OsStr;
+/// let matcher = wild::Matcher::new(OsStr::new("src/*.rs"));
+/// assert!(matcher.is_match(OsStr::new("src/lib.rs")));
+/// assert!(matcher.is_match(OsStr::new(r"SRC\Main.RS")));
+/// assert!(!matcher.is_match(OsStr::new("src/bin/main.rs")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    parts: Vec<Part>,
+    case_sensitive: bool,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: ::config::Normalization,
+}
+
+impl Matcher {
+    /// Compiles the pattern with the `Config` of `wild::set_config()`. See `Config::compile()`.
+    pub fn new(pattern: &OsStr) -> Self {
+        Config::global().compile(pattern)
+    }
+
+    pub(crate) fn with_config(pattern: &OsStr, config: &Config) -> Self {
+        let mut matcher = Self {
+            parts: Vec::new(),
+            case_sensitive: config.case_sensitive,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: config.normalization,
+        };
+        matcher.parts = components(pattern).into_iter().map(|component| {
+            if component == "**" {
+                return Part::Recursive;
+            }
+            let mut component = matcher.folded(component);
+            if !config.brackets {
+                component = literal_brackets(&component);
+            }
+            match compile(&component, config.extglob) {
+                Some(pattern) => Part::Name(pattern),
+                None => Part::Literal(component),
+            }
+        }).collect();
+        matcher
+    }
+
+    /// Whether the name or path matches the pattern. Relative paths are relative to the same directory as the pattern.
+    pub fn is_match(&self, path: &OsStr) -> bool {
+        let names: Vec<OsString> = components(path).into_iter().map(|name| self.folded(name)).collect();
+        matches_parts(&self.parts, &names)
+    }
+
+    /// Whether the pattern has no separator, so it matches only a name, e.g. of `Config::exclude()`
+    #[cfg_attr(not(any(test, windows, target_os = "wasi", feature = "always-expand")), allow(dead_code))]
+    pub(crate) fn is_single_name(&self) -> bool {
+        self.parts.len() == 1
+    }
+
+    fn folded(&self, s: &OsStr) -> OsString {
+        #[cfg(feature = "unicode-normalization")]
+        let normalized = ::case::normalize(s, self.normalization);
+        #[cfg(feature = "unicode-normalization")]
+        let s = &*normalized;
+        if self.case_sensitive {
+            s.to_owned()
+        } else {
+            fold(s)
+        }
+    }
+}
+
+/// Components split at `/` and `\`, without `.` and empty ones, except the empty one before a leading separator of an absolute path
+fn components(path: &OsStr) -> Vec<&OsStr> {
+    path.as_encoded_bytes().split(|&b| b == b'/' || b == b'\\').enumerate()
+        .filter(|&(i, component)| (i == 0 || !component.is_empty()) && component != b".")
+        // safe, since it's split at ASCII characters
+        .map(|(_, component)| unsafe { OsStr::from_encoded_bytes_unchecked(component) })
+        .collect()
+}
+
+/// The component with `[` and `]` escaped, for `Config::brackets(false)`
+fn literal_brackets(component: &OsStr) -> OsString {
+    let mut escaped = Vec::with_capacity(component.len());
+    for &b in component.as_encoded_bytes() {
+        if b == b'[' || b == b']' {
+            escaped.extend_from_slice(&[b'[', b, b']']);
+        } else {
+            escaped.push(b);
+        }
+    }
+    // safe, since only ASCII characters are added, and only around other ASCII characters
+    unsafe { OsString::from_encoded_bytes_unchecked(escaped) }
+}
+
+fn matches_parts(parts: &[Part], names: &[OsString]) -> bool {
+    match parts.split_first() {
+        None => names.is_empty(),
+        Some((&Part::Recursive, rest)) => (0..=names.len()).any(|i| matches_parts(rest, &names[i..])),
+        Some((part, rest)) => names.split_first().is_some_and(|(name, names)| matches_part(part, name) && matches_parts(rest, names)),
+    }
+}
+
+fn matches_part(part: &Part, name: &OsStr) -> bool {
+    match *part {
+        Part::Name(ref pattern) => is_match(pattern, name),
+        Part::Literal(ref literal) => literal == name,
+        Part::Recursive => true,
+    }
+}
+
+#[test]
+fn matches_paths_without_file_system() {
+    let matcher = |pattern: &str, config: Config| config.compile(OsStr::new(pattern));
+    let rs = matcher("src/**/*.rs", Config::new());
+    assert!(rs.is_match(OsStr::new("src/lib.rs")));
+    assert!(rs.is_match(OsStr::new("./SRC\\a\\b/c.rs")));
+    assert!(!rs.is_match(OsStr::new("lib.rs")) && !rs.is_match(OsStr::new("src/lib.rs/x")));
+    assert!(!matcher("*.rs", Config::new()).is_match(OsStr::new("src/lib.rs")));
+    assert!(!matcher("*.RS", Config::new().case_sensitive(true)).is_match(OsStr::new("lib.rs")));
+    assert!(matcher("/srv/*.log", Config::new()).is_match(OsStr::new("\\srv\\a.log")));
+    assert!(!matcher("/srv/*.log", Config::new()).is_match(OsStr::new("srv/a.log")));
+    assert!(matcher("[draft]*", Config::new().brackets(false)).is_match(OsStr::new("[draft] notes.md")));
+    assert!(matcher("[ab", Config::new()).is_match(OsStr::new("[ab")));
+    assert!(matcher("+(a|b).rs", Config::new().extglob(true)).is_match(OsStr::new("abba.rs")));
+}
@@ -0,0 +1,54 @@
+//! Long and 8.3 short forms of file names
+use std::ffi::OsString;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use config::NameForm;
+
+/// Converts the path with a function that has the calling convention of `GetLongPathNameW`
+fn convert(path: &Path, f: unsafe extern "system" fn(*const u16, *mut u16, u32) -> u32) -> Option<PathBuf> {
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut buf = vec![0u16; 261];
+    loop {
+        let len = unsafe { f(path.as_ptr(), buf.as_mut_ptr(), buf.len() as u32) } as usize;
+        if len == 0 {
+            return None;
+        }
+        if len < buf.len() {
+            return Some(PathBuf::from(OsString::from_wide(&buf[..len])));
+        }
+        // too small; `len` includes the terminator
+        buf.resize(len, 0);
+    }
+}
+
+/// The path with names in the given form. Paths that don't exist are left unchanged.
+pub(crate) fn in_form(path: &Path, form: NameForm) -> PathBuf {
+    let converted = match form {
+        NameForm::AsMatched => None,
+        NameForm::Long => convert(path, ::sys::GetLongPathNameW),
+        NameForm::Short => convert(path, ::sys::GetShortPathNameW),
+    };
+    converted.unwrap_or_else(|| path.to_owned())
+}
+
+#[test]
+fn converts_names() {
+    assert_eq!(Path::new("src\\names.rs"), in_form(Path::new("src\\names.rs"), NameForm::Long));
+    assert_eq!(Path::new("src/names.rs"), in_form(Path::new("src/names.rs"), NameForm::AsMatched));
+    assert_eq!(Path::new("_not_a_file_"), in_form(Path::new("_not_a_file_"), NameForm::Long));
+    assert_eq!(Path::new("_not_a_file_"), in_form(Path::new("_not_a_file_"), NameForm::Short));
+}
+
+#[test]
+fn converts_to_short_and_back() {
+    let temp = ::tempdir::TempDir::new("short_names");
+    // the temp dir itself may be given with short names
+    let file = in_form(temp.path(), NameForm::Long).join("a long file name.text");
+    ::std::fs::write(&file, "").unwrap();
+    let short = in_form(&file, NameForm::Short);
+    // short names may be disabled on the volume
+    if short != file {
+        assert!(!short.file_name().unwrap().to_string_lossy().contains(' '));
+        assert_eq!(file, in_form(&short, NameForm::Long));
+    }
+}
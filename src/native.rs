@@ -0,0 +1,134 @@
+//! Expansion using Windows' own wildcard matching
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+#[cfg(test)]
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{io, mem, ptr};
+use sys::*;
+use longpath;
+use walk::{has_wildcards, walk, DirCache, Entry, Found, Matcher, Part};
+#[cfg(test)]
+use walk::paths;
+use config::Config;
+use vfs::VfsMetadata;
+
+/// Lists entries matching `dir\pattern`, letting the OS do the filtering.
+///
+/// The OS matches against 8.3 short names too, so `*.htm` finds `.html` files, like `dir` does.
+pub(crate) fn find_in_dir(dir: &Path, pattern: &OsStr, case_sensitive: bool) -> io::Result<Vec<Entry>> {
+    let mut query = dir.join(pattern);
+    // std does this for its own file system calls, but here the OS API is called directly.
+    // The pattern may end with a dot, like `*.`, so only the directory is checked for names that need it.
+    if longpath::wide_len(&query) >= longpath::MAX_PATH || longpath::needs_verbatim(dir) {
+        query = longpath::extended(&query);
+    }
+    let query: Vec<u16> = query.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut found = Vec::new();
+    unsafe {
+        let mut data: WIN32_FIND_DATAW = mem::zeroed();
+        let handle = FindFirstFileExW(query.as_ptr(), FindExInfoBasic, &mut data as *mut _ as *mut _,
+            FindExSearchNameMatch, ptr::null_mut(), FIND_FIRST_EX_LARGE_FETCH | if case_sensitive { FIND_FIRST_EX_CASE_SENSITIVE } else { 0 });
+        if handle == INVALID_HANDLE_VALUE {
+            let err = io::Error::last_os_error();
+            // no matches isn't an error
+            if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) {
+                return Ok(found);
+            }
+            return Err(err);
+        }
+        loop {
+            let len = data.cFileName.iter().position(|&c| c == 0).unwrap_or(data.cFileName.len());
+            let name = &data.cFileName[..len];
+            if name != [u16::from(b'.')] && name != [u16::from(b'.'), u16::from(b'.')] {
+                found.push(Entry {
+                    name: OsString::from_wide(name),
+                    is_dir: data.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY != 0,
+                    is_link: data.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY != 0 && data.dwFileAttributes & FILE_ATTRIBUTE_REPARSE_POINT != 0 &&
+                        (data.dwReserved0 == IO_REPARSE_TAG_SYMLINK || data.dwReserved0 == IO_REPARSE_TAG_MOUNT_POINT),
+                    hidden: data.dwFileAttributes & FILE_ATTRIBUTE_HIDDEN != 0,
+                    system: data.dwFileAttributes & FILE_ATTRIBUTE_SYSTEM != 0,
+                    metadata: Some(VfsMetadata {
+                        is_dir: data.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY != 0,
+                        len: u64::from(data.nFileSizeHigh) << 32 | u64::from(data.nFileSizeLow),
+                        created: system_time(data.ftCreationTime),
+                        modified: system_time(data.ftLastWriteTime),
+                        accessed: system_time(data.ftLastAccessTime),
+                        attributes: data.dwFileAttributes,
+                    }),
+                });
+            }
+            if FindNextFileW(handle, &mut data) == 0 {
+                let err = io::Error::last_os_error();
+                FindClose(handle);
+                // an error in the middle of the listing, e.g. of a network share, would leave it incomplete
+                if err.raw_os_error() != Some(ERROR_NO_MORE_FILES as i32) {
+                    return Err(err);
+                }
+                break;
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Converts 100ns intervals since 1601. Zero means the file system doesn't have the timestamp.
+fn system_time(time: FILETIME) -> Option<SystemTime> {
+    const UNIX_EPOCH_TICKS: u64 = 116_444_736_000_000_000;
+    let ticks = u64::from(time.dwHighDateTime) << 32 | u64::from(time.dwLowDateTime);
+    if ticks == 0 {
+        return None;
+    }
+    let since = |ticks: u64| Duration::new(ticks / 10_000_000, (ticks % 10_000_000) as u32 * 100);
+    Some(if ticks >= UNIX_EPOCH_TICKS {
+        UNIX_EPOCH + since(ticks - UNIX_EPOCH_TICKS)
+    } else {
+        UNIX_EPOCH - since(UNIX_EPOCH_TICKS - ticks)
+    })
+}
+
+/// Expands a pattern with Windows wildcard semantics, one path component at a time.
+///
+/// Only `*` and `?` are special; `[` and `]` are literal.
+pub(crate) fn find(pattern: &OsStr, config: &Config, cache: &DirCache) -> Vec<Found> {
+    walk(pattern, &NativeMatcher { case_sensitive: config.case_sensitive }, config, cache)
+}
+
+/// Windows wildcard semantics implemented by the OS
+struct NativeMatcher {
+    case_sensitive: bool,
+}
+
+impl Matcher for NativeMatcher {
+    type Pattern = OsString;
+
+    fn compile(&self, component: &OsStr) -> Part<OsString> {
+        if has_wildcards(component) {
+            Part::Pattern(component.to_owned())
+        } else {
+            Part::Literal
+        }
+    }
+
+    fn list(&self, dir: &Path, pattern: &OsString, cache: &DirCache) -> Vec<Entry> {
+        if cache.is_cancelled() {
+            return Vec::new();
+        }
+        let entries = find_in_dir(dir, pattern, self.case_sensitive).unwrap_or_else(|err| {
+            cache.report_error(dir, &err);
+            Vec::new()
+        });
+        cache.visited();
+        entries
+    }
+}
+
+#[test]
+fn finds_natively() {
+    assert_eq!(vec![PathBuf::from("Cargo.toml")], paths(find(OsStr::new("Cargo.tom?"), &Config::new(), &DirCache::default())));
+    assert_eq!(vec![PathBuf::from("cargo.TOML")], paths(find(OsStr::new("cargo.TOML"), &Config::new(), &DirCache::default())));
+    assert!(paths(find(OsStr::new("src\\*.rs"), &Config::new(), &DirCache::default())).contains(&PathBuf::from("src\\native.rs")));
+    assert!(paths(find(OsStr::new("s?c/../src/n*.rs"), &Config::new(), &DirCache::default())).contains(&PathBuf::from("src\\..\\src\\native.rs")));
+    assert!(paths(find(OsStr::new("[src]\\*.rs"), &Config::new(), &DirCache::default())).is_empty());
+}
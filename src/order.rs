@@ -0,0 +1,61 @@
+//! Ordering of matches within a directory
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use case::fold;
+use config::SortOrder;
+
+/// Sorts items by their file names
+pub(crate) fn sort_by_name<T, F>(items: &mut [T], order: SortOrder, name: F) where F: Fn(&T) -> &OsStr {
+    match order {
+        SortOrder::Unsorted => {},
+        SortOrder::Lexicographic => items.sort_by(|a, b| name(a).cmp(name(b))),
+        SortOrder::CaseInsensitive => items.sort_by(|a, b| {
+            fold(name(a)).cmp(&fold(name(b))).then_with(|| name(a).cmp(name(b)))
+        }),
+        SortOrder::Natural => items.sort_by(|a, b| {
+            natural(&fold(name(a)).to_string_lossy(), &fold(name(b)).to_string_lossy()).then_with(|| name(a).cmp(name(b)))
+        }),
+    }
+}
+
+/// Compares runs of digits by their numeric value, and everything else character by character
+fn natural(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (a_digits, b_digits) = (digits_len(a), digits_len(b));
+        if a_digits > 0 && b_digits > 0 {
+            let (a_num, b_num) = (a[..a_digits].trim_start_matches('0'), b[..b_digits].trim_start_matches('0'));
+            let ord = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            a = &a[a_digits..];
+            b = &b[b_digits..];
+            continue;
+        }
+        let mut a_chars = a.chars();
+        let mut b_chars = b.chars();
+        match (a_chars.next(), b_chars.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(x), Some(y)) if x == y => {},
+            (x, y) => return x.cmp(&y),
+        }
+        a = a_chars.as_str();
+        b = b_chars.as_str();
+    }
+}
+
+fn digits_len(s: &str) -> usize {
+    s.bytes().take_while(u8::is_ascii_digit).count()
+}
+
+#[test]
+fn natural_order() {
+    let mut names = vec!["file10.txt", "File2.txt", "file1.txt", "file02.txt", "b", "A"];
+    sort_by_name(&mut names, SortOrder::Natural, |s| OsStr::new(s));
+    assert_eq!(vec!["A", "b", "file1.txt", "File2.txt", "file02.txt", "file10.txt"], names);
+    sort_by_name(&mut names, SortOrder::Lexicographic, |s| OsStr::new(s));
+    assert_eq!(vec!["A", "File2.txt", "b", "file02.txt", "file1.txt", "file10.txt"], names);
+    sort_by_name(&mut names, SortOrder::CaseInsensitive, |s| OsStr::new(s));
+    assert_eq!(vec!["A", "b", "file02.txt", "file1.txt", "file10.txt", "File2.txt"], names);
+}
@@ -1,3 +1,9 @@
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::iter::FusedIterator;
+use std::vec;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 
 #[derive(Debug)]
 enum State {
@@ -18,7 +24,46 @@ enum State {
 ///
 /// This parses u16 code units, rather than code points.
 /// This allows supporting unpaired surrogates and ensures they won't "eat" any control characters.
-pub fn next_arg<AddC, ArgVec>(line: &[u16], mut arg: ArgVec, push: AddC) -> (Option<ArgVec>, &[u16])
+#[cfg(any(test, windows, target_os = "wasi", feature = "always-expand"))]
+pub fn next_arg<AddC, ArgVec>(line: &[u16], arg: ArgVec, push: AddC) -> (Option<ArgVec>, &[u16])
+where
+    AddC: Fn(&mut ArgVec, u16, bool),
+{
+    next_arg_with(line, arg, false, push)
+}
+
+/// Number of arguments in the rest of a command line, for `size_hint()`. Splitting without keeping them is cheap.
+pub(crate) fn count_args(mut line: &[u16], crt2008: bool) -> usize {
+    let mut count = 0;
+    while let (Some(()), rest) = next_arg_with(line, (), crt2008, |_, _, _| {}) {
+        count += 1;
+        line = rest;
+    }
+    count
+}
+
+/// Splits off the program name, which `CommandLineToArgvW` parses by its own rules: if it starts with a quote,
+/// it's everything up to the next quote, otherwise up to the first space or tab. Backslashes don't escape anything in it.
+pub(crate) fn split_program_name(line: &[u16]) -> (&[u16], &[u16]) {
+    let quote = u16::from(b'"');
+    if line.first() == Some(&quote) {
+        let line = &line[1..];
+        let end = line.iter().position(|&c| c == quote).unwrap_or(line.len());
+        (&line[..end], line.get(end + 1..).unwrap_or_default())
+    } else {
+        let end = line.iter().position(|&c| is_space(c)).unwrap_or(line.len());
+        line.split_at(end)
+    }
+}
+
+/// Spaces and tabs separate arguments
+fn is_space(c: u16) -> bool {
+    c == u16::from(b' ') || c == u16::from(b'\t')
+}
+
+/// Same as `next_arg`, but with `crt2008` follows the rules of the C runtime since Visual C++ 2008,
+/// which differ only in that `""` in quotes doesn't end the quoted part.
+pub fn next_arg_with<AddC, ArgVec>(line: &[u16], mut arg: ArgVec, crt2008: bool, push: AddC) -> (Option<ArgVec>, &[u16])
 where
     AddC: Fn(&mut ArgVec, u16, bool),
 {
@@ -27,7 +72,7 @@ where
     for (i, &cu) in line.iter().enumerate() {
         state = match state {
             BetweenArgs => match cu {
-                c if c == u16::from(b' ') => BetweenArgs,
+                c if is_space(c) => BetweenArgs,
                 c if c == u16::from(b'"') => InArg(true),
                 c if c == u16::from(b'\\') => Backslashes(1, false),
                 c => {
@@ -39,7 +84,7 @@ where
                 c if c == u16::from(b'\\') => Backslashes(1, quoted),
                 c if quoted && c == u16::from(b'"') => OnQuote,
                 c if !quoted && c == u16::from(b'"') => InArg(true),
-                c if !quoted && c == u16::from(b' ') => {
+                c if !quoted && is_space(c) => {
                     return (Some(arg), &line[i+1..]);
                 },
                 c => {
@@ -51,9 +96,9 @@ where
                 c if c == u16::from(b'"') => {
                     // In quoted arg "" means literal quote and the end of the quoted string (but not arg)
                     push(&mut arg, u16::from(b'"'), true);
-                    InArg(false)
+                    InArg(crt2008)
                 },
-                c if c == u16::from(b' ') => {
+                c if is_space(c) => {
                     return (Some(arg), &line[i+1..]);
                 },
                 c => {
@@ -85,6 +130,10 @@ where
                     for _ in 0..count {
                         push(&mut arg, u16::from(b'\\'), quoted);
                     }
+                    // `CommandLineToArgvW` splits `C:\dir\ *.txt` into two arguments, since the backslash doesn't escape the space
+                    if !quoted && is_space(c) {
+                        return (Some(arg), &line[i+1..]);
+                    }
                     push(&mut arg, c, quoted);
                     InArg(quoted)
                 },
@@ -105,3 +154,156 @@ where
     };
     (arg, &line[..0])
 }
+
+/// This is used on non-Windows only in tests and by `wild::Matcher`
+#[cfg(not(windows))]
+pub(crate) trait LossyOsStringExt {
+    fn from_wide(wide: &[u16]) -> OsString {
+        OsString::from(String::from_utf16_lossy(wide))
+    }
+}
+
+#[cfg(not(windows))]
+impl LossyOsStringExt for OsString {}
+
+/// This is used on non-Windows only in tests and by `wild::Matcher`
+#[cfg(not(windows))]
+pub(crate) trait LossyOsStrExt {
+    fn encode_wide(&self) -> ::std::vec::IntoIter<u16>;
+}
+
+#[cfg(not(windows))]
+impl LossyOsStrExt for ::std::ffi::OsStr {
+    fn encode_wide(&self) -> ::std::vec::IntoIter<u16> {
+        self.to_string_lossy().encode_utf16().collect::<Vec<_>>().into_iter()
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn from_wide(wide: &[u16]) -> OsString {
+    OsString::from_wide(wide)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn from_wide(wide: &[u16]) -> OsString {
+    OsString::from(String::from_utf16_lossy(wide))
+}
+
+#[cfg(windows)]
+pub(crate) fn to_wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().collect()
+}
+
+#[cfg(not(windows))]
+pub(crate) fn to_wide(s: &OsStr) -> Vec<u16> {
+    s.to_string_lossy().encode_utf16().collect()
+}
+
+/// Rules for splitting a command line into arguments. See `wild::parse_command_line_with()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    /// The command line as received by a program from `CreateProcess`, split like `CommandLineToArgvW` does. This is the default.
+    CommandLineToArgvW,
+    /// A command as typed in `cmd.exe` or a batch file, e.g. from `%CMDCMDLINE%`.
+    ///
+    /// `^` escapes, redirections like `> file` are removed, and everything after an unquoted `&` or `|` is ignored,
+    /// as `cmd.exe` does before starting the program. The rest is split like `CommandLineToArgvW` does.
+    Cmd,
+    /// Arguments of a command written for PowerShell: `'single quotes'`, `"double quotes"` and `` ` `` escapes.
+    ///
+    /// Variables aren't expanded. Everything after the `--%` stop-parsing token is split like `CommandLineToArgvW` does.
+    PowerShell,
+    /// Split like `argv` of C and C++ programs built with Visual C++ 2008 or later, including the Universal CRT.
+    ///
+    /// Unlike in `CommandLineToArgvW` and older C runtimes, `""` inside quotes adds a literal quote without ending the quoted part,
+    /// so `"a""b c"` is one argument `a"b c`.
+    Crt2008,
+}
+
+/// Iterator of arguments split from a command line, without glob expansion. Returned by `wild::parse_command_line()`.
+#[derive(Debug, Clone)]
+pub struct CommandLineArgs<'a> {
+    /// Arguments that have been split upfront, returned before parsing the `line`
+    split: vec::IntoIter<OsString>,
+    line: Cow<'a, [u16]>,
+    /// Start of the unparsed rest of the line
+    pos: usize,
+    crt2008: bool,
+}
+
+impl<'a> CommandLineArgs<'a> {
+    pub(crate) fn new(line: &'a [u16], syntax: Syntax) -> Self {
+        let (split, line) = match syntax {
+            Syntax::CommandLineToArgvW | Syntax::Crt2008 => (Vec::new(), Cow::Borrowed(line)),
+            Syntax::Cmd => (Vec::new(), Cow::Owned(::cmd::strip_cmd_syntax(line))),
+            Syntax::PowerShell => {
+                let (args, rest) = ::powershell::split_powershell(line);
+                (args.iter().map(|arg| from_wide(arg)).collect(), Cow::Borrowed(rest))
+            },
+        };
+        Self { split: split.into_iter(), line, pos: 0, crt2008: syntax == Syntax::Crt2008 }
+    }
+}
+
+impl<'a> Iterator for CommandLineArgs<'a> {
+    type Item = OsString;
+
+    fn next(&mut self) -> Option<OsString> {
+        if let Some(arg) = self.split.next() {
+            return Some(arg);
+        }
+        let (arg, rest) = next_arg_with(&self.line[self.pos..], Vec::new(), self.crt2008, |arg, c, _| arg.push(c));
+        self.pos = self.line.len() - rest.len();
+        arg.map(|arg| from_wide(&arg))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.split.len() + count_args(&self.line[self.pos..], self.crt2008);
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for CommandLineArgs<'a> {}
+
+impl<'a> FusedIterator for CommandLineArgs<'a> {}
+
+/// Iterator of arguments split from a command line like `CommandLineArgs`, but as UTF-16 borrowed from the line. Returned by `wild::parse_command_line_wide()`.
+///
+/// Only arguments with quotes have to be unquoted into a new buffer.
+#[derive(Debug, Clone)]
+pub struct CommandLineArgsWide<'a> {
+    line: &'a [u16],
+}
+
+impl<'a> CommandLineArgsWide<'a> {
+    pub(crate) fn new(line: &'a [u16]) -> Self {
+        Self { line }
+    }
+}
+
+impl<'a> Iterator for CommandLineArgsWide<'a> {
+    type Item = Cow<'a, [u16]>;
+
+    fn next(&mut self) -> Option<Cow<'a, [u16]>> {
+        let start = self.line.iter().position(|&c| !is_space(c))?;
+        let line = &self.line[start..];
+        // without quotes, backslashes have no special meaning, and the argument ends at a space or a tab
+        let end = line.iter().position(|&c| is_space(c) || c == u16::from(b'"')).unwrap_or(line.len());
+        if line.get(end) != Some(&u16::from(b'"')) {
+            self.line = &line[end..];
+            return Some(Cow::Borrowed(&line[..end]));
+        }
+        let (arg, rest) = next_arg_with(line, Vec::new(), false, |arg, c, _| arg.push(c));
+        self.line = rest;
+        arg.map(Cow::Owned)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = count_args(self.line, false);
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for CommandLineArgsWide<'a> {}
+
+impl<'a> FusedIterator for CommandLineArgsWide<'a> {}
@@ -0,0 +1,80 @@
+//! Telling patterns apart from plain arguments, and escaping literal text, without expanding anything
+use std::ffi::{OsStr, OsString};
+use parser::{next_arg_with, to_wide};
+
+/// Characters that make an argument a pattern
+#[cfg(not(feature = "globset"))]
+pub(crate) const METACHARACTERS: &[u8] = b"*?[";
+
+/// Characters that make an argument a pattern. `{a,b}` is an alternation in `globset`.
+#[cfg(feature = "globset")]
+pub(crate) const METACHARACTERS: &[u8] = b"*?[{";
+
+/// Whether an already split argument has the glob metacharacters `*`, `?` or `[`, or with the `globset` feature, `{`.
+///
+/// There's no quoting in split arguments, so all of them count, like in `wild::expand()`.
+///
+/// ```rust
+/// use std::ffi::OsStr;
+/// assert!(wild::contains_glob(OsStr::new("src/*.rs")));
+/// assert!(!wild::contains_glob(OsStr::new("README.md")));
+/// ```
+pub fn contains_glob(arg: &OsStr) -> bool {
+    arg.as_encoded_bytes().iter().any(|b| METACHARACTERS.contains(b))
+}
+
+/// Whether an argument, written as on a Windows command line with its quotes, has unquoted metacharacters
+/// that `wild::args()` would expand. If the text has several arguments, whether any of them does.
+///
+/// Use it e.g. to warn that a pattern matched nothing only when the user typed a pattern.
+///
+/// ```rust
+/// use std::ffi::OsStr;
+/// assert!(wild::is_pattern(OsStr::new("*.txt")));
+/// assert!(wild::is_pattern(OsStr::new(r#""my files"\*.txt"#)));
+/// assert!(!wild::is_pattern(OsStr::new(r#""*.txt""#)));
+/// assert!(!wild::is_pattern(OsStr::new("notes.txt")));
+/// ```
+pub fn is_pattern(command_line: &OsStr) -> bool {
+    let line = to_wide(command_line);
+    let mut rest = &line[..];
+    loop {
+        let (arg, next) = next_arg_with(rest, false, false, |has_metacharacters, c, quoted| {
+            *has_metacharacters |= !quoted && c < 128 && METACHARACTERS.contains(&(c as u8));
+        });
+        match arg {
+            Some(true) => return true,
+            Some(false) => rest = next,
+            None => return false,
+        }
+    }
+}
+
+/// Characters escaped in quotes. Braces are literal in the `glob` crate, and parentheses unless `Config::extglob` is enabled,
+/// but escaping them doesn't change that.
+pub(crate) const ESCAPED: &[u8] = b"?*[]{}()|";
+
+/// Escapes metacharacters like `[*]`, so that the text matches only itself in a pattern composed from it,
+/// e.g. a directory name given by the user, which may contain `[` or `?`.
+///
+/// The escaping is that of `Engine::Glob` patterns, like in `Config::expand()` and `wild::expand()`.
+/// The other engines have no escaping, so the result is only for the default engine.
+///
+/// ```rust
+/// use std::ffi::OsStr;
+/// let mut pattern = wild::escape_literal(OsStr::new(r"C:\Logs [old]"));
+/// pattern.push(r"\*.log");
+/// assert_eq!(pattern, r"C:\Logs [[]old[]]\*.log");
+/// ```
+pub fn escape_literal(text: &OsStr) -> OsString {
+    let mut escaped = Vec::with_capacity(text.len());
+    for &b in text.as_encoded_bytes() {
+        if ESCAPED.contains(&b) {
+            escaped.extend_from_slice(&[b'[', b, b']']);
+        } else {
+            escaped.push(b);
+        }
+    }
+    // safe, since only ASCII characters are added, and only around other ASCII characters
+    unsafe { OsString::from_encoded_bytes_unchecked(escaped) }
+}
@@ -0,0 +1,121 @@
+const BACKTICK: u16 = b'`' as u16;
+const SINGLE: u16 = b'\'' as u16;
+const DOUBLE: u16 = b'"' as u16;
+
+fn is_space(c: u16) -> bool {
+    c == u16::from(b' ') || c == u16::from(b'\t')
+}
+
+/// Character for `` `c `` in a double-quoted string
+fn escaped(c: u16) -> u16 {
+    if c > 127 {
+        return c;
+    }
+    u16::from(match c as u8 {
+        b'0' => 0,
+        b'a' => 7,
+        b'b' => 8,
+        b'e' => 27,
+        b'f' => 12,
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        b'v' => 11,
+        c => c,
+    })
+}
+
+/// Splits arguments of a command written for PowerShell.
+///
+/// Returns the arguments, and what follows the `--%` stop-parsing token, if any.
+/// That part is passed to the program unchanged, so it has to be split with the program's rules.
+///
+/// Variables and subexpressions aren't expanded, and the command ends at an unquoted `;`, `|` or `#` comment.
+pub(crate) fn split_powershell(line: &[u16]) -> (Vec<Vec<u16>>, &[u16]) {
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < line.len() && is_space(line[i]) {
+            i += 1;
+        }
+        let start = i;
+        match line.get(i) {
+            None => return (args, &line[..0]),
+            Some(&c) if c == u16::from(b'#') => return (args, &line[..0]),
+            _ => {},
+        }
+        let mut arg = Vec::new();
+        let mut had_quotes = false;
+        while let Some(&c) = line.get(i) {
+            i += 1;
+            match c {
+                c if is_space(c) => break,
+                c if c == u16::from(b';') || c == u16::from(b'|') => {
+                    if !arg.is_empty() || had_quotes {
+                        args.push(arg);
+                    }
+                    return (args, &line[..0]);
+                },
+                BACKTICK => {
+                    if let Some(&next) = line.get(i) {
+                        arg.push(next);
+                        i += 1;
+                    }
+                },
+                SINGLE => {
+                    had_quotes = true;
+                    while let Some(&c) = line.get(i) {
+                        i += 1;
+                        if c == SINGLE {
+                            // `''` is a literal quote
+                            if line.get(i) != Some(&SINGLE) {
+                                break;
+                            }
+                            i += 1;
+                        }
+                        arg.push(c);
+                    }
+                },
+                DOUBLE => {
+                    had_quotes = true;
+                    while let Some(&c) = line.get(i) {
+                        i += 1;
+                        if c == BACKTICK {
+                            if let Some(&next) = line.get(i) {
+                                arg.push(escaped(next));
+                                i += 1;
+                            }
+                            continue;
+                        }
+                        if c == DOUBLE {
+                            // `""` is a literal quote
+                            if line.get(i) != Some(&DOUBLE) {
+                                break;
+                            }
+                            i += 1;
+                        }
+                        arg.push(c);
+                    }
+                },
+                c => arg.push(c),
+            }
+        }
+        if !had_quotes && line[start..i].iter().cloned().filter(|&c| !is_space(c)).eq("--%".encode_utf16()) {
+            return (args, &line[i..]);
+        }
+        args.push(arg);
+    }
+}
+
+#[test]
+fn splits_powershell() {
+    let split = |s: &str| {
+        let line: Vec<u16> = s.encode_utf16().collect();
+        let (args, rest) = split_powershell(&line);
+        (args.iter().map(|a| String::from_utf16(a).unwrap()).collect::<Vec<_>>(), String::from_utf16(rest).unwrap())
+    };
+    assert_eq!((vec!["prog".to_owned(), "it's".to_owned(), "a\tb\"".to_owned(), "c d".to_owned(), "".to_owned()], String::new()),
+        split(r#"prog 'it''s' "a`tb`"" c` d '' ; echo"#));
+    assert_eq!((vec!["prog".to_owned(), "$x".to_owned()], r#"/c "a b" 'c'"#.to_owned()), split(r#"prog '$x' --% /c "a b" 'c'"#));
+    assert_eq!((vec!["prog".to_owned()], String::new()), split("prog # comment"));
+}
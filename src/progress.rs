@@ -0,0 +1,38 @@
+//! Reporting progress of slow expansions, and stopping them
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Progress of the expansion, passed to the callback of [`Config::progress`](struct.Config.html#method.progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Progress {
+    /// Directories listed so far
+    pub dirs_visited: usize,
+    /// Paths matched so far by all arguments
+    pub matches: usize,
+}
+
+/// Stops the expansion from another thread. See [`Config::cancel_token`](struct.Config.html#method.cancel_token).
+///
+/// Clones share the state, so cancelling any of them cancels all.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops listing directories. Matches found so far are still returned, and arguments that haven't been expanded yet are returned as-is.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
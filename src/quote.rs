@@ -0,0 +1,54 @@
+use std::ffi::{OsStr, OsString};
+use parser::{from_wide, to_wide};
+
+/// Quotes the argument if needed, so that `CommandLineToArgvW` turns it back into exactly the same string.
+pub(crate) fn quote_into(line: &mut Vec<u16>, arg: &[u16]) {
+    let needs_quotes = arg.is_empty() || arg.iter().any(|&c| c == u16::from(b' ') || c == u16::from(b'\t') || c == u16::from(b'"'));
+    if !needs_quotes {
+        line.extend_from_slice(arg);
+        return;
+    }
+    line.push(u16::from(b'"'));
+    let mut backslashes = 0;
+    for &c in arg {
+        if c == u16::from(b'\\') {
+            backslashes += 1;
+        } else {
+            if c == u16::from(b'"') {
+                // backslashes before a quote are escaped, and so is the quote
+                line.extend((0..=backslashes).map(|_| u16::from(b'\\')));
+            }
+            backslashes = 0;
+        }
+        line.push(c);
+    }
+    // backslashes before the closing quote would escape it
+    line.extend((0..backslashes).map(|_| u16::from(b'\\')));
+    line.push(u16::from(b'"'));
+}
+
+pub(crate) fn quote(arg: &OsStr) -> OsString {
+    let mut line = Vec::new();
+    quote_into(&mut line, &to_wide(arg));
+    from_wide(&line)
+}
+
+pub(crate) fn join<I, S>(args: I) -> OsString where I: IntoIterator<Item = S>, S: AsRef<OsStr> {
+    let mut line = Vec::new();
+    for (i, arg) in args.into_iter().enumerate() {
+        if i > 0 {
+            line.push(u16::from(b' '));
+        }
+        quote_into(&mut line, &to_wide(arg.as_ref()));
+    }
+    from_wide(&line)
+}
+
+#[test]
+fn round_trips() {
+    let args = ["prog.exe", "", "a b", "a\"b", "trailing\\", "c:\\with space\\", "\\\\\"", "\"", "*", "tab\there"];
+    let line = join(args);
+    assert_eq!(r#"prog.exe "" "a b" "a\"b" trailing\ "c:\with space\\" "\\\\\"" "\"" * "tab	here""#, line);
+    let line: Vec<u16> = to_wide(&line);
+    assert_eq!(args.iter().map(OsString::from).collect::<Vec<_>>(), ::parse_command_line(&line).collect::<Vec<_>>());
+}
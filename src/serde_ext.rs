@@ -0,0 +1,48 @@
+//! `Serialize` of reports and errors, with the `serde` feature.
+//!
+//! Paths and arguments are written as strings, with `U+FFFD` replacing anything that isn't valid Unicode,
+//! so that reports read well as JSON, and never fail to serialize because of an odd file name.
+use serde::Serializer;
+use std::ffi::OsStr;
+use std::fmt::Display;
+
+pub(crate) fn lossy<P: AsRef<OsStr>, S: Serializer>(s: &P, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&s.as_ref().to_string_lossy())
+}
+
+pub(crate) fn lossy_option<P: AsRef<OsStr>, S: Serializer>(s: &Option<P>, serializer: S) -> Result<S::Ok, S::Error> {
+    match *s {
+        Some(ref s) => serializer.serialize_some(&*s.as_ref().to_string_lossy()),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(crate) fn lossy_vec<P: AsRef<OsStr>, S: Serializer>(s: &[P], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(s.iter().map(|s| s.as_ref().to_string_lossy()))
+}
+
+/// Errors, like `io::Error`, are written as their messages
+pub(crate) fn display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[test]
+fn serializes_reports() {
+    use std::path::PathBuf;
+    let expansion = ::Expansion {
+        original: "*.rs".into(),
+        option: None,
+        matches: vec![PathBuf::from("a.rs")],
+        linked: vec![false],
+        metadata: vec![None],
+        was_quoted: false,
+    };
+    assert_eq!(r#"{"original":"*.rs","option":null,"matches":["a.rs"],"linked":[false],"metadata":[null],"was_quoted":false}"#,
+        ::serde_json::to_string(&expansion).unwrap());
+    let error = ::Error {
+        too_many_matches: vec![::TooManyMatches { pattern: "**".into(), limit: 1 }],
+        unreadable_dirs: vec![::UnreadableDir { pattern: "*/*".into(), dir: "locked".into(), error: ::std::io::Error::new(::std::io::ErrorKind::PermissionDenied, "denied") }],
+    };
+    assert_eq!(r#"{"too_many_matches":[{"pattern":"**","limit":1}],"unreadable_dirs":[{"pattern":"*/*","dir":"locked","error":"denied"}]}"#,
+        ::serde_json::to_string(&error).unwrap());
+}
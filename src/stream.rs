@@ -0,0 +1,40 @@
+//! Async adapter, enabled with the `stream` feature
+use std::ffi::OsString;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use blocking::Unblock;
+use futures_core::Stream;
+use iter::ArgsOs;
+
+/// `Stream` of glob-expanded arguments. Returned by `wild::args_stream()`.
+///
+/// Directories are listed on a blocking thread pool, so polling the stream never blocks the executor.
+/// It works with any async runtime.
+#[derive(Debug)]
+pub struct ArgsStream {
+    inner: Unblock<ArgsOs>,
+}
+
+impl ArgsStream {
+    pub(crate) fn new(args: ArgsOs) -> Self {
+        Self {
+            inner: Unblock::new(args),
+        }
+    }
+}
+
+impl Stream for ArgsStream {
+    type Item = OsString;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<OsString>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+#[test]
+fn streams_args() {
+    use futures_lite::StreamExt;
+    let streamed: Vec<_> = ::futures_lite::future::block_on(ArgsStream::new(::args_os()).collect());
+    assert_eq!(::args_os().collect::<Vec<_>>(), streamed);
+}
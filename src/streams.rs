@@ -0,0 +1,134 @@
+//! NTFS alternate data streams, like `file.txt:Zone.Identifier`
+use std::ffi::OsString;
+#[cfg(windows)]
+use std::ffi::OsStr;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+#[cfg(windows)]
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::{io, mem};
+#[cfg(not(windows))]
+use globiter::{LossyOsStrExt, LossyOsStringExt};
+use globiter::Arg;
+#[cfg(windows)]
+use dos::DosPattern;
+
+const COLON: u16 = b':' as u16;
+
+fn is_separator(c: u16) -> bool {
+    c == u16::from(b'\\') || c == u16::from(b'/')
+}
+
+/// Position of the `:` before the stream name, in the last path component
+fn stream_colon(path: &[u16]) -> Option<usize> {
+    let start = match path.iter().rposition(|&c| is_separator(c)) {
+        Some(separator) => separator + 1,
+        // `D:file.txt:stream`
+        None if path.get(1) == Some(&COLON) => 2,
+        None => 0,
+    };
+    let colon = start + path[start..].iter().position(|&c| c == COLON)?;
+    // `:stream` alone has no file name
+    if colon == start {
+        return None;
+    }
+    Some(colon)
+}
+
+/// Splits `file:stream` into the argument for the file and the pattern of the stream name
+pub(crate) fn split(arg: &Arg) -> Option<(Arg, OsString)> {
+    let text: Vec<u16> = arg.text().encode_wide().collect();
+    let colon = stream_colon(&text)?;
+    // `:` is never escaped, so the pattern has it in the same component
+    let pattern: Vec<u16> = arg.pattern.encode_wide().collect();
+    let pattern_colon = stream_colon(&pattern)?;
+    let file = Arg::new(OsString::from_wide(&pattern[..pattern_colon]), OsString::from_wide(&text[..colon]), arg.quoted, arg.quoted_wildcard);
+    Some((file, OsString::from_wide(&text[colon + 1..])))
+}
+
+/// Streams of the file matching the pattern, as `file:stream`. The unnamed stream is skipped.
+///
+/// With a `:` in the pattern, it's matched against the name with its type, e.g. `stream:$DATA`.
+#[cfg(windows)]
+pub(crate) fn find(file: &Path, pattern: &OsStr) -> io::Result<Vec<PathBuf>> {
+    let with_type = pattern.encode_wide().any(|c| c == COLON);
+    let pattern = DosPattern::new(pattern);
+    let mut query = file.to_owned();
+    if ::longpath::wide_len(&query) >= ::longpath::MAX_PATH || ::longpath::needs_verbatim(&query) {
+        query = ::longpath::extended(&query);
+    }
+    let query: Vec<u16> = query.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut found = Vec::new();
+    unsafe {
+        let mut data: ::sys::WIN32_FIND_STREAM_DATA = mem::zeroed();
+        let handle = ::sys::FindFirstStreamW(query.as_ptr(), ::sys::FindStreamInfoStandard, &mut data as *mut _ as *mut _, 0);
+        if handle == ::sys::INVALID_HANDLE_VALUE {
+            let err = io::Error::last_os_error();
+            // a file without streams, or a directory without named ones
+            if err.raw_os_error() == Some(::sys::ERROR_HANDLE_EOF as i32) {
+                return Ok(found);
+            }
+            return Err(err);
+        }
+        let handle = ::sys::FindHandle(handle);
+        loop {
+            let len = data.cStreamName.iter().position(|&c| c == 0).unwrap_or(data.cStreamName.len());
+            // `:name:$TYPE`
+            let full = &data.cStreamName[1.min(len)..len];
+            let name_len = full.iter().rposition(|&c| c == COLON).unwrap_or(full.len());
+            if name_len > 0 {
+                let name = OsString::from_wide(if with_type { full } else { &full[..name_len] });
+                if pattern.matches(&name, false) {
+                    let mut path = file.as_os_str().to_owned();
+                    path.push(":");
+                    path.push(&name);
+                    found.push(PathBuf::from(path));
+                }
+            }
+            if ::sys::FindNextStreamW(handle.0, &mut data as *mut _ as *mut _) == 0 {
+                let err = io::Error::last_os_error();
+                // anything else would leave the list incomplete
+                if err.raw_os_error() != Some(::sys::ERROR_HANDLE_EOF as i32) {
+                    return Err(err);
+                }
+                break;
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[test]
+fn splits_stream_names() {
+    let split = |arg: &str| split(&Arg::from(OsString::from(arg))).map(|(file, stream)| (file.into_text().into_string().unwrap(), stream.into_string().unwrap()));
+    assert_eq!(Some(("file.txt".to_owned(), "*".to_owned())), split("file.txt:*"));
+    assert_eq!(Some(("dir\\*.txt".to_owned(), "Zone.Identifier".to_owned())), split("dir\\*.txt:Zone.Identifier"));
+    assert_eq!(Some(("D:*.txt".to_owned(), "s*:$DATA".to_owned())), split("D:*.txt:s*:$DATA"));
+    assert_eq!(Some(("C:\\dir/f*".to_owned(), "*".to_owned())), split("C:\\dir/f*:*"));
+    assert_eq!(None, split("C:\\*.txt"));
+    assert_eq!(None, split("D:*.txt"));
+    assert_eq!(None, split("dir\\:*"));
+    assert_eq!(None, split("a:b\\*.txt"));
+}
+
+#[cfg(windows)]
+#[test]
+fn finds_streams() {
+    let temp = ::tempdir::TempDir::new("streams");
+    let dir = temp.path();
+    let file = dir.join("file.txt");
+    ::std::fs::write(&file, "").unwrap();
+    // fails on file systems without streams, like FAT
+    if ::std::fs::write(dir.join("file.txt:Zone.Identifier"), "").is_err() {
+        return;
+    }
+    ::std::fs::write(dir.join("file.txt:other"), "").unwrap();
+    let mut found = find(&file, OsStr::new("zone.*")).unwrap();
+    assert_eq!(vec![dir.join("file.txt:Zone.Identifier")], found);
+    found = find(&file, OsStr::new("*")).unwrap();
+    found.sort();
+    assert_eq!(vec![dir.join("file.txt:Zone.Identifier"), dir.join("file.txt:other")], found);
+    assert_eq!(vec![dir.join("file.txt:other:$DATA")], find(&file, OsStr::new("o*:$DATA")).unwrap());
+    assert!(find(dir, OsStr::new("*")).unwrap().is_empty());
+}
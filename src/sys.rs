@@ -0,0 +1,100 @@
+//! Win32 bindings
+//!
+//! With the `raw-dylib` feature, the functions are imported from the DLLs directly, without import libraries,
+//! which some toolchains, e.g. `*-gnullvm`, don't have for all of them.
+#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, clippy::upper_case_acronyms)]
+
+use std::os::raw::c_void;
+
+pub type HANDLE = *mut c_void;
+pub const INVALID_HANDLE_VALUE: HANDLE = !0 as HANDLE;
+
+pub const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+pub const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+pub const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+pub const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+pub const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA0000003;
+pub const IO_REPARSE_TAG_SYMLINK: u32 = 0xA000000C;
+
+pub const ERROR_FILE_NOT_FOUND: u32 = 2;
+pub const ERROR_NO_MORE_FILES: u32 = 18;
+pub const ERROR_HANDLE_EOF: u32 = 38;
+
+pub const FindExInfoBasic: i32 = 1;
+pub const FindExSearchNameMatch: i32 = 0;
+pub const FIND_FIRST_EX_CASE_SENSITIVE: u32 = 1;
+pub const FIND_FIRST_EX_LARGE_FETCH: u32 = 2;
+pub const FindStreamInfoStandard: i32 = 0;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FILETIME {
+    pub dwLowDateTime: u32,
+    pub dwHighDateTime: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct WIN32_FIND_DATAW {
+    pub dwFileAttributes: u32,
+    pub ftCreationTime: FILETIME,
+    pub ftLastAccessTime: FILETIME,
+    pub ftLastWriteTime: FILETIME,
+    pub nFileSizeHigh: u32,
+    pub nFileSizeLow: u32,
+    pub dwReserved0: u32,
+    pub dwReserved1: u32,
+    pub cFileName: [u16; 260],
+    pub cAlternateFileName: [u16; 14],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct WIN32_FIND_STREAM_DATA {
+    pub StreamSize: i64,
+    /// `:name:$TYPE`, e.g. `::$DATA` for the unnamed stream
+    pub cStreamName: [u16; 296],
+}
+
+/// A handle of `FindFirstStreamW` or `FindFirstFileExW`, closed when dropped, also if the listing panics
+pub struct FindHandle(pub HANDLE);
+
+impl Drop for FindHandle {
+    fn drop(&mut self) {
+        unsafe {
+            FindClose(self.0);
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "raw-dylib"), link(name = "kernel32"))]
+#[cfg_attr(all(feature = "raw-dylib", not(target_arch = "x86")), link(name = "kernel32", kind = "raw-dylib"))]
+#[cfg_attr(all(feature = "raw-dylib", target_arch = "x86"), link(name = "kernel32", kind = "raw-dylib", import_name_type = "undecorated"))]
+extern "system" {
+    pub fn GetCommandLineW() -> *const u16;
+    pub fn FindFirstFileExW(lpFileName: *const u16, fInfoLevelId: i32, lpFindFileData: *mut c_void, fSearchOp: i32, lpSearchFilter: *mut c_void, dwAdditionalFlags: u32) -> HANDLE;
+    pub fn FindNextFileW(hFindFile: HANDLE, lpFindFileData: *mut WIN32_FIND_DATAW) -> i32;
+    pub fn FindClose(hFindFile: HANDLE) -> i32;
+    pub fn FindFirstStreamW(lpFileName: *const u16, InfoLevel: i32, lpFindStreamData: *mut c_void, dwFlags: u32) -> HANDLE;
+    pub fn FindNextStreamW(hFindStream: HANDLE, lpFindStreamData: *mut c_void) -> i32;
+    pub fn GetFullPathNameW(lpFileName: *const u16, nBufferLength: u32, lpBuffer: *mut u16, lpFilePart: *mut *mut u16) -> u32;
+    pub fn GetLongPathNameW(lpszShortPath: *const u16, lpszLongPath: *mut u16, cchBuffer: u32) -> u32;
+    pub fn GetShortPathNameW(lpszLongPath: *const u16, lpszShortPath: *mut u16, cchBuffer: u32) -> u32;
+    #[cfg(feature = "verify-parser")]
+    pub fn LocalFree(hMem: *mut c_void) -> *mut c_void;
+}
+
+#[cfg(feature = "verify-parser")]
+#[cfg_attr(not(feature = "raw-dylib"), link(name = "shell32"))]
+#[cfg_attr(all(feature = "raw-dylib", not(target_arch = "x86")), link(name = "shell32", kind = "raw-dylib"))]
+#[cfg_attr(all(feature = "raw-dylib", target_arch = "x86"), link(name = "shell32", kind = "raw-dylib", import_name_type = "undecorated"))]
+extern "system" {
+    pub fn CommandLineToArgvW(lpCmdLine: *const u16, pNumArgs: *mut i32) -> *mut *mut u16;
+}
+
+#[cfg_attr(not(feature = "raw-dylib"), link(name = "ntdll"))]
+#[cfg_attr(all(feature = "raw-dylib", not(target_arch = "x86")), link(name = "ntdll", kind = "raw-dylib"))]
+#[cfg_attr(all(feature = "raw-dylib", target_arch = "x86"), link(name = "ntdll", kind = "raw-dylib", import_name_type = "undecorated"))]
+extern "system" {
+    pub fn RtlUpcaseUnicodeChar(SourceCharacter: u16) -> u16;
+}
@@ -0,0 +1,30 @@
+//! Directories for tests that need the real file system, like links, long paths or `.gitignore` files
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A new empty directory in the system's temporary directory, unique to the test run, and removed with its contents when dropped
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        let unique = format!("wild_{}_{}_{}", name, ::std::process::id(), CREATED.fetch_add(1, Ordering::Relaxed));
+        let dir = ::std::env::temp_dir().join(unique);
+        ::std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // the tests create names that only verbatim paths can reach, like `notes. `
+        #[cfg(windows)]
+        let _ = ::std::fs::remove_dir_all(::longpath::extended(&self.0));
+        #[cfg(not(windows))]
+        let _ = ::std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,400 @@
+//! File system access of the expansion, replaceable with `Config::vfs()`
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// An entry of a directory listing. Returned by `Vfs::read_dir()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfsEntry {
+    /// File name, without the directory
+    pub name: OsString,
+    /// Whether it's a directory, following symlinks
+    pub is_dir: bool,
+    /// Whether it's a directory, not following symlinks. Only symlinks and junctions to directories differ from `is_dir`.
+    pub is_real_dir: bool,
+    /// Has the hidden attribute, or on Unix, starts with a dot
+    pub hidden: bool,
+    /// Has the system attribute
+    pub system: bool,
+    /// Metadata, if the listing provides it without extra file system calls, like directory listings on Windows do.
+    /// It's passed on to `wild::args_entries()`.
+    pub metadata: Option<VfsMetadata>,
+}
+
+/// Metadata of a path. Returned by `Vfs::metadata()`, and with matches of `wild::args_entries()`.
+///
+/// File systems that don't have some of the fields leave them at their defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct VfsMetadata {
+    /// Whether it's a directory, following symlinks
+    pub is_dir: bool,
+    /// Size in bytes
+    pub len: u64,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    /// `FILE_ATTRIBUTE_*` flags on Windows. 0 elsewhere.
+    pub attributes: u32,
+}
+
+const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+
+impl VfsMetadata {
+    /// Has `FILE_ATTRIBUTE_OFFLINE`: the data isn't available locally, e.g. it has been moved to remote storage.
+    pub fn is_offline(&self) -> bool {
+        self.attributes & FILE_ATTRIBUTE_OFFLINE != 0
+    }
+
+    /// Has `FILE_ATTRIBUTE_RECALL_ON_OPEN`: opening it makes the file system fetch it, e.g. a OneDrive directory that hasn't been synced.
+    pub fn recalls_on_open(&self) -> bool {
+        self.attributes & FILE_ATTRIBUTE_RECALL_ON_OPEN != 0
+    }
+
+    /// Has `FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS`: reading it downloads it, like an online-only OneDrive file.
+    pub fn recalls_on_data_access(&self) -> bool {
+        self.attributes & FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS != 0
+    }
+
+    /// Any of the above, so the contents aren't stored locally, and using them can take long or need a network connection.
+    ///
+    /// Expansion itself only reads directory listings, so it never fetches such files.
+    ///
+    /// ```rust
+    /// for entry in wild::args_entries() {
+    ///     if entry.metadata.is_some_and(|metadata| metadata.is_placeholder()) {
+    ///         eprintln!("{} is online-only", entry.path.display());
+    ///     }
+    /// }
+    /// ```
+    pub fn is_placeholder(&self) -> bool {
+        self.is_offline() || self.recalls_on_open() || self.recalls_on_data_access()
+    }
+}
+
+impl From<&fs::Metadata> for VfsMetadata {
+    fn from(metadata: &fs::Metadata) -> Self {
+        Self {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            attributes: attributes(metadata),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn attributes(metadata: &fs::Metadata) -> u32 {
+    use std::os::windows::fs::MetadataExt;
+    metadata.file_attributes()
+}
+
+#[cfg(not(windows))]
+fn attributes(_: &fs::Metadata) -> u32 {
+    0
+}
+
+/// File system access needed for glob expansion. See [`Config::vfs`](struct.Config.html#method.vfs).
+///
+/// Paths are passed as they're going to be returned, so they can be relative to the current directory,
+/// and use the separators typed in the pattern.
+pub trait Vfs: fmt::Debug + Send + Sync {
+    /// Entries of `dir`, in any order. An empty path means the current directory.
+    ///
+    /// An error is treated like an empty directory, since an unreadable directory has no matches.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<VfsEntry>>;
+
+    /// Metadata of the path, following symlinks
+    fn metadata(&self, path: &Path) -> io::Result<VfsMetadata>;
+
+    /// Whether anything exists at the path, without following symlinks
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Absolute path with all links resolved, used to detect cycles with `Config::follow_links()`.
+    /// An empty path means the current directory.
+    ///
+    /// By default it's the path unchanged, which is enough for file systems without links.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_owned())
+    }
+}
+
+/// The real file system. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFs;
+
+/// The path as Windows APIs need it. Names with trailing dots or spaces would be stripped, and refer to another file.
+#[cfg(windows)]
+pub(crate) fn win32_path(path: &Path) -> Cow<'_, Path> {
+    if ::longpath::needs_verbatim(path) {
+        Cow::Owned(::longpath::extended(path))
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn win32_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+impl Vfs for OsFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<VfsEntry>> {
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        // an entry that can't be read makes the listing incomplete, so it fails as a whole
+        win32_path(dir).read_dir()?
+            .map(|entry| entry.map(|entry| {
+                let (hidden, system) = hidden_system(&entry);
+                let file_type = entry.file_type().ok();
+                let is_real_dir = file_type.is_some_and(|t| t.is_dir());
+                // only links have to be opened to find out what they point to
+                let is_dir = if file_type.is_some_and(|t| t.is_symlink()) { win32_path(&entry.path()).is_dir() } else { is_real_dir };
+                VfsEntry {
+                    is_real_dir,
+                    name: entry.file_name(),
+                    hidden,
+                    system,
+                    metadata: listed_metadata(&entry, is_dir),
+                    is_dir,
+                }
+            }))
+            .collect()
+    }
+
+    #[cfg(windows)]
+    fn metadata(&self, path: &Path) -> io::Result<VfsMetadata> {
+        let path = win32_path(path);
+        match find_path(&path) {
+            Some(Ok(metadata)) if !is_followed_link(&metadata) => Ok(metadata),
+            Some(Err(ref err)) if err.kind() == io::ErrorKind::NotFound => Err(io::ErrorKind::NotFound.into()),
+            _ => path.metadata().map(|m| VfsMetadata::from(&m)),
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn metadata(&self, path: &Path) -> io::Result<VfsMetadata> {
+        path.metadata().map(|m| VfsMetadata::from(&m))
+    }
+
+    #[cfg(windows)]
+    fn exists(&self, path: &Path) -> bool {
+        let path = win32_path(path);
+        match find_path(&path) {
+            Some(Ok(_)) => true,
+            Some(Err(ref err)) if err.kind() == io::ErrorKind::NotFound => false,
+            // e.g. the directory can't be listed, but the path can be opened
+            _ => path.symlink_metadata().is_ok(),
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn exists(&self, path: &Path) -> bool {
+        path.symlink_metadata().is_ok()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        if path.as_os_str().is_empty() {
+            Path::new(".").canonicalize()
+        } else {
+            win32_path(path).canonicalize()
+        }
+    }
+}
+
+/// Metadata of the path from a listing of its directory, which reads only the attributes. Opening the path instead
+/// could make a cloud file placeholder, like an online-only OneDrive file, download it.
+///
+/// `None` if the path has no name to list, like a root directory, or if the name has characters that the listing
+/// would take as wildcards. Such names can't exist, but e.g. `what?.txt` would match `what1.txt`.
+#[cfg(windows)]
+fn find_path(path: &Path) -> Option<io::Result<VfsMetadata>> {
+    let (dir, name) = (path.parent()?, path.file_name()?);
+    if name.as_encoded_bytes().iter().any(|b| b"*?<>\"".contains(b)) {
+        return None;
+    }
+    Some(match ::native::find_in_dir(dir, name, false) {
+        Ok(entries) => entries.into_iter().next().and_then(|entry| entry.metadata).ok_or_else(|| io::ErrorKind::NotFound.into()),
+        Err(err) => Err(err),
+    })
+}
+
+/// Links have to be opened to get metadata of their targets. Cloud files are reparse points too, but opening them is safe once they're local.
+#[cfg(windows)]
+fn is_followed_link(metadata: &VfsMetadata) -> bool {
+    metadata.attributes & ::sys::FILE_ATTRIBUTE_REPARSE_POINT != 0 && !metadata.is_placeholder()
+}
+
+/// Metadata of directory entries comes from the directory listing on Windows, so it's cheap
+#[cfg(windows)]
+fn listed_metadata(entry: &fs::DirEntry, is_dir: bool) -> Option<VfsMetadata> {
+    // it describes the link itself, not its target
+    entry.metadata().ok().map(|m| VfsMetadata { is_dir, ..VfsMetadata::from(&m) })
+}
+
+/// Elsewhere it'd take a `stat` per entry, even if it's never used
+#[cfg(not(windows))]
+fn listed_metadata(_: &fs::DirEntry, _: bool) -> Option<VfsMetadata> {
+    None
+}
+
+#[cfg(windows)]
+fn hidden_system(entry: &::std::fs::DirEntry) -> (bool, bool) {
+    use std::os::windows::fs::MetadataExt;
+    // metadata of directory entries comes from the directory listing, so it's cheap
+    let attributes = entry.metadata().map(|m| m.file_attributes()).unwrap_or(0);
+    (attributes & ::sys::FILE_ATTRIBUTE_HIDDEN != 0, attributes & ::sys::FILE_ATTRIBUTE_SYSTEM != 0)
+}
+
+#[cfg(not(windows))]
+fn hidden_system(entry: &::std::fs::DirEntry) -> (bool, bool) {
+    (entry.file_name().to_string_lossy().starts_with('.'), false)
+}
+
+/// In-memory file system, for testing how patterns expand without touching the disk.
+///
+/// Directories are listed in the order of names. Names starting with a dot aren't hidden unless marked so, like on Windows.
+///
+/// ```rust
+/// let fs = wild::MemoryFs::new(&["src/main.rs", "src/lib.rs", "target/debug/app.exe"])
+///     .dir("empty")
+///     .hidden("target");
+/// let config = wild::Config::new().vfs(fs);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    /// All paths, and whether they're directories
+    paths: BTreeMap<PathBuf, bool>,
+    hidden: BTreeSet<PathBuf>,
+    system: BTreeSet<PathBuf>,
+    unreadable: BTreeSet<PathBuf>,
+    unreadable_entries: BTreeSet<PathBuf>,
+}
+
+impl MemoryFs {
+    /// File system with the given files, and all their parent directories
+    pub fn new(files: &[&str]) -> Self {
+        files.iter().fold(Self::default(), |fs, file| fs.file(file))
+    }
+
+    /// Adds a file, and all its parent directories
+    pub fn file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.add_parents(path.as_ref());
+        self.paths.entry(path.as_ref().to_owned()).or_insert(false);
+        self
+    }
+
+    /// Adds a directory, and all its parent directories
+    pub fn dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.add_parents(path.as_ref());
+        self.paths.insert(path.as_ref().to_owned(), true);
+        self
+    }
+
+    /// Gives the path the hidden attribute
+    pub fn hidden<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.hidden.insert(path.as_ref().to_owned());
+        self
+    }
+
+    /// Gives the path the system attribute
+    pub fn system<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.system.insert(path.as_ref().to_owned());
+        self
+    }
+
+    /// Makes listing of the directory fail, like without a permission to read it
+    pub fn unreadable<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.unreadable.insert(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Makes listing of the path's directory fail when it reaches the path, after the entries before it
+    pub fn unreadable_entry<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.unreadable_entries.insert(path.as_ref().to_owned());
+        self
+    }
+
+    fn add_parents(&mut self, path: &Path) {
+        for dir in path.ancestors().skip(1).filter(|dir| !dir.as_os_str().is_empty()) {
+            self.paths.insert(dir.to_owned(), true);
+        }
+    }
+}
+
+impl Vfs for MemoryFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<VfsEntry>> {
+        if self.unreadable.contains(dir) {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        if !dir.as_os_str().is_empty() && self.paths.get(dir) != Some(&true) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        self.paths.iter()
+            .filter(|&(path, _)| path.parent() == Some(dir))
+            .filter_map(|(path, &is_dir)| path.file_name().map(|name| if self.unreadable_entries.contains(path) {
+                Err(io::ErrorKind::InvalidData.into())
+            } else {
+                Ok(VfsEntry {
+                    name: name.to_owned(),
+                    is_dir,
+                    is_real_dir: is_dir,
+                    hidden: self.hidden.contains(path),
+                    system: self.system.contains(path),
+                    metadata: Some(VfsMetadata { is_dir, ..VfsMetadata::default() }),
+                })
+            }))
+            .collect()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<VfsMetadata> {
+        match self.paths.get(path) {
+            Some(&is_dir) => Ok(VfsMetadata { is_dir, ..VfsMetadata::default() }),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.paths.contains_key(path)
+    }
+}
+
+#[test]
+fn detects_placeholders() {
+    let metadata = |attributes| VfsMetadata { attributes, ..VfsMetadata::default() };
+    assert!(!metadata(0x20).is_placeholder());
+    assert!(metadata(0x400 | 0x400000).recalls_on_data_access() && metadata(0x400000).is_placeholder());
+    assert!(metadata(0x40010).recalls_on_open() && !metadata(0x40010).is_offline());
+    assert!(metadata(0x1000).is_offline() && metadata(0x1000).is_placeholder());
+}
+
+#[cfg(windows)]
+#[test]
+fn reads_metadata_like_std() {
+    let temp = ::tempdir::TempDir::new("os_fs");
+    let dir = temp.path();
+    ::std::fs::create_dir(dir.join("sub")).unwrap();
+    ::std::fs::write(dir.join("file.txt"), "text").unwrap();
+    ::std::fs::write(dir.join("what1.txt"), "").unwrap();
+    let mut paths = vec![dir.join("file.txt"), dir.join("FILE.TXT"), dir.join("sub"), dir.join("missing"), dir.join("missing\\file.txt"), dir.join("what?.txt")];
+    // creating symlinks needs a privilege or developer mode
+    if ::std::os::windows::fs::symlink_dir(dir.join("sub"), dir.join("link")).is_ok() {
+        paths.push(dir.join("link"));
+    }
+    for path in &paths {
+        assert_eq!(path.symlink_metadata().is_ok(), OsFs.exists(path), "{}", path.display());
+        let expected = path.metadata().map(|m| VfsMetadata::from(&m)).ok();
+        let metadata = OsFs.metadata(path).ok();
+        assert_eq!(expected.map(|m| (m.is_dir, m.len)), metadata.map(|m| (m.is_dir, m.len)), "{}", path.display());
+    }
+}
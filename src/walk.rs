@@ -0,0 +1,546 @@
+//! Expansion of patterns one path component at a time
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::mem;
+use std::path::{is_separator, Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use config::{AttributeFilter, Callback, Config, EntryTypes, ErrorFn, ProgressFn};
+use exclude::Exclusions;
+use order::sort_by_name;
+use progress::{CancelToken, Progress};
+use vfs::{OsFs, Vfs, VfsEntry, VfsMetadata};
+
+/// A directory entry matching a pattern component
+pub(crate) struct Entry {
+    pub name: OsString,
+    pub is_dir: bool,
+    /// A symlink or junction to a directory
+    pub is_link: bool,
+    pub hidden: bool,
+    pub system: bool,
+    pub metadata: Option<VfsMetadata>,
+}
+
+/// A path matching the whole pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Found {
+    pub path: PathBuf,
+    /// A wildcard matched a symlink or junction on the way
+    pub linked: bool,
+    /// Metadata learned while looking for it, if any
+    pub metadata: Option<VfsMetadata>,
+}
+
+impl Found {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, linked: false, metadata: None }
+    }
+}
+
+/// A path component of a pattern
+pub(crate) enum Part<P> {
+    /// No wildcards, used as-is
+    Literal,
+    /// Matched against directory entries
+    Pattern(P),
+    /// `**`, any number of directories
+    Recursive,
+}
+
+/// Matching rules of an engine, applied to a single path component at a time
+pub(crate) trait Matcher: Sync {
+    type Pattern: Sync;
+
+    fn compile(&self, component: &OsStr) -> Part<Self::Pattern>;
+
+    /// Entries of `dir` matching the pattern
+    fn list(&self, dir: &Path, pattern: &Self::Pattern, cache: &DirCache) -> Vec<Entry>;
+}
+
+/// Whether the component has Windows wildcards `*` or `?`
+pub(crate) fn has_wildcards(component: &OsStr) -> bool {
+    // ASCII bytes of the encoding are always ASCII characters, even next to unpaired surrogates
+    component.as_encoded_bytes().iter().any(|&b| b == b'*' || b == b'?')
+}
+
+/// Directory listings shared by all patterns of one iterator, so that every directory is read only once.
+///
+/// Clones of the iterator share the cache.
+#[derive(Debug, Clone)]
+pub(crate) struct DirCache {
+    fs: Arc<dyn Vfs>,
+    dirs: Arc<Mutex<HashMap<PathBuf, Arc<Vec<VfsEntry>>>>>,
+    on_error: Option<Callback<ErrorFn>>,
+    progress: Option<Callback<ProgressFn>>,
+    /// The pattern whose directories are listed, for `Config::on_error`
+    pattern: Option<Arc<OsStr>>,
+    cancel_token: Option<CancelToken>,
+    counts: Arc<Counts>,
+    /// Listing errors kept for `Config::args_vec`, if enabled
+    errors: Option<Arc<Mutex<Vec<DirError>>>>,
+    #[cfg(feature = "ignore")]
+    ignore_rules: ::gitignore::IgnoreRules,
+}
+
+/// A directory that couldn't be listed
+pub(crate) type DirError = (PathBuf, io::Error);
+
+/// Totals reported by `Config::progress`, shared by clones of the cache
+#[derive(Debug, Default)]
+struct Counts {
+    dirs: AtomicUsize,
+    matches: AtomicUsize,
+}
+
+impl Default for DirCache {
+    fn default() -> Self {
+        Self::new(&Config::new())
+    }
+}
+
+impl DirCache {
+    /// Cache reading the file system of the config
+    pub fn new(config: &Config) -> Self {
+        Self {
+            fs: config.vfs.clone().unwrap_or_else(|| Arc::new(OsFs)),
+            dirs: Arc::default(),
+            on_error: config.on_error.clone(),
+            progress: config.progress.clone(),
+            pattern: None,
+            cancel_token: config.cancel_token.clone(),
+            counts: Arc::default(),
+            errors: None,
+            #[cfg(feature = "ignore")]
+            ignore_rules: ::gitignore::IgnoreRules::default(),
+        }
+    }
+
+    /// Whether anything exists at the path, without following symlinks
+    pub fn exists(&self, path: &Path) -> bool {
+        self.fs.exists(path)
+    }
+
+    /// Whether the path is a directory, following symlinks
+    pub fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path).is_some_and(|m| m.is_dir)
+    }
+
+    /// Metadata of the path, following symlinks, or `None` if it doesn't exist
+    pub fn metadata(&self, path: &Path) -> Option<VfsMetadata> {
+        self.fs.metadata(path).ok()
+    }
+
+    /// Passes an error of listing `dir` to `Config::on_error`, unless the directory simply doesn't exist
+    pub fn report_error(&self, dir: &Path, err: &io::Error) {
+        if self.on_error.is_none() && self.errors.is_none() {
+            return;
+        }
+        if err.kind() == io::ErrorKind::NotFound || !(dir.as_os_str().is_empty() || self.is_dir(dir)) {
+            return;
+        }
+        self.report(dir, err);
+    }
+
+    /// Passes an error of reading `path` to `Config::on_error`, e.g. of listing the streams of a file
+    pub fn report(&self, path: &Path, err: &io::Error) {
+        if let Some(ref on_error) = self.on_error {
+            (on_error.0)(self.pattern.as_deref().unwrap_or_default(), path, err);
+        }
+        if let Some(ref errors) = self.errors {
+            errors.lock().unwrap_or_else(PoisonError::into_inner).push((path.to_owned(), io::Error::new(err.kind(), err.to_string())));
+        }
+    }
+
+    /// The same cache, passing `pattern` to `Config::on_error` with errors of the directories it lists
+    pub fn for_pattern(&self, pattern: &OsStr) -> Self {
+        Self { pattern: Some(Arc::from(pattern)), ..self.clone() }
+    }
+
+    /// Starts keeping listing errors, to be taken with `take_errors()`
+    pub fn keep_errors(&mut self) {
+        self.errors = Some(Arc::default());
+    }
+
+    /// Listing errors since the last call
+    pub fn take_errors(&self) -> Vec<DirError> {
+        match self.errors {
+            Some(ref errors) => mem::take(&mut *errors.lock().unwrap_or_else(PoisonError::into_inner)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether the `Config::cancel_token` has been cancelled, so no more directories are listed
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Counts a listed directory for `Config::progress`
+    pub fn visited(&self) {
+        self.counts.dirs.fetch_add(1, Ordering::Relaxed);
+        self.report_progress();
+    }
+
+    /// Counts matches of an argument for `Config::progress`
+    pub fn matched(&self, matches: usize) {
+        self.counts.matches.fetch_add(matches, Ordering::Relaxed);
+        self.report_progress();
+    }
+
+    fn report_progress(&self) {
+        if let Some(ref progress) = self.progress {
+            (progress.0)(Progress {
+                dirs_visited: self.counts.dirs.load(Ordering::Relaxed),
+                matches: self.counts.matches.load(Ordering::Relaxed),
+            });
+        }
+    }
+
+    fn entries(&self, dir: &Path) -> Arc<Vec<VfsEntry>> {
+        if let Some(entries) = self.dirs.lock().unwrap_or_else(PoisonError::into_inner).get(dir) {
+            return entries.clone();
+        }
+        if self.is_cancelled() {
+            return Arc::default();
+        }
+        let entries = Arc::new(self.fs.read_dir(dir).unwrap_or_else(|err| {
+            #[cfg(feature = "tracing")]
+            ::tracing::debug!(dir = %dir.display(), error = %err, "can't list directory");
+            self.report_error(dir, &err);
+            Vec::new()
+        }));
+        #[cfg(feature = "tracing")]
+        ::tracing::trace!(dir = %dir.display(), entries = entries.len(), "listed directory");
+        self.dirs.lock().unwrap_or_else(PoisonError::into_inner).insert(dir.to_owned(), entries.clone());
+        self.visited();
+        entries
+    }
+
+    /// Entries of `dir` with names accepted by `matches`
+    pub fn list_matching<F>(&self, dir: &Path, matches: F) -> Vec<Entry> where F: Fn(&OsStr) -> bool {
+        self.entries(dir).iter()
+            .filter(|entry| matches(&entry.name))
+            .map(|entry| Entry {
+                name: entry.name.clone(),
+                is_dir: entry.is_dir,
+                is_link: entry.is_dir && !entry.is_real_dir,
+                hidden: entry.hidden,
+                system: entry.system,
+                metadata: entry.metadata,
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.dirs.lock().unwrap().len()
+    }
+
+    /// With `Config::gitignore`, whether `dir/name` is excluded by ignore files
+    #[cfg(feature = "ignore")]
+    pub fn is_ignored(&self, dir: &Path, name: &OsStr, is_dir: bool, config: &Config) -> bool {
+        config.gitignore && self.ignore_rules.is_ignored(&dir.join(name), is_dir)
+    }
+
+    #[cfg(not(feature = "ignore"))]
+    pub fn is_ignored(&self, _dir: &Path, _name: &OsStr, _is_dir: bool, _config: &Config) -> bool {
+        false
+    }
+
+    /// Names of subdirectories of `dir` that can be traversed by `**`, whether they're links, and their metadata
+    fn subdir_names(&self, dir: &Path, config: &Config) -> Vec<(OsString, bool, Option<VfsMetadata>)> {
+        self.entries(dir).iter()
+            .filter(|entry| (entry.is_real_dir || (config.follow_links && entry.is_dir)) &&
+                visible(config.hidden_files, entry.hidden, false) && visible(config.system_files, entry.system, false) &&
+                !self.is_ignored(dir, &entry.name, true, config))
+            .map(|entry| (entry.name.clone(), !entry.is_real_dir, entry.metadata))
+            .collect()
+    }
+}
+
+/// Expands a pattern by listing only directories that its components can match.
+///
+/// Paths are followed to the end of the pattern one by one, so that with `Config::max_results` listing stops
+/// as soon as there is one match too many, even in the middle of a `**` search, and the result has more than `limit` matches.
+pub(crate) fn walk<M: Matcher>(pattern: &OsStr, matcher: &M, config: &Config, cache: &DirCache) -> Vec<Found> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+    // like in Unix shells, `dir*/` matches only directories, and the separator is kept in the results
+    let trailing_separator = pattern.as_encoded_bytes().last().map(|&b| char::from(b)).filter(|&c| is_separator(c));
+    let mut rest = Path::new(pattern);
+    // relative patterns are matched in `Config::base_dir`, and the matches are joined with it
+    let base = base_dir(rest, config);
+    let start = base.map(Path::to_owned).unwrap_or_default();
+    let mut first = Found::new(start.clone());
+    let literal = literal_prefix(pattern, matcher);
+    // directories before the first wildcard are checked at once, so that a mistyped path isn't searched at all
+    let mut verified = 0;
+    let mut prefix_metadata = None;
+    if let Some((prefix, remainder)) = literal {
+        let prefix_path = Path::new(prefix);
+        if !prefix_path.components().all(|c| matches!(c, Component::Prefix(_) | Component::RootDir)) {
+            prefix_metadata = cache.metadata(&start.join(prefix)).filter(|m| m.is_dir);
+            if prefix_metadata.is_none() {
+                #[cfg(feature = "tracing")]
+                ::tracing::debug!(dir = %prefix_path.display(), "directory doesn't exist");
+                return Vec::new();
+            }
+            verified = prefix_path.components().count();
+        }
+        if config.preserve_prefix {
+            // pushing onto it keeps the prefix spelled as typed
+            first = Found::new(start.join(prefix));
+            rest = Path::new(remainder);
+            verified = 0;
+        }
+    }
+    let components: Vec<_> = rest.components().enumerate().map(|(index, component)| {
+        let part = match component {
+            Component::Normal(name) => matcher.compile(name),
+            _ => Part::Literal,
+        };
+        (index, component, part)
+    }).collect();
+    let walker = Walker {
+        matcher,
+        config,
+        cache,
+        base,
+        verified,
+        prefix_metadata,
+        trailing_separator,
+        exclusions: Exclusions::new(config),
+        found: AtomicUsize::new(0),
+    };
+    let mut paths = walker.below(first, &components);
+    if let Some(separator) = trailing_separator {
+        for found in &mut paths {
+            if !found.path.as_os_str().as_encoded_bytes().last().is_some_and(|&b| is_separator(char::from(b))) {
+                let mut path = mem::take(&mut found.path).into_os_string();
+                path.push(separator.encode_utf8(&mut [0; 4]));
+                found.path = path.into();
+            }
+        }
+    }
+    paths
+}
+
+/// A component of the pattern, its index, and how it's matched
+type Compiled<'a, P> = (usize, Component<'a>, Part<P>);
+
+/// State of one `walk()`
+struct Walker<'a, M: 'a> {
+    matcher: &'a M,
+    config: &'a Config,
+    cache: &'a DirCache,
+    base: Option<&'a Path>,
+    /// Number of leading components known to be directories
+    verified: usize,
+    prefix_metadata: Option<VfsMetadata>,
+    trailing_separator: Option<char>,
+    exclusions: Exclusions,
+    /// Matches so far, for `Config::max_results`
+    found: AtomicUsize,
+}
+
+impl<'a, M: Matcher> Walker<'a, M> {
+    /// Whether there are more matches than allowed, so nothing more is listed
+    fn is_over_limit(&self) -> bool {
+        self.config.max_results.is_some_and(|limit| self.found.load(Ordering::Relaxed) > limit)
+    }
+
+    /// Paths matching the rest of the pattern below `found`
+    fn below(&self, mut found: Found, components: &[Compiled<M::Pattern>]) -> Vec<Found> {
+        let (&(index, component, ref part), rest) = match components.split_first() {
+            Some(first) => first,
+            None => {
+                // excluded paths don't count against the limit
+                if !self.exclusions.is_empty() && self.exclusions.is_excluded(&found.path) {
+                    return Vec::new();
+                }
+                self.found.fetch_add(1, Ordering::Relaxed);
+                return vec![found];
+            },
+        };
+        if self.is_over_limit() {
+            return Vec::new();
+        }
+        let (config, cache) = (self.config, self.cache);
+        let is_last = rest.is_empty();
+        let files_match = is_last && self.trailing_separator.is_none() && config.entry_types != EntryTypes::Directories;
+        let dirs_match = !is_last || config.entry_types != EntryTypes::Files;
+        match *part {
+            Part::Pattern(ref pattern) => {
+                let mut entries = self.matcher.list(&found.path, pattern, cache);
+                entries.retain(|entry| (if entry.is_dir { dirs_match } else { files_match }) &&
+                    visible(config.hidden_files, entry.hidden, is_last) && visible(config.system_files, entry.system, is_last) &&
+                    !cache.is_ignored(&found.path, &entry.name, entry.is_dir, config));
+                sort_by_name(&mut entries, config.sort_order, |entry| &entry.name);
+                let children = entries.into_iter().map(|entry| Found {
+                    path: found.path.join(entry.name),
+                    linked: found.linked || entry.is_link,
+                    metadata: entry.metadata,
+                }).collect();
+                self.flat_map_dirs(children, |child| self.below(child, rest))
+            },
+            Part::Recursive if !dirs_match => Vec::new(),
+            Part::Recursive => {
+                // canonical paths of the directories being traversed, to detect links pointing back at them
+                let ancestors: Vec<_> = if config.follow_links {
+                    cache.fs.canonicalize(&found.path).into_iter().collect()
+                } else {
+                    Vec::new()
+                };
+                // the starting directory itself isn't a match of `**`
+                let mut paths = if !is_last || !(found.path.as_os_str().is_empty() || Some(&*found.path) == self.base) {
+                    self.below(found.clone(), rest)
+                } else {
+                    Vec::new()
+                };
+                paths.extend(self.below_subdirs(&found, &ancestors, config.max_depth, rest));
+                paths
+            },
+            Part::Literal => {
+                found.path.push(component.as_os_str());
+                found.metadata = None;
+                let exists = match component {
+                    // roots, like `C:\` or `\\server\share\`, aren't listed, so they don't need to be checked
+                    Component::Prefix(_) | Component::RootDir if !is_last => true,
+                    _ if index + 1 == self.verified => {
+                        found.metadata = self.prefix_metadata;
+                        true
+                    },
+                    _ if index < self.verified => true,
+                    _ if is_last => {
+                        found.metadata = cache.metadata(&found.path);
+                        if found.metadata.is_some_and(|m| m.is_dir) { dirs_match } else { files_match && cache.exists(&found.path) }
+                    },
+                    _ => {
+                        found.metadata = cache.metadata(&found.path);
+                        found.metadata.is_some_and(|m| m.is_dir)
+                    },
+                };
+                if exists { self.below(found, rest) } else { Vec::new() }
+            },
+        }
+    }
+
+    /// Paths matching the rest of the pattern in all directories below `dir`, depth-first in the configured order,
+    /// at most `depth` levels deep.
+    ///
+    /// With `follow_links`, links to directories are traversed too, unless they point to one of the `ancestors`.
+    fn below_subdirs(&self, dir: &Found, ancestors: &[PathBuf], depth: Option<usize>, rest: &[Compiled<M::Pattern>]) -> Vec<Found> {
+        if depth == Some(0) || self.is_over_limit() {
+            return Vec::new();
+        }
+        let (config, cache) = (self.config, self.cache);
+        let mut names = cache.subdir_names(&dir.path, config);
+        sort_by_name(&mut names, config.sort_order, |(name, _, _)| name);
+        let children = names.into_iter().map(|(name, is_link, metadata)| (Found {
+            path: dir.path.join(&name),
+            linked: dir.linked || is_link,
+            metadata,
+        }, name, is_link)).collect();
+        self.flat_map_dirs(children, |(child, name, is_link)| {
+            let mut ancestors = ancestors.to_vec();
+            if config.follow_links {
+                let canonical = match ancestors.last() {
+                    Some(parent) if !is_link => parent.join(name),
+                    _ => match cache.fs.canonicalize(&child.path) {
+                        Ok(canonical) => canonical,
+                        Err(_) => return Vec::new(),
+                    },
+                };
+                if ancestors.contains(&canonical) {
+                    return Vec::new();
+                }
+                ancestors.push(canonical);
+            }
+            let mut paths = self.below(child.clone(), rest);
+            paths.extend(self.below_subdirs(&child, &ancestors, depth.map(|depth| depth - 1), rest));
+            paths
+        })
+    }
+
+    /// Maps every directory to paths found in it, keeping their order.
+    ///
+    /// Directories are independent, so with the `rayon` feature they can be listed in parallel,
+    /// in the pool of `Config::parallel_walk()` if there is one.
+    /// Directories left once there are too many matches aren't listed.
+    fn flat_map_dirs<T, F>(&self, dirs: Vec<T>, f: F) -> Vec<Found> where T: Send, F: Fn(T) -> Vec<Found> + Sync + Send {
+        let f = |dir| if self.is_over_limit() { Vec::new() } else { f(dir) };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            if let Some(ref pool) = self.config.walk_pool {
+                return pool.install(|| dirs.into_par_iter().flat_map_iter(f).collect());
+            }
+            if self.config.parallel {
+                return dirs.into_par_iter().flat_map_iter(f).collect();
+            }
+        }
+        dirs.into_iter().flat_map(f).collect()
+    }
+}
+
+/// `Config::base_dir`, if the path is relative to it. Paths with a root or a drive aren't.
+fn base_dir<'a>(path: &Path, config: &'a Config) -> Option<&'a Path> {
+    match config.base_dir {
+        Some(ref base) if !path.has_root() && !matches!(path.components().next(), Some(Component::Prefix(_))) => Some(base),
+        _ => None,
+    }
+}
+
+/// The path joined with `Config::base_dir`, if it's relative to it
+pub(crate) fn in_base_dir(path: &Path, config: &Config) -> PathBuf {
+    match base_dir(path, config) {
+        Some(base) => base.join(path),
+        None => path.to_owned(),
+    }
+}
+
+/// Whether a wildcard match with (or without) the attribute passes the filter.
+///
+/// `Only` applies to the final matches, not directories on the way to them.
+fn visible(filter: AttributeFilter, has_attribute: bool, is_last: bool) -> bool {
+    match filter {
+        AttributeFilter::Include => true,
+        AttributeFilter::Exclude => !has_attribute,
+        AttributeFilter::Only => has_attribute || !is_last,
+    }
+}
+
+/// Splits the pattern before its first component with wildcards.
+///
+/// The prefix includes the separator. `None` if the pattern has no wildcards at all.
+fn literal_prefix<'a, M: Matcher>(pattern: &'a OsStr, matcher: &M) -> Option<(&'a OsStr, &'a OsStr)> {
+    let is_separator = |b: u8| b == b'/' || (cfg!(windows) && b == b'\\');
+    // split as bytes, so that names that aren't valid Unicode keep their exact spelling.
+    // It's safe, since they're split only next to ASCII characters.
+    let bytes = pattern.as_encoded_bytes();
+    let os_str = |bytes: &'a [u8]| unsafe { OsStr::from_encoded_bytes_unchecked(bytes) };
+    // the root, like `\\?\C:\` or `\\server\share\`, is never a pattern, even if it has a `?`
+    let mut start = match Path::new(pattern).components().next() {
+        Some(Component::Prefix(prefix)) => prefix.as_os_str().len(),
+        _ => 0,
+    };
+    if bytes.get(start).is_some_and(|&b| is_separator(b)) {
+        start += 1;
+    }
+    let ends = (start..bytes.len()).filter(|&i| is_separator(bytes[i])).chain(Some(bytes.len()));
+    for end in ends {
+        match matcher.compile(os_str(&bytes[start..end])) {
+            Part::Literal => start = end + 1,
+            _ => return Some((os_str(&bytes[..start]), os_str(&bytes[start..]))),
+        }
+    }
+    None
+}
+
+/// Paths of the results, for tests
+#[cfg(test)]
+pub(crate) fn paths(found: Vec<Found>) -> Vec<PathBuf> {
+    found.into_iter().map(|found| found.path).collect()
+}
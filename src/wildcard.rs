@@ -0,0 +1,311 @@
+//! Matching of `*`, `?` and `[a-z]` in a single path component, used unless the `glob` or `globset` feature is enabled,
+//! and by them for components that aren't valid Unicode.
+//!
+//! Names are compared as UTF-16, so unpaired surrogates in file names can be matched too.
+use std::ffi::OsStr;
+#[cfg(windows)]
+use std::os::windows::ffi::OsStrExt;
+#[cfg(not(windows))]
+use parser::LossyOsStrExt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(u32),
+    /// `?`
+    Any,
+    /// `*`
+    Many,
+    /// `[a-z]` ranges, inclusive, or with `[!a-z]` anything except them
+    Class { negated: bool, ranges: Vec<(u32, u32)> },
+    /// Extended glob `?(a|b)`, `*(a|b)`, `+(a|b)`, `@(a|b)` or `!(a|b)`, where `op` is the character before the parenthesis
+    Group { op: char, alternatives: Vec<Vec<Token>> },
+}
+
+/// A compiled pattern of a single path component
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pattern {
+    tokens: Vec<Token>,
+    /// Patterns without groups are matched without recursion
+    has_groups: bool,
+}
+
+/// Code points, with unpaired surrogates kept as they are
+fn code_points(s: &OsStr) -> Vec<u32> {
+    ::std::char::decode_utf16(s.encode_wide())
+        .map(|c| c.map(u32::from).unwrap_or_else(|err| u32::from(err.unpaired_surrogate())))
+        .collect()
+}
+
+/// Whether the component has an extended glob group, like `+(a|b)`. Quoted parentheses are escaped, so they don't start one.
+pub(crate) fn has_groups(component: &OsStr) -> bool {
+    component.as_encoded_bytes().windows(2).any(|pair| b"?*+@!".contains(&pair[0]) && pair[1] == b'(')
+}
+
+struct Parser {
+    chars: Vec<u32>,
+    i: usize,
+    extglob: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).and_then(|&c| char_of(c))
+    }
+
+    /// Alternatives separated by `|` up to the closing `)` if `in_group`, or the end of the pattern
+    fn alternatives(&mut self, in_group: bool) -> Option<Vec<Vec<Token>>> {
+        let mut alternatives = Vec::new();
+        let mut tokens = Vec::new();
+        while let Some(&c) = self.chars.get(self.i) {
+            self.i += 1;
+            let token = match char_of(c) {
+                Some(op @ ('?' | '*' | '+' | '@' | '!')) if self.extglob && self.peek() == Some('(') => {
+                    self.i += 1;
+                    Token::Group { op, alternatives: self.alternatives(true)? }
+                },
+                Some('|') if in_group => {
+                    alternatives.push(::std::mem::take(&mut tokens));
+                    continue;
+                },
+                Some(')') if in_group => {
+                    alternatives.push(tokens);
+                    return Some(alternatives);
+                },
+                Some('?') => Token::Any,
+                Some('*') => {
+                    if tokens.last() == Some(&Token::Many) {
+                        return None;
+                    }
+                    Token::Many
+                },
+                Some('[') => self.class()?,
+                _ => Token::Char(c),
+            };
+            tokens.push(token);
+        }
+        // an unclosed group is invalid, like an unclosed class
+        if in_group {
+            return None;
+        }
+        alternatives.push(tokens);
+        Some(alternatives)
+    }
+
+    /// The rest of `[a-z]` after the `[`
+    fn class(&mut self) -> Option<Token> {
+        let chars = &self.chars;
+        let mut i = self.i;
+        let negated = chars.get(i).and_then(|&c| char_of(c)) == Some('!');
+        if negated {
+            i += 1;
+        }
+        let mut ranges = Vec::new();
+        // `]` right after the opening is literal
+        let start = i;
+        loop {
+            let &c = chars.get(i)?;
+            if char_of(c) == Some(']') && i > start {
+                i += 1;
+                break;
+            }
+            if chars.get(i + 1).and_then(|&c| char_of(c)) == Some('-') && chars.get(i + 2).is_some_and(|&end| char_of(end) != Some(']')) {
+                ranges.push((c, chars[i + 2]));
+                i += 3;
+            } else {
+                ranges.push((c, c));
+                i += 1;
+            }
+        }
+        self.i = i;
+        Some(Token::Class { negated, ranges })
+    }
+}
+
+impl Pattern {
+    /// Like in the `glob` crate, an unclosed `[` or `**` that isn't the whole component make the pattern invalid.
+    ///
+    /// With `extglob`, groups like `+(a|b)` are recognized too, and an unclosed group makes it invalid as well.
+    pub fn new(component: &OsStr, extglob: bool) -> Option<Self> {
+        let mut parser = Parser { chars: code_points(component), i: 0, extglob };
+        let tokens = parser.alternatives(false)?.pop()?;
+        let has_groups = tokens.iter().any(|token| matches!(*token, Token::Group { .. }));
+        Some(Self { tokens, has_groups })
+    }
+
+    pub fn matches(&self, name: &OsStr) -> bool {
+        let name = code_points(name);
+        if self.has_groups {
+            return match_ends(&self.tokens, &name, at(0, name.len()))[name.len()];
+        }
+        // backtracks only to the last `*`, which is enough, since any later `*` can match more instead
+        let (mut t, mut n) = (0, 0);
+        let mut star: Option<(usize, usize)> = None;
+        while n < name.len() {
+            let matched = match self.tokens.get(t) {
+                Some(&Token::Many) => {
+                    star = Some((t, n));
+                    t += 1;
+                    continue;
+                },
+                Some(token) => matches_one(token, name[n]),
+                None => false,
+            };
+            if matched {
+                t += 1;
+                n += 1;
+            } else if let Some((star_t, star_n)) = star {
+                t = star_t + 1;
+                n = star_n + 1;
+                star = Some((star_t, star_n + 1));
+            } else {
+                return false;
+            }
+        }
+        self.tokens[t..].iter().all(|token| *token == Token::Many)
+    }
+}
+
+/// Whether a token matching a single character matches `c`
+fn matches_one(token: &Token, c: u32) -> bool {
+    match *token {
+        Token::Any => true,
+        Token::Char(expected) => c == expected,
+        Token::Class { negated, ref ranges } => negated != ranges.iter().any(|&(from, to)| from <= c && c <= to),
+        Token::Many | Token::Group { .. } => false,
+    }
+}
+
+/// Positions in `name` where a match of `tokens` can end, if it starts at any of the `starts`.
+///
+/// Every position is reached at most once per token, so the cost doesn't grow with the number of ways to get there,
+/// which backtracking through nested `+(a|aa)` would try one by one.
+fn match_ends(tokens: &[Token], name: &[u32], starts: Vec<bool>) -> Vec<bool> {
+    let mut reached = starts;
+    for token in tokens {
+        let mut next = vec![false; name.len() + 1];
+        match *token {
+            Token::Many => if let Some(first) = reached.iter().position(|&r| r) {
+                next[first..].iter_mut().for_each(|r| *r = true);
+            },
+            Token::Group { op, ref alternatives } => for start in positions(&reached) {
+                for end in positions(&group_ends(op, alternatives, name, start)) {
+                    next[end] = true;
+                }
+            },
+            _ => for n in positions(&reached) {
+                if n < name.len() && matches_one(token, name[n]) {
+                    next[n + 1] = true;
+                }
+            },
+        }
+        if !next.contains(&true) {
+            return next;
+        }
+        reached = next;
+    }
+    reached
+}
+
+/// Positions in `name` where a match of the group starting at `start` can end
+fn group_ends(op: char, alternatives: &[Vec<Token>], name: &[u32], start: usize) -> Vec<bool> {
+    let any = |start: usize| {
+        let mut ends = vec![false; name.len() + 1];
+        for tokens in alternatives {
+            for end in positions(&match_ends(tokens, name, at(start, name.len()))) {
+                ends[end] = true;
+            }
+        }
+        ends
+    };
+    let mut ends = any(start);
+    match op {
+        '?' => ends[start] = true,
+        '!' => for (end, matched) in ends.iter_mut().enumerate() {
+            *matched = end >= start && !*matched;
+        },
+        // `*` and `+`, one or more alternatives in a row, continued once from each new end
+        '*' | '+' => {
+            let mut pending = positions(&ends);
+            while let Some(from) = pending.pop() {
+                for end in positions(&any(from)) {
+                    if !ends[end] {
+                        ends[end] = true;
+                        pending.push(end);
+                    }
+                }
+            }
+            if op == '*' {
+                ends[start] = true;
+            }
+        },
+        _ => {},
+    }
+    ends
+}
+
+/// Only the position `n` of a name with `len` characters
+fn at(n: usize, len: usize) -> Vec<bool> {
+    let mut positions = vec![false; len + 1];
+    positions[n] = true;
+    positions
+}
+
+fn positions(set: &[bool]) -> Vec<usize> {
+    set.iter().enumerate().filter(|&(_, &r)| r).map(|(n, _)| n).collect()
+}
+
+fn char_of(c: u32) -> Option<char> {
+    ::std::char::from_u32(c)
+}
+
+#[test]
+fn matches_wildcards() {
+    let matches = |pattern: &str, name: &str| Pattern::new(OsStr::new(pattern), false).unwrap().matches(OsStr::new(name));
+    assert!(matches("*.rs", "lib.rs"));
+    assert!(matches("*.rs", ".rs"));
+    assert!(!matches("*.rs", "lib.rs.bak"));
+    assert!(matches("a*b*c", "aXbYbZc"));
+    assert!(matches("l?b.*", "lib.rs"));
+    assert!(!matches("l?b.*", "lb.rs"));
+    assert!(matches("[a-c]?[!0-9]", "bxy"));
+    assert!(!matches("[a-c]?[!0-9]", "bx1"));
+    assert!(matches("[]]x", "]x"));
+    assert!(matches("[!]]x", "ax"));
+    assert!(matches("[a-]", "-"));
+    assert!(matches("*", ""));
+    assert!(matches("été*", "été.txt"));
+    assert!(Pattern::new(OsStr::new("[ab"), false).is_none());
+    assert!(Pattern::new(OsStr::new("a**"), false).is_none());
+}
+
+#[test]
+fn matches_extglob() {
+    let matches = |pattern: &str, name: &str| Pattern::new(OsStr::new(pattern), true).unwrap().matches(OsStr::new(name));
+    assert!(matches("@(a|b).rs", "a.rs"));
+    assert!(!matches("@(a|b).rs", "ab.rs"));
+    assert!(matches("+(a|b).rs", "abba.rs"));
+    assert!(!matches("+(a|b).rs", ".rs"));
+    assert!(matches("*(a|b).rs", ".rs"));
+    assert!(matches("x?(y).rs", "x.rs"));
+    assert!(matches("x?(y).rs", "xy.rs"));
+    assert!(!matches("x?(y).rs", "xyy.rs"));
+    assert!(matches("!(*.rs)", "lib.txt"));
+    assert!(!matches("!(*.rs)", "lib.rs"));
+    assert!(matches("*.@(r[s]|t?t)", "lib.txt"));
+    assert!(matches("+(a|@(b|c))", "acb"));
+    // quoted parentheses and bars are escaped as classes
+    assert!(matches("@([(]a[|]b[)])", "(a|b)"));
+    assert!(Pattern::new(OsStr::new("@(a|b"), true).is_none());
+    assert!(Pattern::new(OsStr::new("@(a|b)"), false).unwrap().matches(OsStr::new("@(a|b)")));
+    assert!(has_groups(OsStr::new("x+(a)")) && !has_groups(OsStr::new("x[(]a)")));
+}
+
+#[test]
+fn matches_extglob_in_polynomial_time() {
+    let matches = |pattern: &str, name: &str| Pattern::new(OsStr::new(pattern), true).unwrap().matches(OsStr::new(name));
+    let long = "a".repeat(200);
+    assert!(matches("+(a|aa)", &long));
+    assert!(!matches("+(a|aa)", &format!("{}b", long)));
+    assert!(!matches("*(+(a|aa)|a*)b", &long));
+    assert!(matches("!(+(a|aa))", &format!("{}b", long)));
+}